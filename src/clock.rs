@@ -0,0 +1,27 @@
+use chrono::NaiveDateTime;
+
+// 时钟：提供“当前时间”，便于测试时注入固定时间
+pub trait Clock: Send {
+    // 当前本地时间
+    fn now(&self) -> NaiveDateTime;
+}
+
+// 系统时钟，读取本机本地时间
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> NaiveDateTime {
+        chrono::Local::now().naive_local()
+    }
+}
+
+// 固定时钟，总是返回构造时给定的时间，用于测试与回放
+#[derive(Debug, Clone, Copy)]
+pub struct FixedClock(pub NaiveDateTime);
+
+impl Clock for FixedClock {
+    fn now(&self) -> NaiveDateTime {
+        self.0
+    }
+}
@@ -1,10 +1,27 @@
+use crate::error::CokeError;
+use crate::models::JournalMode;
 use rusqlite::Connection;
 
-// 初始化数据库
-pub fn initialize_db(conn: &Connection) -> Result<(), rusqlite::Error> {
+// 初始化数据库，按指定模式设置日志模式
+pub fn initialize_db(conn: &Connection, journal_mode: JournalMode) -> Result<(), CokeError> {
+    // SQLite 无法切换时（如文件系统不支持 WAL）保持原模式，需检查实际结果；
+    // 内存数据库总是返回 memory
+    let actual: String = conn.query_row(
+        &format!("PRAGMA journal_mode = {}", journal_mode.as_str()),
+        [],
+        |row| row.get(0),
+    )?;
+    let actual = actual.to_lowercase();
+    if actual != journal_mode.as_str() && actual != "memory" {
+        return Err(CokeError::Conflict(format!(
+            "无法将日志模式设置为{}，当前为{}",
+            journal_mode.as_str(),
+            actual
+        )));
+    }
+
     conn.execute_batch(
-        "PRAGMA journal_mode = WAL;
-         PRAGMA synchronous = NORMAL;
+        "PRAGMA synchronous = NORMAL;
          PRAGMA foreign_keys = ON;
          
          CREATE TABLE IF NOT EXISTS temperature_records (
@@ -13,7 +30,7 @@ pub fn initialize_db(conn: &Connection) -> Result<(), rusqlite::Error> {
              time TEXT NOT NULL,
              machine_side REAL NOT NULL,
              coke_side REAL NOT NULL,
-             UNIQUE(coke_oven, time)
+             probe TEXT
          );
          
          CREATE TABLE IF NOT EXISTS operation_records (
@@ -32,13 +49,297 @@ pub fn initialize_db(conn: &Connection) -> Result<(), rusqlite::Error> {
              loading_time TEXT NOT NULL,
              push_time TEXT NOT NULL,
              duration_hhmm TEXT NOT NULL, 
+             duration_minutes INTEGER,
              avg_temp_machine REAL,
              avg_temp_coke REAL,
+             loading_op_id INTEGER REFERENCES operation_records(id),
+             push_op_id INTEGER REFERENCES operation_records(id),
+             low_confidence INTEGER NOT NULL DEFAULT 0,
              UNIQUE(coke_oven, chamber, push_time)
          );
+
+         -- 结焦周期备注，按（焦炉, 炭化室, 推焦时间）关联周期，周期重建后仍然保留
+         CREATE TABLE IF NOT EXISTS cycle_notes (
+             id INTEGER PRIMARY KEY,
+             coke_oven INTEGER NOT NULL,
+             chamber TEXT NOT NULL,
+             push_time TEXT NOT NULL,
+             note TEXT NOT NULL
+         );
+
+         -- 系统元数据（如增量计算水位），键值对
+         CREATE TABLE IF NOT EXISTS metadata (
+             key TEXT PRIMARY KEY,
+             value TEXT NOT NULL
+         );
          
          CREATE INDEX IF NOT EXISTS idx_temp_oven_time ON temperature_records(coke_oven, time);
          CREATE INDEX IF NOT EXISTS idx_ops_oven_chamber_time ON operation_records(coke_oven, chamber, time);
-         CREATE INDEX IF NOT EXISTS idx_cycles_oven_chamber ON coking_cycles(coke_oven, chamber);"
+         CREATE INDEX IF NOT EXISTS idx_cycles_oven_chamber ON coking_cycles(coke_oven, chamber);
+
+         CREATE TABLE IF NOT EXISTS audit_log (
+             id INTEGER PRIMARY KEY,
+             action TEXT NOT NULL,
+             table_name TEXT NOT NULL,
+             record_key TEXT NOT NULL,
+             time TEXT NOT NULL,
+             user TEXT
+         );
+
+         CREATE INDEX IF NOT EXISTS idx_audit_time ON audit_log(time);
+
+         -- 写入审计日志的操作人，只有一行（id = 1），由 set_current_user 设置
+         CREATE TABLE IF NOT EXISTS audit_user (
+             id INTEGER PRIMARY KEY CHECK (id = 1),
+             name TEXT
+         );"
+    )?;
+
+    migrate(conn)?;
+    install_audit_triggers(conn)?;
+    Ok(())
+}
+
+// 需要审计的数据表
+const AUDITED_TABLES: [&str; 3] = ["temperature_records", "operation_records", "coking_cycles"];
+
+// 在数据库中安装审计触发器：数据表的每次增删改都写入 audit_log，操作人取自 audit_user。
+//
+// 触发器随数据库保存，其他连接（包括外部工具）的修改同样会被记录；
+// 审计时间以 UTC 写入，读取时由 audit_trail 换算为存储时间
+fn install_audit_triggers(conn: &Connection) -> Result<(), rusqlite::Error> {
+    // 审计日志只允许追加
+    let mut sql = String::from(
+        "CREATE TRIGGER IF NOT EXISTS audit_log_no_update BEFORE UPDATE ON audit_log
+         BEGIN SELECT RAISE(ABORT, '审计日志不允许修改'); END;
+         CREATE TRIGGER IF NOT EXISTS audit_log_no_delete BEFORE DELETE ON audit_log
+         BEGIN SELECT RAISE(ABORT, '审计日志不允许删除'); END;",
+    );
+    for table in AUDITED_TABLES {
+        for (action, row) in [("INSERT", "NEW"), ("UPDATE", "NEW"), ("DELETE", "OLD")] {
+            sql.push_str(&format!(
+                "CREATE TRIGGER IF NOT EXISTS audit_{table}_{action}
+                 AFTER {action} ON {table}
+                 BEGIN
+                     INSERT INTO audit_log (action, table_name, record_key, time, user)
+                     VALUES ('{action}', '{table}', CAST({row}.id AS TEXT), datetime('now'),
+                             (SELECT name FROM audit_user WHERE id = 1));
+                 END;",
+            ));
+        }
+    }
+    conn.execute_batch(&sql)
+}
+
+// 升级旧版本数据库结构
+fn migrate(conn: &Connection) -> Result<(), rusqlite::Error> {
+    // 结焦周期增加分钟数字段，并由 duration_hhmm 回填
+    if !column_exists(conn, "coking_cycles", "duration_minutes")? {
+        conn.execute_batch(
+            "ALTER TABLE coking_cycles ADD COLUMN duration_minutes INTEGER;
+             UPDATE coking_cycles SET duration_minutes =
+                 CAST(substr(duration_hhmm, 1, instr(duration_hhmm, ':') - 1) AS INTEGER) * 60
+                 + CAST(substr(duration_hhmm, instr(duration_hhmm, ':') + 1) AS INTEGER);",
+        )?;
+    }
+
+    // 版本 1：将已有时间字段统一为标准格式，冲突或无法解析的行保持原样
+    let version: i32 = conn.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+    if version < 1 {
+        let tx = conn.unchecked_transaction()?;
+        let (_, issues) = normalize_time_columns(&tx)?;
+        for issue in issues {
+            log::warn!("时间标准化跳过: {}", issue);
+        }
+        tx.execute_batch("PRAGMA user_version = 1")?;
+        tx.commit()?;
+    }
+
+    // 版本 2：审计时间由本地时间改为 UTC；暂时移除禁止修改的触发器，
+    // 由 install_audit_triggers 重新创建
+    if version < 2 {
+        let tx = conn.unchecked_transaction()?;
+        tx.execute_batch(
+            "DROP TRIGGER IF EXISTS audit_log_no_update;
+             UPDATE audit_log SET time = datetime(time, 'utc');
+             PRAGMA user_version = 2;",
+        )?;
+        tx.commit()?;
+    }
+
+    // 结焦周期增加来源操作外键，按（焦炉, 炭化室, 时间）回填；需在时间标准化之后执行
+    if !column_exists(conn, "coking_cycles", "loading_op_id")? {
+        conn.execute_batch(
+            "ALTER TABLE coking_cycles ADD COLUMN loading_op_id INTEGER REFERENCES operation_records(id);
+             ALTER TABLE coking_cycles ADD COLUMN push_op_id INTEGER REFERENCES operation_records(id);
+             UPDATE coking_cycles SET
+                 loading_op_id = (
+                     SELECT id FROM operation_records o
+                     WHERE o.coke_oven = coking_cycles.coke_oven
+                       AND o.chamber = coking_cycles.chamber
+                       AND o.operation_type = 'LOAD'
+                       AND o.time = coking_cycles.loading_time
+                 ),
+                 push_op_id = (
+                     SELECT id FROM operation_records o
+                     WHERE o.coke_oven = coking_cycles.coke_oven
+                       AND o.chamber = coking_cycles.chamber
+                       AND o.operation_type = 'PUSH'
+                       AND o.time = coking_cycles.push_time
+                 );",
+        )?;
+    }
+
+    // 结焦周期增加低置信度标记，已有周期视为正常
+    if !column_exists(conn, "coking_cycles", "low_confidence")? {
+        conn.execute_batch(
+            "ALTER TABLE coking_cycles ADD COLUMN low_confidence INTEGER NOT NULL DEFAULT 0;",
+        )?;
+    }
+
+    // 温度记录增加探头字段：重建表以将唯一约束扩展为 (焦炉, 时间, 探头)
+    if !column_exists(conn, "temperature_records", "probe")? {
+        let tx = conn.unchecked_transaction()?;
+        tx.execute_batch(
+            "CREATE TABLE temperature_records_new (
+                 id INTEGER PRIMARY KEY,
+                 coke_oven INTEGER NOT NULL,
+                 time TEXT NOT NULL,
+                 machine_side REAL NOT NULL,
+                 coke_side REAL NOT NULL,
+                 probe TEXT
+             );
+             INSERT INTO temperature_records_new (id, coke_oven, time, machine_side, coke_side)
+                 SELECT id, coke_oven, time, machine_side, coke_side FROM temperature_records;
+             DROP TABLE temperature_records;
+             ALTER TABLE temperature_records_new RENAME TO temperature_records;
+             CREATE INDEX IF NOT EXISTS idx_temp_oven_time ON temperature_records(coke_oven, time);",
+        )?;
+        tx.commit()?;
+    }
+
+    // 结焦周期备注由周期 id 外键改为按（焦炉, 炭化室, 推焦时间）关联，重建周期时不再丢失；
+    // 已有备注按当前周期回填
+    if column_exists(conn, "cycle_notes", "cycle_id")? {
+        let tx = conn.unchecked_transaction()?;
+        tx.execute_batch(
+            "CREATE TABLE cycle_notes_new (
+                 id INTEGER PRIMARY KEY,
+                 coke_oven INTEGER NOT NULL,
+                 chamber TEXT NOT NULL,
+                 push_time TEXT NOT NULL,
+                 note TEXT NOT NULL
+             );
+             INSERT INTO cycle_notes_new (id, coke_oven, chamber, push_time, note)
+                 SELECT n.id, c.coke_oven, c.chamber, c.push_time, n.note
+                 FROM cycle_notes n JOIN coking_cycles c ON c.id = n.cycle_id;
+             DROP TABLE cycle_notes;
+             ALTER TABLE cycle_notes_new RENAME TO cycle_notes;",
+        )?;
+        tx.commit()?;
+    }
+    conn.execute_batch(
+        "CREATE INDEX IF NOT EXISTS idx_cycle_notes_cycle
+             ON cycle_notes(coke_oven, chamber, push_time);",
+    )?;
+
+    // 未指定探头视为同一探头，保持原有的 (焦炉, 时间) 唯一性
+    conn.execute_batch(
+        "CREATE UNIQUE INDEX IF NOT EXISTS idx_temp_oven_time_probe
+             ON temperature_records(coke_oven, time, COALESCE(probe, ''));",
+    )?;
+
+    Ok(())
+}
+
+// 将所有表中的时间字段改写为标准格式，返回更新的行数及跳过的问题行
+//
+// 无法解析或改写后违反唯一约束的行保持原样，由调用方决定如何处理
+pub fn normalize_time_columns(conn: &Connection) -> Result<(usize, Vec<String>), rusqlite::Error> {
+    const TIME_COLUMNS: [(&str, &[&str]); 4] = [
+        ("temperature_records", &["time"]),
+        ("operation_records", &["time"]),
+        ("coking_cycles", &["loading_time", "push_time"]),
+        ("cycle_notes", &["push_time"]),
+    ];
+
+    let mut updated = 0;
+    let mut issues = Vec::new();
+
+    for (table, columns) in TIME_COLUMNS {
+        let rows: Vec<(i64, Vec<String>)> = {
+            let mut stmt =
+                conn.prepare(&format!("SELECT id, {} FROM {}", columns.join(", "), table))?;
+            let rows = stmt.query_map([], |row| {
+                let values = (0..columns.len())
+                    .map(|i| row.get::<_, String>(i + 1))
+                    .collect::<Result<Vec<_>, _>>()?;
+                Ok((row.get(0)?, values))
+            })?;
+            rows.collect::<Result<Vec<_>, _>>()?
+        };
+
+        let assignments = columns
+            .iter()
+            .enumerate()
+            .map(|(i, c)| format!("{} = ?{}", c, i + 1))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let update_sql = format!(
+            "UPDATE {} SET {} WHERE id = ?{}",
+            table,
+            assignments,
+            columns.len() + 1
+        );
+
+        for (id, raws) in rows {
+            let normalized = match raws
+                .iter()
+                .map(|raw| crate::models::normalize_time(raw))
+                .collect::<Result<Vec<_>, _>>()
+            {
+                Ok(normalized) => normalized,
+                Err(e) => {
+                    issues.push(format!("{}#{} ({}): {}", table, id, raws.join(", "), e));
+                    continue;
+                }
+            };
+            if normalized == raws {
+                continue;
+            }
+
+            let mut values: Vec<&dyn rusqlite::ToSql> = normalized
+                .iter()
+                .map(|v| v as &dyn rusqlite::ToSql)
+                .collect();
+            values.push(&id);
+
+            match conn.execute(&update_sql, values.as_slice()) {
+                Ok(n) => updated += n,
+                Err(rusqlite::Error::SqliteFailure(err, _))
+                    if err.code == rusqlite::ErrorCode::ConstraintViolation =>
+                {
+                    issues.push(format!(
+                        "{}#{} ({}): 与已有记录冲突",
+                        table,
+                        id,
+                        raws.join(", ")
+                    ));
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    Ok((updated, issues))
+}
+
+// 检查表中是否存在指定字段
+fn column_exists(conn: &Connection, table: &str, column: &str) -> Result<bool, rusqlite::Error> {
+    conn.query_row(
+        "SELECT COUNT(*) FROM pragma_table_info(?1) WHERE name = ?2",
+        [table, column],
+        |row| row.get::<_, i64>(0),
     )
+    .map(|count| count > 0)
 }
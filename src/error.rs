@@ -0,0 +1,90 @@
+use std::fmt;
+
+// 焦炉系统错误类型，调用方可按变体区分错误原因
+#[derive(Debug)]
+pub enum CokeError {
+    // 焦炉编号未配置
+    InvalidOven(i32),
+    // 炭化室不属于该焦炉
+    InvalidChamber { oven: i32, chamber: String },
+    // 时间字符串无法解析，保存原始输入
+    InvalidTime(String),
+    // 操作类型不是 LOAD/PUSH
+    InvalidOperationType(String),
+    // 其他参数无效（阈值、步长、目标值、时间先后等）
+    InvalidArgument(String),
+    // 缺少计算所需的记录或配置
+    NoData(String),
+    // 与已有记录冲突或前置条件不满足
+    Conflict(String),
+    // 数据库错误
+    Db(rusqlite::Error),
+    // 文件读写错误
+    Io(std::io::Error),
+}
+
+impl fmt::Display for CokeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CokeError::InvalidOven(oven) => write!(f, "无效焦炉编号: {}", oven),
+            CokeError::InvalidChamber { oven, chamber } => {
+                write!(f, "焦炉{}中无效的炭化室: {}", oven, chamber)
+            }
+            CokeError::InvalidTime(time) => write!(f, "无效时间格式: {}", time),
+            CokeError::InvalidOperationType(op_type) => write!(f, "无效操作类型: {}", op_type),
+            CokeError::InvalidArgument(msg) | CokeError::NoData(msg) | CokeError::Conflict(msg) => {
+                f.write_str(msg)
+            }
+            CokeError::Db(e) => write!(f, "数据库错误: {}", e),
+            CokeError::Io(e) => write!(f, "文件读写错误: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for CokeError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            CokeError::Db(e) => Some(e),
+            CokeError::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<rusqlite::Error> for CokeError {
+    fn from(e: rusqlite::Error) -> Self {
+        CokeError::Db(e)
+    }
+}
+
+impl From<std::io::Error> for CokeError {
+    fn from(e: std::io::Error) -> Self {
+        CokeError::Io(e)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_display() {
+        assert_eq!(CokeError::InvalidOven(4).to_string(), "无效焦炉编号: 4");
+        assert_eq!(
+            CokeError::InvalidChamber {
+                oven: 1,
+                chamber: "10#".to_string()
+            }
+            .to_string(),
+            "焦炉1中无效的炭化室: 10#"
+        );
+        assert_eq!(
+            CokeError::InvalidTime("bad".to_string()).to_string(),
+            "无效时间格式: bad"
+        );
+
+        let db = CokeError::from(rusqlite::Error::InvalidQuery);
+        assert!(matches!(db, CokeError::Db(_)));
+        assert!(std::error::Error::source(&db).is_some());
+    }
+}
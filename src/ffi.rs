@@ -1,20 +1,40 @@
-use libc::{c_char, c_double, c_int};
-use std::ffi::CStr;
+use libc::{c_char, c_double, c_int, c_void};
+use std::cell::RefCell;
 #[cfg(windows)]
 use std::ffi::OsString;
+use std::ffi::{CStr, CString};
+use std::fs::File;
+use std::io::{BufWriter, Write};
 #[cfg(windows)]
 use std::os::windows::ffi::OsStringExt;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::{Mutex, OnceLock};
 
+use crate::error::CokeError;
 use crate::system::CokeOvenSystem;
 
 // 全局系统句柄
 static SYSTEM: OnceLock<Mutex<Option<CokeOvenSystem>>> = OnceLock::new();
 
+// 导出临时文件名序号，避免同一进程内并发导出时冲突
+static TMP_FILE_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+thread_local! {
+    // 当前线程最近一次错误信息，由 get_last_error 返回
+    static LAST_ERROR: RefCell<CString> = RefCell::new(CString::default());
+}
+
 /// 初始化系统（Windows 宽字符版本）
+///
+/// # Safety
+///
+/// db_path 须为空指针，或指向以 0 结尾的 UTF-16 字符串。
 #[cfg(windows)]
 #[no_mangle]
-pub extern "C" fn coke_system_init_wide(db_path: *const u16) -> c_int {
+pub unsafe extern "C" fn coke_system_init_wide(db_path: *const u16) -> c_int {
+    if db_path.is_null() {
+        return -1;
+    }
     let db_path_str = unsafe {
         let mut len = 0;
         let mut ptr = db_path;
@@ -30,8 +50,12 @@ pub extern "C" fn coke_system_init_wide(db_path: *const u16) -> c_int {
 }
 
 /// 兼容性包装函数
+///
+/// # Safety
+///
+/// db_path 须为空指针，或指向以 NUL 结尾的 C 字符串。
 #[no_mangle]
-pub extern "C" fn coke_system_init(db_path: *const c_char) -> c_int {
+pub unsafe extern "C" fn coke_system_init(db_path: *const c_char) -> c_int {
     let db_path_str = match unsafe { c_char_to_string(db_path) } {
         Ok(s) => s,
         Err(_) => return -1,
@@ -40,8 +64,59 @@ pub extern "C" fn coke_system_init(db_path: *const c_char) -> c_int {
 }
 
 /// 记录温度
+///
+/// 返回值：
+/// - 0：成功
+/// - -1：系统未初始化或锁获取失败
+/// - -2：时间字符串为空或不是有效 UTF-8
+/// - 其他负值：见 error_code
+///
+/// # Safety
+///
+/// time 须为空指针，或指向以 NUL 结尾的 C 字符串。
+#[no_mangle]
+pub unsafe extern "C" fn record_temperature(
+    coke_oven: c_int,
+    time: *const c_char,
+    machine_temp: c_double,
+    coke_temp: c_double,
+) -> c_int {
+    unsafe {
+        record_temperature_impl(
+            std::ptr::null_mut(),
+            coke_oven,
+            time,
+            machine_temp,
+            coke_temp,
+        )
+    }
+}
+
+/// 在 coke_system_open 返回的句柄上记录温度
+///
+/// 返回值同 record_temperature，句柄为空时返回 -1
+///
+/// # Safety
+///
+/// handle 须为 coke_system_open 返回且尚未经 coke_system_close 释放的句柄，
+/// 且不能在多个线程间同时使用；time 的要求同 record_temperature。
 #[no_mangle]
-pub extern "C" fn record_temperature(
+pub unsafe extern "C" fn record_temperature_h(
+    handle: *mut c_void,
+    coke_oven: c_int,
+    time: *const c_char,
+    machine_temp: c_double,
+    coke_temp: c_double,
+) -> c_int {
+    if handle.is_null() {
+        return -1;
+    }
+    record_temperature_impl(handle, coke_oven, time, machine_temp, coke_temp)
+}
+
+// 指针参数的要求同 record_temperature_h，handle 为空时访问全局系统
+unsafe fn record_temperature_impl(
+    handle: *mut c_void,
     coke_oven: c_int,
     time: *const c_char,
     machine_temp: c_double,
@@ -52,30 +127,104 @@ pub extern "C" fn record_temperature(
         Err(_) => return -2,
     };
 
-    let result = with_system_mut(|system| {
-        system.record_temperature(coke_oven as i32, &time_str, machine_temp, coke_temp)
+    let result = with_handle_or_system(handle, |system| {
+        system.record_temperature(coke_oven, &time_str, machine_temp, coke_temp)
     });
 
     match result {
         Ok(Ok(())) => 0,
         Ok(Err(e)) => {
-            eprintln!("温度记录错误: {}", e);
-            -3
+            log::warn!("温度记录错误: {}", e);
+            set_last_error(&e.to_string());
+            error_code(&e)
         }
         Err(e) => {
-            eprintln!("系统错误: {}", e);
+            log::error!("系统错误: {}", e);
             -1
         }
     }
 }
 
 /// 记录操作
+///
+/// 返回值：
+/// - 0：成功
+/// - -1：系统未初始化或锁获取失败
+/// - -2 / -3 / -4：炭化室 / 操作类型 / 时间字符串为空或不是有效 UTF-8
+/// - 其他负值：见 error_code
+///
+/// # Safety
+///
+/// chamber、op_type、time 须为空指针，或指向以 NUL 结尾的 C 字符串。
+#[no_mangle]
+pub unsafe extern "C" fn record_operation(
+    coke_oven: c_int,
+    chamber: *const c_char,
+    op_type: *const c_char,
+    time: *const c_char,
+) -> c_int {
+    unsafe { record_operation_ex(coke_oven, chamber, op_type, time, std::ptr::null_mut()) }
+}
+
+/// 记录操作，并通过 out_cycle_created 返回本次推焦是否生成了结焦周期（1 是，0 否）
+///
+/// out_cycle_created 可以为空，此时不写入；返回值同 record_operation
+///
+/// # Safety
+///
+/// chamber、op_type、time 须为空指针，或指向以 NUL 结尾的 C 字符串；
+/// out_cycle_created 须为空指针，或指向可写的 c_int。
+#[no_mangle]
+pub unsafe extern "C" fn record_operation_ex(
+    coke_oven: c_int,
+    chamber: *const c_char,
+    op_type: *const c_char,
+    time: *const c_char,
+    out_cycle_created: *mut c_int,
+) -> c_int {
+    unsafe {
+        record_operation_impl(
+            std::ptr::null_mut(),
+            coke_oven,
+            chamber,
+            op_type,
+            time,
+            out_cycle_created,
+        )
+    }
+}
+
+/// 在 coke_system_open 返回的句柄上记录操作
+///
+/// out_cycle_created 可以为空；返回值同 record_operation_ex，句柄为空时返回 -1
+///
+/// # Safety
+///
+/// handle 须为 coke_system_open 返回且尚未经 coke_system_close 释放的句柄，
+/// 且不能在多个线程间同时使用；其余指针参数的要求同 record_operation_ex。
 #[no_mangle]
-pub extern "C" fn record_operation(
+pub unsafe extern "C" fn record_operation_h(
+    handle: *mut c_void,
+    coke_oven: c_int,
+    chamber: *const c_char,
+    op_type: *const c_char,
+    time: *const c_char,
+    out_cycle_created: *mut c_int,
+) -> c_int {
+    if handle.is_null() {
+        return -1;
+    }
+    record_operation_impl(handle, coke_oven, chamber, op_type, time, out_cycle_created)
+}
+
+// 指针参数的要求同 record_operation_h，handle 为空时访问全局系统
+unsafe fn record_operation_impl(
+    handle: *mut c_void,
     coke_oven: c_int,
     chamber: *const c_char,
     op_type: *const c_char,
     time: *const c_char,
+    out_cycle_created: *mut c_int,
 ) -> c_int {
     let chamber_str = match unsafe { c_char_to_string(chamber) } {
         Ok(s) => s,
@@ -92,29 +241,386 @@ pub extern "C" fn record_operation(
         Err(_) => return -4,
     };
 
+    let result = with_handle_or_system(handle, |system| {
+        system.record_operation(coke_oven, &chamber_str, &op_type_str, &time_str)
+    });
+
+    match result {
+        Ok(Ok(cycle_created)) => {
+            if !out_cycle_created.is_null() {
+                unsafe {
+                    *out_cycle_created = cycle_created as c_int;
+                }
+            }
+            0
+        }
+        Ok(Err(e)) => {
+            log::warn!("操作记录错误: {}", e);
+            set_last_error(&e.to_string());
+            error_code(&e)
+        }
+        Err(e) => {
+            log::error!("系统错误: {}", e);
+            -1
+        }
+    }
+}
+
+/// 获取时间窗口内的平均温度
+///
+/// 返回值：
+/// - 0：成功，结果写入 out_machine / out_coke
+/// - -1：系统未初始化或锁获取失败
+/// - -2：开始时间字符串无效
+/// - -3：结束时间字符串无效
+/// - -4：输出指针为空
+/// - -6：窗口附近没有温度记录
+/// - 其他负值：见 error_code，错误信息可通过 get_last_error 获取
+///
+/// # Safety
+///
+/// start、end 须为空指针，或指向以 NUL 结尾的 C 字符串；
+/// out_machine、out_coke 须为空指针，或指向可写的 c_double。
+#[no_mangle]
+pub unsafe extern "C" fn get_average_temperature(
+    coke_oven: c_int,
+    start: *const c_char,
+    end: *const c_char,
+    out_machine: *mut c_double,
+    out_coke: *mut c_double,
+) -> c_int {
+    let start_str = match unsafe { c_char_to_string(start) } {
+        Ok(s) => s,
+        Err(_) => return -2,
+    };
+
+    let end_str = match unsafe { c_char_to_string(end) } {
+        Ok(s) => s,
+        Err(_) => return -3,
+    };
+
+    if out_machine.is_null() || out_coke.is_null() {
+        return -4;
+    }
+
+    let result =
+        with_system_mut(|system| system.average_temperature(coke_oven, &start_str, &end_str));
+
+    match result {
+        Ok(Ok(Some((machine, coke)))) => {
+            unsafe {
+                *out_machine = machine;
+                *out_coke = coke;
+            }
+            0
+        }
+        Ok(Ok(None)) => -6,
+        Ok(Err(e)) => {
+            log::warn!("平均温度计算错误: {}", e);
+            set_last_error(&e.to_string());
+            error_code(&e)
+        }
+        Err(e) => {
+            log::error!("系统错误: {}", e);
+            -1
+        }
+    }
+}
+
+/// 获取任意时刻的插值温度，超出记录范围时取最近记录
+///
+/// 返回值：
+/// - 0：成功，结果写入 out_machine / out_coke
+/// - 1：焦炉没有任何温度记录，输出不写入
+/// - -1：系统未初始化或锁获取失败
+/// - -2：时间字符串为空或不是有效 UTF-8
+/// - -3：输出指针为空
+/// - 其他负值：见 error_code
+///
+/// # Safety
+///
+/// time 须为空指针，或指向以 NUL 结尾的 C 字符串；
+/// out_machine、out_coke 须为空指针，或指向可写的 c_double。
+#[no_mangle]
+pub unsafe extern "C" fn get_temperature_at(
+    coke_oven: c_int,
+    time: *const c_char,
+    out_machine: *mut c_double,
+    out_coke: *mut c_double,
+) -> c_int {
+    let time_str = match unsafe { c_char_to_string(time) } {
+        Ok(s) => s,
+        Err(_) => return -2,
+    };
+
+    if out_machine.is_null() || out_coke.is_null() {
+        return -3;
+    }
+
+    let result = with_system_mut(|system| system.temperature_at(coke_oven, &time_str));
+
+    match result {
+        Ok(Ok(Some((machine, coke)))) => {
+            unsafe {
+                *out_machine = machine;
+                *out_coke = coke;
+            }
+            0
+        }
+        Ok(Ok(None)) => 1,
+        Ok(Err(e)) => {
+            log::warn!("温度查询错误: {}", e);
+            set_last_error(&e.to_string());
+            error_code(&e)
+        }
+        Err(e) => {
+            log::error!("系统错误: {}", e);
+            -1
+        }
+    }
+}
+
+/// 获取焦炉的炭化室列表，以换行符连接写入调用方提供的缓冲区
+///
+/// 返回值：
+/// - >=0：写入的字节数（不含结尾的 NUL）
+/// - buf 为空时返回所需缓冲区大小（含结尾的 NUL）
+/// - -1：系统未初始化或锁获取失败
+/// - -4：缓冲区过小
+/// - -5：列表过长，长度超出 c_int 范围
+/// - 其他负值：见 error_code，错误信息可通过 get_last_error 获取
+///
+/// # Safety
+///
+/// buf 须为空指针，或指向至少 buf_len 字节的可写缓冲区。
+#[no_mangle]
+pub unsafe extern "C" fn get_chambers(coke_oven: c_int, buf: *mut c_char, buf_len: usize) -> c_int {
+    let result = with_system_mut(|system| system.chambers(coke_oven));
+
+    let joined = match result {
+        Ok(Ok(chambers)) => chambers.join("\n"),
+        Ok(Err(e)) => {
+            log::warn!("炭化室查询错误: {}", e);
+            set_last_error(&e.to_string());
+            return error_code(&e);
+        }
+        Err(e) => {
+            log::error!("系统错误: {}", e);
+            return -1;
+        }
+    };
+
+    let bytes = joined.as_bytes();
+    let Ok(written) = c_int::try_from(bytes.len() + 1).map(|n| n - 1) else {
+        set_last_error("炭化室列表过长");
+        return -5;
+    };
+    if buf.is_null() {
+        return written + 1;
+    }
+    if buf_len < bytes.len() + 1 {
+        return -4;
+    }
+
+    unsafe {
+        std::ptr::copy_nonoverlapping(bytes.as_ptr(), buf as *mut u8, bytes.len());
+        *buf.add(bytes.len()) = 0;
+    }
+    written
+}
+
+/// 获取已配置的焦炉数量，系统未初始化时返回 -1
+#[no_mangle]
+pub extern "C" fn get_oven_count() -> c_int {
+    match with_system_mut(|system| Ok(system.ovens.len())) {
+        Ok(Ok(count)) => count as c_int,
+        Ok(Err(_)) => -1,
+        Err(e) => {
+            log::error!("系统错误: {}", e);
+            -1
+        }
+    }
+}
+
+/// 获取焦炉的炭化室数量，系统未初始化或焦炉编号无效时返回 -1
+#[no_mangle]
+pub extern "C" fn get_chamber_count(coke_oven: c_int) -> c_int {
     let result = with_system_mut(|system| {
-        system.record_operation(coke_oven as i32, &chamber_str, &op_type_str, &time_str)
+        system
+            .ovens
+            .get(&coke_oven)
+            .map(|oven| oven.chambers.len())
+            .ok_or(CokeError::InvalidOven(coke_oven))
     });
 
     match result {
-        Ok(Ok(())) => 0,
+        Ok(Ok(count)) => count as c_int,
         Ok(Err(e)) => {
-            eprintln!("操作记录错误: {}", e);
-            -5
+            log::warn!("炭化室查询错误: {}", e);
+            -1
         }
         Err(e) => {
-            eprintln!("系统错误: {}", e);
+            log::error!("系统错误: {}", e);
             -1
         }
     }
 }
 
+/// 将推焦时间在 [start, end] 范围内的结焦周期导出为 CSV 文件
+///
+/// 失败时可通过 get_last_error 获取错误详情。
+///
+/// 返回值：
+/// - 0：成功
+/// - -1：系统未初始化或锁获取失败
+/// - -2：开始时间字符串无效
+/// - -3：结束时间字符串无效
+/// - -4：输出路径为空或无效
+/// - -5：无法创建输出文件
+/// - -6：导出失败（焦炉编号或时间无效、写入失败等）
+///
+/// # Safety
+///
+/// start、end、out_path 须为空指针，或指向以 NUL 结尾的 C 字符串。
+#[no_mangle]
+pub unsafe extern "C" fn export_cycles_csv(
+    coke_oven: c_int,
+    start: *const c_char,
+    end: *const c_char,
+    out_path: *const c_char,
+) -> c_int {
+    let start_str = match unsafe { c_char_to_string(start) } {
+        Ok(s) => s,
+        Err(_) => {
+            set_last_error("开始时间字符串无效");
+            return -2;
+        }
+    };
+
+    let end_str = match unsafe { c_char_to_string(end) } {
+        Ok(s) => s,
+        Err(_) => {
+            set_last_error("结束时间字符串无效");
+            return -3;
+        }
+    };
+
+    let path_str = match unsafe { c_char_to_string(out_path) } {
+        Ok(s) if !s.is_empty() => s,
+        _ => {
+            set_last_error("输出路径为空或无效");
+            return -4;
+        }
+    };
+
+    // 先写入同目录下的临时文件，成功后再替换目标文件；失败时只删除临时文件，
+    // 调用方已有的同名文件保持不变
+    let tmp_path = format!(
+        "{}.{}-{}.tmp",
+        path_str,
+        std::process::id(),
+        TMP_FILE_COUNTER.fetch_add(1, Ordering::Relaxed)
+    );
+    let file = match File::create(&tmp_path) {
+        Ok(f) => f,
+        Err(e) => {
+            set_last_error(&format!("无法创建输出文件 {}: {}", path_str, e));
+            return -5;
+        }
+    };
+    let mut writer = BufWriter::new(file);
+
+    let result = with_system_mut(|system| {
+        system.export_cycles_csv(coke_oven, &start_str, &end_str, &mut writer)?;
+        writer.flush()?;
+        Ok(())
+    });
+    drop(writer);
+
+    let code = match result {
+        Ok(Ok(())) => match std::fs::rename(&tmp_path, &path_str) {
+            Ok(()) => return 0,
+            Err(e) => {
+                set_last_error(&format!("无法创建输出文件 {}: {}", path_str, e));
+                -5
+            }
+        },
+        Ok(Err(e)) => {
+            set_last_error(&format!("CSV 导出错误: {}", e));
+            -6
+        }
+        Err(e) => {
+            set_last_error(&format!("系统错误: {}", e));
+            -1
+        }
+    };
+    let _ = std::fs::remove_file(&tmp_path);
+    code
+}
+
 /// 关闭系统并清理资源
 #[no_mangle]
 pub extern "C" fn coke_system_shutdown() {
-    if let Some(mutex) = SYSTEM.get() {
-        let mut guard = mutex.lock().unwrap();
-        *guard = None;
+    let _ = coke_system_shutdown_checked();
+}
+
+/// 关闭系统并报告结果，成功（或系统未初始化）返回 0；
+/// 检查点或关闭数据库失败时返回错误码，错误信息可通过 get_last_error 获取
+///
+/// 名称中的 checked 用于区分句柄版的 coke_system_close
+#[no_mangle]
+pub extern "C" fn coke_system_shutdown_checked() -> c_int {
+    let Some(mutex) = SYSTEM.get() else {
+        return 0;
+    };
+    let system = mutex.lock().unwrap_or_else(|e| e.into_inner()).take();
+    match system.map(CokeOvenSystem::close) {
+        Some(Err(e)) => {
+            set_last_error(&e.to_string());
+            error_code(&e)
+        }
+        _ => 0,
+    }
+}
+
+/// 打开数据库并返回独立的系统句柄，可同时打开多个数据库；失败时返回空指针，
+/// 错误信息可通过 get_last_error 获取
+///
+/// 句柄须由 coke_system_close 释放，且不能在多个线程间同时使用
+///
+/// # Safety
+///
+/// db_path 须为空指针，或指向以 NUL 结尾的 C 字符串。
+#[no_mangle]
+pub unsafe extern "C" fn coke_system_open(db_path: *const c_char) -> *mut c_void {
+    let db_path_str = match unsafe { c_char_to_string(db_path) } {
+        Ok(s) => s,
+        Err(_) => {
+            set_last_error("数据库路径为空或不是有效 UTF-8");
+            return std::ptr::null_mut();
+        }
+    };
+
+    match CokeOvenSystem::new(&db_path_str) {
+        Ok(system) => Box::into_raw(Box::new(system)) as *mut c_void,
+        Err(e) => {
+            log::error!("初始化错误: {}", e);
+            set_last_error(&e.to_string());
+            std::ptr::null_mut()
+        }
+    }
+}
+
+/// 关闭 coke_system_open 返回的句柄，空指针忽略
+///
+/// # Safety
+///
+/// handle 须为空指针，或为 coke_system_open 返回且尚未释放的句柄；
+/// 关闭时不能有其他线程正在使用该句柄，关闭后不得再使用。
+#[no_mangle]
+pub unsafe extern "C" fn coke_system_close(handle: *mut c_void) {
+    if !handle.is_null() {
+        drop(Box::from_raw(handle as *mut CokeOvenSystem));
     }
 }
 
@@ -130,10 +636,46 @@ unsafe fn c_char_to_string(c_str: *const c_char) -> Result<String, ()> {
         .map_err(|_| ())
 }
 
+/// 错误类型对应的稳定错误码，-1 ~ -9 保留给系统状态与参数转换失败
+///
+/// - -10：焦炉编号无效
+/// - -11：炭化室无效
+/// - -12：时间格式无效
+/// - -13：数据库错误
+/// - -14：操作类型无效
+/// - -15：其他参数无效
+/// - -16：缺少所需数据或配置
+/// - -17：与已有记录冲突（重复记录）或前置条件不满足
+/// - -18：文件读写错误
+pub fn error_code(e: &CokeError) -> c_int {
+    match e {
+        CokeError::InvalidOven(_) => -10,
+        CokeError::InvalidChamber { .. } => -11,
+        CokeError::InvalidTime(_) => -12,
+        CokeError::Db(_) => -13,
+        CokeError::InvalidOperationType(_) => -14,
+        CokeError::InvalidArgument(_) => -15,
+        CokeError::NoData(_) => -16,
+        CokeError::Conflict(_) => -17,
+        CokeError::Io(_) => -18,
+    }
+}
+
+// 记录当前线程的错误信息，内部 NUL 字符替换为空格
+fn set_last_error(msg: &str) {
+    let msg = CString::new(msg.replace('\0', " ")).unwrap_or_default();
+    LAST_ERROR.with(|e| *e.borrow_mut() = msg);
+}
+
+/// 获取当前线程最近一次错误信息，无错误时返回空字符串
+///
+/// # Safety
+///
+/// 返回的指针由库持有，调用方不得释放或修改；
+/// 在同一线程下一次记录错误之前保持有效。
 #[no_mangle]
 pub unsafe extern "C" fn get_last_error() -> *const c_char {
-    static ERROR: &str = "未实现错误跟踪\0";
-    ERROR.as_ptr() as *const c_char
+    LAST_ERROR.with(|e| e.borrow().as_ptr())
 }
 
 // 初始化系统通用逻辑
@@ -141,21 +683,21 @@ fn init_system(db_path: &str) -> c_int {
     match CokeOvenSystem::new(db_path) {
         Ok(system) => {
             let mutex = SYSTEM.get_or_init(|| Mutex::new(None));
-            let mut guard = mutex.lock().unwrap();
-            *guard = Some(system);
+            *mutex.lock().unwrap_or_else(|e| e.into_inner()) = Some(system);
             0
         }
         Err(e) => {
-            eprintln!("初始化错误: {}", e);
+            log::error!("初始化错误: {}", e);
+            set_last_error(&e.to_string());
             -2
         }
     }
 }
 
 // 带错误处理的系统访问
-fn with_system_mut<F, T>(f: F) -> Result<Result<T, String>, String>
+fn with_system_mut<F, T>(f: F) -> Result<Result<T, CokeError>, String>
 where
-    F: FnOnce(&mut CokeOvenSystem) -> Result<T, String>,
+    F: FnOnce(&mut CokeOvenSystem) -> Result<T, CokeError>,
 {
     let system = SYSTEM.get().ok_or("系统未初始化".to_string())?;
     let mut guard = system.lock().map_err(|_| "锁获取失败".to_string())?;
@@ -163,10 +705,29 @@ where
     Ok(f(system))
 }
 
+// 句柄非空时访问句柄对应的系统，否则访问全局系统；
+// 非空句柄须为 coke_system_open 返回、尚未释放且没有其他线程正在使用的句柄
+unsafe fn with_handle_or_system<F, T>(
+    handle: *mut c_void,
+    f: F,
+) -> Result<Result<T, CokeError>, String>
+where
+    F: FnOnce(&mut CokeOvenSystem) -> Result<T, CokeError>,
+{
+    if handle.is_null() {
+        return with_system_mut(f);
+    }
+    let system = &mut *(handle as *mut CokeOvenSystem);
+    Ok(f(system))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use std::ffi::CString;
+    use tempfile::NamedTempFile;
+
+    // 访问全局系统句柄的测试需要串行执行
+    static FFI_TEST_LOCK: Mutex<()> = Mutex::new(());
 
     #[test]
     fn test_c_char_conversion() {
@@ -180,4 +741,428 @@ mod tests {
         let null_result = unsafe { c_char_to_string(std::ptr::null()) };
         assert!(null_result.is_err());
     }
+
+    #[test]
+    fn test_get_average_temperature_args() {
+        let start = CString::new("2025-06-18 09:00").unwrap();
+        let end = CString::new("2025-06-18 11:00").unwrap();
+        let mut machine = 0.0;
+        let mut coke = 0.0;
+
+        assert_eq!(
+            unsafe {
+                get_average_temperature(1, std::ptr::null(), end.as_ptr(), &mut machine, &mut coke)
+            },
+            -2
+        );
+        assert_eq!(
+            unsafe {
+                get_average_temperature(
+                    1,
+                    start.as_ptr(),
+                    end.as_ptr(),
+                    std::ptr::null_mut(),
+                    &mut coke,
+                )
+            },
+            -4
+        );
+    }
+
+    #[test]
+    fn test_get_average_temperature_error_codes() {
+        let _lock = FFI_TEST_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        let temp_db = NamedTempFile::new().unwrap();
+        assert_eq!(init_system(temp_db.path().to_str().unwrap()), 0);
+
+        let start = CString::new("2025-06-18 09:00").unwrap();
+        let end = CString::new("2025-06-18 11:00").unwrap();
+        let bad = CString::new("not a time").unwrap();
+        let mut machine = 0.0;
+        let mut coke = 0.0;
+        let last_error = || {
+            unsafe { CStr::from_ptr(get_last_error()) }
+                .to_str()
+                .unwrap()
+                .to_string()
+        };
+
+        assert_eq!(
+            unsafe {
+                get_average_temperature(9, start.as_ptr(), end.as_ptr(), &mut machine, &mut coke)
+            },
+            -10
+        );
+        assert_eq!(last_error(), CokeError::InvalidOven(9).to_string());
+        assert_eq!(
+            unsafe {
+                get_average_temperature(1, bad.as_ptr(), end.as_ptr(), &mut machine, &mut coke)
+            },
+            -12
+        );
+        assert_eq!(
+            unsafe {
+                get_average_temperature(1, end.as_ptr(), start.as_ptr(), &mut machine, &mut coke)
+            },
+            -15
+        );
+        assert_eq!(
+            unsafe {
+                get_average_temperature(1, start.as_ptr(), end.as_ptr(), &mut machine, &mut coke)
+            },
+            -6
+        );
+
+        coke_system_shutdown();
+    }
+
+    #[test]
+    fn test_get_temperature_at() {
+        let _lock = FFI_TEST_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        let temp_db = NamedTempFile::new().unwrap();
+        assert_eq!(init_system(temp_db.path().to_str().unwrap()), 0);
+
+        let t0 = CString::new("2025-06-18 08:00").unwrap();
+        let t1 = CString::new("2025-06-18 10:00").unwrap();
+        let mid = CString::new("2025-06-18 09:00").unwrap();
+        let mut machine = 0.0;
+        let mut coke = 0.0;
+
+        assert_eq!(
+            unsafe { get_temperature_at(1, mid.as_ptr(), &mut machine, &mut coke) },
+            1
+        );
+
+        assert_eq!(
+            unsafe { record_temperature(1, t0.as_ptr(), 1300.0, 1400.0) },
+            0
+        );
+        assert_eq!(
+            unsafe { record_temperature(1, t1.as_ptr(), 1320.0, 1420.0) },
+            0
+        );
+        assert_eq!(
+            unsafe { get_temperature_at(1, mid.as_ptr(), &mut machine, &mut coke) },
+            0
+        );
+        assert!((machine - 1310.0).abs() < 1e-9);
+        assert!((coke - 1410.0).abs() < 1e-9);
+
+        assert_eq!(
+            unsafe { get_temperature_at(1, mid.as_ptr(), std::ptr::null_mut(), &mut coke) },
+            -3
+        );
+        assert_eq!(
+            unsafe { get_temperature_at(1, std::ptr::null(), &mut machine, &mut coke) },
+            -2
+        );
+        assert_eq!(
+            unsafe { get_temperature_at(9, mid.as_ptr(), &mut machine, &mut coke) },
+            -10
+        );
+
+        coke_system_shutdown();
+    }
+
+    #[test]
+    fn test_get_chambers() {
+        let _lock = FFI_TEST_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        let temp_db = NamedTempFile::new().unwrap();
+        assert_eq!(init_system(temp_db.path().to_str().unwrap()), 0);
+
+        let required = unsafe { get_chambers(2, std::ptr::null_mut(), 0) };
+        assert!(required > 0);
+
+        let mut small = [0 as c_char; 4];
+        assert_eq!(
+            unsafe { get_chambers(2, small.as_mut_ptr(), small.len()) },
+            -4
+        );
+
+        let mut buf = vec![0 as c_char; required as usize];
+        let written = unsafe { get_chambers(2, buf.as_mut_ptr(), buf.len()) };
+        assert_eq!(written, required - 1);
+
+        let text = unsafe { CStr::from_ptr(buf.as_ptr()) }.to_str().unwrap();
+        let chambers: Vec<&str> = text.split('\n').collect();
+        assert_eq!(chambers.first(), Some(&"76#"));
+        assert_eq!(chambers.last(), Some(&"151#"));
+
+        assert_eq!(unsafe { get_chambers(9, buf.as_mut_ptr(), buf.len()) }, -10);
+        let msg = unsafe { CStr::from_ptr(get_last_error()) }
+            .to_str()
+            .unwrap();
+        assert_eq!(msg, "无效焦炉编号: 9");
+
+        coke_system_shutdown();
+    }
+
+    #[test]
+    fn test_export_cycles_csv() {
+        let _lock = FFI_TEST_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        let temp_db = NamedTempFile::new().unwrap();
+        assert_eq!(init_system(temp_db.path().to_str().unwrap()), 0);
+
+        let chamber = CString::new("1#").unwrap();
+        let load = CString::new("LOAD").unwrap();
+        let push = CString::new("PUSH").unwrap();
+        let t0 = CString::new("2025-06-18 08:00").unwrap();
+        let t1 = CString::new("2025-06-18 10:00").unwrap();
+        assert_eq!(
+            unsafe { record_operation(1, chamber.as_ptr(), load.as_ptr(), t0.as_ptr()) },
+            0
+        );
+        assert_eq!(
+            unsafe { record_operation(1, chamber.as_ptr(), push.as_ptr(), t1.as_ptr()) },
+            0
+        );
+
+        let out_dir = tempfile::tempdir().unwrap();
+        let out_path = out_dir.path().join("cycles.csv");
+        let out = CString::new(out_path.to_str().unwrap()).unwrap();
+        let start = CString::new("2025-06-18 00:00").unwrap();
+        let end = CString::new("2025-06-19 00:00").unwrap();
+
+        assert_eq!(
+            unsafe { export_cycles_csv(1, start.as_ptr(), end.as_ptr(), out.as_ptr()) },
+            0
+        );
+        let text = std::fs::read_to_string(&out_path).unwrap();
+        assert_eq!(text.lines().count(), 2);
+        assert!(text.contains("1,1#,2025-06-18 08:00:00,2025-06-18 10:00:00,02:00,120"));
+
+        assert_eq!(
+            unsafe { export_cycles_csv(1, start.as_ptr(), end.as_ptr(), std::ptr::null()) },
+            -4
+        );
+
+        let bad_path = out_dir.path().join("missing").join("cycles.csv");
+        let bad = CString::new(bad_path.to_str().unwrap()).unwrap();
+        assert_eq!(
+            unsafe { export_cycles_csv(1, start.as_ptr(), end.as_ptr(), bad.as_ptr()) },
+            -5
+        );
+        let msg = unsafe { CStr::from_ptr(get_last_error()) }
+            .to_str()
+            .unwrap();
+        assert!(msg.starts_with("无法创建输出文件"));
+
+        // 参数无效时不改动调用方已有的文件，也不留下临时文件
+        std::fs::write(&out_path, "existing").unwrap();
+        assert_eq!(
+            unsafe { export_cycles_csv(9, start.as_ptr(), end.as_ptr(), out.as_ptr()) },
+            -6
+        );
+        assert_eq!(std::fs::read_to_string(&out_path).unwrap(), "existing");
+        assert_eq!(std::fs::read_dir(out_dir.path()).unwrap().count(), 1);
+
+        // 成功时替换已有文件
+        assert_eq!(
+            unsafe { export_cycles_csv(1, start.as_ptr(), end.as_ptr(), out.as_ptr()) },
+            0
+        );
+        assert_eq!(std::fs::read_to_string(&out_path).unwrap(), text);
+
+        coke_system_shutdown();
+    }
+
+    #[test]
+    fn test_record_operation_ex() {
+        let _lock = FFI_TEST_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        let temp_db = NamedTempFile::new().unwrap();
+        assert_eq!(init_system(temp_db.path().to_str().unwrap()), 0);
+
+        let chamber = CString::new("1#").unwrap();
+        let load = CString::new("LOAD").unwrap();
+        let push = CString::new("PUSH").unwrap();
+        let t0 = CString::new("2025-06-18 08:00").unwrap();
+        let t1 = CString::new("2025-06-18 20:00").unwrap();
+
+        let mut created: c_int = -1;
+        assert_eq!(
+            unsafe {
+                record_operation_ex(
+                    1,
+                    chamber.as_ptr(),
+                    load.as_ptr(),
+                    t0.as_ptr(),
+                    &mut created,
+                )
+            },
+            0
+        );
+        assert_eq!(created, 0);
+        assert_eq!(
+            unsafe {
+                record_operation_ex(
+                    1,
+                    chamber.as_ptr(),
+                    push.as_ptr(),
+                    t1.as_ptr(),
+                    &mut created,
+                )
+            },
+            0
+        );
+        assert_eq!(created, 1);
+
+        // 输出指针为空时仍正常记录
+        let t2 = CString::new("2025-06-19 08:00").unwrap();
+        assert_eq!(
+            unsafe {
+                record_operation_ex(
+                    1,
+                    chamber.as_ptr(),
+                    load.as_ptr(),
+                    t2.as_ptr(),
+                    std::ptr::null_mut(),
+                )
+            },
+            0
+        );
+
+        coke_system_shutdown();
+    }
+
+    #[test]
+    fn test_system_handles() {
+        let db_a = NamedTempFile::new().unwrap();
+        let db_b = NamedTempFile::new().unwrap();
+        let path_a = CString::new(db_a.path().to_str().unwrap()).unwrap();
+        let path_b = CString::new(db_b.path().to_str().unwrap()).unwrap();
+
+        let a = unsafe { coke_system_open(path_a.as_ptr()) };
+        let b = unsafe { coke_system_open(path_b.as_ptr()) };
+        assert!(!a.is_null() && !b.is_null());
+
+        let time = CString::new("2025-06-18 08:00").unwrap();
+        let chamber = CString::new("1#").unwrap();
+        let load = CString::new("LOAD").unwrap();
+        assert_eq!(
+            unsafe { record_temperature_h(a, 1, time.as_ptr(), 1300.0, 1310.0) },
+            0
+        );
+        assert_eq!(
+            unsafe {
+                record_operation_h(
+                    b,
+                    1,
+                    chamber.as_ptr(),
+                    load.as_ptr(),
+                    time.as_ptr(),
+                    std::ptr::null_mut(),
+                )
+            },
+            0
+        );
+
+        let count = |handle: *mut c_void, table: &str| -> i64 {
+            let system = unsafe { &*(handle as *const CokeOvenSystem) };
+            system
+                .conn
+                .query_row(&format!("SELECT COUNT(*) FROM {}", table), [], |row| {
+                    row.get(0)
+                })
+                .unwrap()
+        };
+        assert_eq!(count(a, "temperature_records"), 1);
+        assert_eq!(count(a, "operation_records"), 0);
+        assert_eq!(count(b, "temperature_records"), 0);
+        assert_eq!(count(b, "operation_records"), 1);
+
+        assert_eq!(
+            unsafe { record_temperature_h(std::ptr::null_mut(), 1, time.as_ptr(), 1300.0, 1310.0) },
+            -1
+        );
+
+        unsafe { coke_system_close(a) };
+        unsafe { coke_system_close(b) };
+        unsafe { coke_system_close(std::ptr::null_mut()) };
+    }
+
+    #[test]
+    fn test_init_system_error() {
+        let _lock = FFI_TEST_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("missing").join("coke.db");
+        assert_eq!(init_system(path.to_str().unwrap()), -2);
+        let msg = unsafe { CStr::from_ptr(get_last_error()) }
+            .to_str()
+            .unwrap();
+        assert!(!msg.is_empty());
+    }
+
+    #[test]
+    fn test_layout_counts() {
+        let _lock = FFI_TEST_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        coke_system_shutdown();
+        assert_eq!(get_oven_count(), -1);
+        assert_eq!(get_chamber_count(1), -1);
+
+        let temp_db = NamedTempFile::new().unwrap();
+        assert_eq!(init_system(temp_db.path().to_str().unwrap()), 0);
+
+        assert_eq!(get_oven_count(), 3);
+        let chambers = with_system_mut(|system| system.chambers(2))
+            .unwrap()
+            .unwrap();
+        assert_eq!(get_chamber_count(2), chambers.len() as c_int);
+        assert_eq!(get_chamber_count(9), -1);
+
+        assert_eq!(coke_system_shutdown_checked(), 0);
+        assert_eq!(get_oven_count(), -1);
+        assert_eq!(coke_system_shutdown_checked(), 0);
+        coke_system_shutdown();
+    }
+
+    #[test]
+    fn test_error_codes() {
+        let _lock = FFI_TEST_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        let temp_db = NamedTempFile::new().unwrap();
+        assert_eq!(init_system(temp_db.path().to_str().unwrap()), 0);
+
+        let time = CString::new("2025-06-18 08:00").unwrap();
+        let bad_time = CString::new("bad").unwrap();
+        let chamber = CString::new("1#").unwrap();
+        let bad_chamber = CString::new("10#").unwrap();
+        let load = CString::new("LOAD").unwrap();
+        let open = CString::new("OPEN").unwrap();
+
+        assert_eq!(
+            unsafe { record_temperature(9, time.as_ptr(), 1300.0, 1310.0) },
+            -10
+        );
+        let msg = unsafe { CStr::from_ptr(get_last_error()) }
+            .to_str()
+            .unwrap();
+        assert_eq!(msg, "无效焦炉编号: 9");
+
+        assert_eq!(
+            unsafe { record_temperature(1, bad_time.as_ptr(), 1300.0, 1310.0) },
+            -12
+        );
+        assert_eq!(
+            unsafe { record_temperature(1, std::ptr::null(), 1300.0, 1310.0) },
+            -2
+        );
+        assert_eq!(
+            unsafe { record_temperature(1, time.as_ptr(), 1300.0, 1310.0) },
+            0
+        );
+        assert_eq!(
+            unsafe { record_temperature(1, time.as_ptr(), 1300.0, 1310.0) },
+            -17
+        );
+
+        assert_eq!(
+            unsafe { record_operation(1, bad_chamber.as_ptr(), load.as_ptr(), time.as_ptr()) },
+            -11
+        );
+        assert_eq!(
+            unsafe { record_operation(1, chamber.as_ptr(), open.as_ptr(), time.as_ptr()) },
+            -14
+        );
+
+        coke_system_shutdown();
+    }
 }
@@ -1,7 +1,9 @@
+pub mod clock;
 mod db;
+pub mod error;
 mod ffi;
-mod models;
-mod oven;
-mod system;
+pub mod models;
+pub mod oven;
+pub mod system;
 
 pub use ffi::*;
@@ -1,4 +1,5 @@
-use chrono::{NaiveDate, NaiveDateTime};
+use crate::error::CokeError;
+use chrono::{FixedOffset, NaiveDate, NaiveDateTime, TimeZone};
 
 // 温度记录点
 pub struct TempRecord {
@@ -8,14 +9,224 @@ pub struct TempRecord {
 }
 
 // 时间-温度点
+#[derive(Debug, Clone, PartialEq)]
 pub struct TimeTempPoint {
     pub time: NaiveDateTime,
     pub machine: f64,
     pub coke: f64,
 }
 
+// 结焦周期记录
+#[derive(Debug, Clone, PartialEq)]
+pub struct CokingCycle {
+    pub id: i64,
+    pub coke_oven: i32,
+    pub chamber: String,
+    pub loading_time: NaiveDateTime,
+    pub push_time: NaiveDateTime,
+    pub duration_hhmm: String,
+    pub duration_minutes: i32,
+    pub avg_temp_machine: Option<f64>,
+    pub avg_temp_coke: Option<f64>,
+    // 来源装煤/推焦操作记录 id，早期数据无法关联时为 None
+    pub loading_op_id: Option<i64>,
+    pub push_op_id: Option<i64>,
+    // 周期内部温度记录少于最少样本数，平均温度未计算
+    pub low_confidence: bool,
+}
+
+// 装煤/推焦操作记录
+#[derive(Debug, Clone, PartialEq)]
+pub struct OperationRecord {
+    pub id: i64,
+    pub coke_oven: i32,
+    pub chamber: String,
+    pub operation_type: String,
+    pub time: NaiveDateTime,
+}
+
+// 审计日志条目
+#[derive(Debug, Clone, PartialEq)]
+pub struct AuditEntry {
+    pub id: i64,
+    // INSERT / UPDATE / DELETE
+    pub action: String,
+    pub table_name: String,
+    // 被修改记录的 id
+    pub record_key: String,
+    pub time: NaiveDateTime,
+    pub user: Option<String>,
+}
+
+// 温度统计
+#[derive(Debug, Clone, PartialEq)]
+pub struct TempStats {
+    pub count: i64,
+    pub min_machine: f64,
+    pub max_machine: f64,
+    pub avg_machine: f64,
+    pub min_coke: f64,
+    pub max_coke: f64,
+    pub avg_coke: f64,
+}
+
+// 两座焦炉同一时间窗口内的平均温度对比，差值为 A 减 B
+#[derive(Debug, Clone, PartialEq)]
+pub struct OvenComparison {
+    pub oven_a: i32,
+    pub oven_b: i32,
+    pub machine_a: f64,
+    pub coke_a: f64,
+    pub machine_b: f64,
+    pub coke_b: f64,
+    pub machine_delta: f64,
+    pub coke_delta: f64,
+}
+
+// 按推焦日期汇总的生产统计
+#[derive(Debug, Clone, PartialEq)]
+pub struct DailySummary {
+    pub date: NaiveDate,
+    pub push_count: i64,
+    pub avg_duration_minutes: f64,
+}
+
+// 清理历史数据的结果：各表删除的行数
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PruneReport {
+    pub temperature_records: usize,
+    pub operation_records: usize,
+}
+
+// 某一类数据问题的数量及示例行
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct IssueSummary {
+    pub count: usize,
+    pub samples: Vec<String>,
+}
+
+impl IssueSummary {
+    // 记录一条问题，示例行最多保留 limit 条
+    pub fn add(&mut self, sample: String, limit: usize) {
+        self.count += 1;
+        if self.samples.len() < limit {
+            self.samples.push(sample);
+        }
+    }
+}
+
+// 数据完整性检查结果
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct IntegrityReport {
+    // 无法解析的时间字段
+    pub unparseable_times: IssueSummary,
+    // 推焦时间不晚于装煤时间的结焦周期
+    pub push_before_load: IssueSummary,
+    // 焦炉或炭化室不在当前布局中的记录
+    pub unknown_chambers: IssueSummary,
+    // 结焦分钟数为负的结焦周期
+    pub negative_durations: IssueSummary,
+}
+
+impl IntegrityReport {
+    pub fn is_clean(&self) -> bool {
+        self.unparseable_times.count == 0
+            && self.push_before_load.count == 0
+            && self.unknown_chambers.count == 0
+            && self.negative_durations.count == 0
+    }
+}
+
+// 温度测量侧：机侧或焦侧
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Side {
+    Machine,
+    Coke,
+}
+
+impl Side {
+    // 从（机侧, 焦侧）中取出对应侧的值
+    pub fn select(self, machine: f64, coke: f64) -> f64 {
+        match self {
+            Side::Machine => machine,
+            Side::Coke => coke,
+        }
+    }
+}
+
+// 炭化室当前状态：最近一次操作为装煤时处于装煤状态，否则为空炉
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChamberState {
+    Loaded { since: NaiveDateTime },
+    Empty,
+}
+
+// 推焦时与装煤记录的配对策略：两次推焦之间存在多次装煤时选择哪一次
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LoadPairing {
+    // 推焦前最近一次装煤（默认，与历史行为一致）
+    #[default]
+    Latest,
+    // 上一次推焦之后最早一次装煤；上一次推焦之后没有装煤时不生成周期
+    Earliest,
+}
+
+// 平均温度的数值积分方法
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum IntegrationMethod {
+    // 梯形法（默认，与历史结果一致）
+    #[default]
+    Trapezoidal,
+    // 复合辛普森法，支持不等间距；点数少于 3 个时退化为梯形法
+    Simpson,
+}
+
+// 数据库日志模式，网络文件系统上 WAL 不可用时改用 DELETE
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum JournalMode {
+    #[default]
+    Wal,
+    Delete,
+}
+
+impl JournalMode {
+    // PRAGMA journal_mode 返回的模式名（小写）
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            JournalMode::Wal => "wal",
+            JournalMode::Delete => "delete",
+        }
+    }
+}
+
+// 时间范围查询的端点语义
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum IntervalBounds {
+    // [start, end]，含两端（默认）
+    #[default]
+    Closed,
+    // (start, end)，不含两端；结焦周期计算取中间记录时使用，两端由插值点补齐
+    Open,
+    // [start, end)
+    ClosedOpen,
+    // (start, end]
+    OpenClosed,
+}
+
+impl IntervalBounds {
+    // 起点与终点的 SQL 比较运算符
+    pub fn operators(&self) -> (&'static str, &'static str) {
+        match self {
+            IntervalBounds::Closed => (">=", "<="),
+            IntervalBounds::Open => (">", "<"),
+            IntervalBounds::ClosedOpen => (">=", "<"),
+            IntervalBounds::OpenClosed => (">", "<="),
+        }
+    }
+}
+
 // 时间格式解析器
-pub fn parse_time(time_str: &str) -> Result<NaiveDateTime, String> {
+pub fn parse_time(time_str: &str) -> Result<NaiveDateTime, CokeError> {
     // 尝试带秒格式
     if let Ok(dt) = NaiveDateTime::parse_from_str(time_str, "%Y-%m-%d %H:%M:%S") {
         return Ok(dt);
@@ -26,30 +237,126 @@ pub fn parse_time(time_str: &str) -> Result<NaiveDateTime, String> {
         return Ok(dt);
     }
 
-    // 最后尝试仅日期格式
+    // 尝试 ISO 8601 带秒格式
+    if let Ok(dt) = NaiveDateTime::parse_from_str(time_str, "%Y-%m-%dT%H:%M:%S") {
+        return Ok(dt);
+    }
+
+    // 尝试 ISO 8601 不带秒格式
+    if let Ok(dt) = NaiveDateTime::parse_from_str(time_str, "%Y-%m-%dT%H:%M") {
+        return Ok(dt);
+    }
+
+    // 尝试仅日期格式
     if let Ok(date) = NaiveDate::parse_from_str(time_str, "%Y-%m-%d") {
         if let Some(dt) = date.and_hms_opt(0, 0, 0) {
             return Ok(dt);
         }
     }
 
-    Err("无效时间格式".to_string())
+    // 兼容旧版 Windows 工具输出的斜杠分隔格式
+    if let Ok(dt) = NaiveDateTime::parse_from_str(time_str, "%Y/%m/%d %H:%M:%S") {
+        return Ok(dt);
+    }
+
+    if let Ok(dt) = NaiveDateTime::parse_from_str(time_str, "%Y/%m/%d %H:%M") {
+        return Ok(dt);
+    }
+
+    if let Ok(date) = NaiveDate::parse_from_str(time_str, "%Y/%m/%d") {
+        if let Some(dt) = date.and_hms_opt(0, 0, 0) {
+            return Ok(dt);
+        }
+    }
+
+    Err(CokeError::InvalidTime(time_str.to_string()))
+}
+
+// 按标准格式输出时间，便于与数据库中的时间字符串比较
+pub fn format_time(dt: NaiveDateTime) -> String {
+    dt.format("%Y-%m-%d %H:%M:%S").to_string()
+}
+
+// 将指定时区偏移下的本地时间字符串解析为 UTC 时间
+pub fn parse_time_with_offset(
+    time_str: &str,
+    offset: FixedOffset,
+) -> Result<NaiveDateTime, CokeError> {
+    let local = parse_time(time_str)?;
+    // 固定偏移不存在夏令时歧义，single() 总是有值
+    offset
+        .from_local_datetime(&local)
+        .single()
+        .map(|dt| dt.naive_utc())
+        .ok_or_else(|| CokeError::InvalidTime(time_str.to_string()))
+}
+
+// 将 UTC 时间转换为指定时区偏移下的本地时间，用于显示
+pub fn utc_to_offset(utc: NaiveDateTime, offset: FixedOffset) -> NaiveDateTime {
+    offset.from_utc_datetime(&utc).naive_local()
+}
+
+// 炭化室自然排序键："2#" -> 2，格式无效的炭化室返回 u32::MAX 排在最后
+pub fn natural_chamber_key(chamber: &str) -> u32 {
+    chamber
+        .strip_suffix('#')
+        .and_then(|n| n.parse().ok())
+        .unwrap_or(u32::MAX)
+}
+
+// 按自然顺序排序炭化室，键相同时按字符串排序以保证结果确定
+pub fn sort_chambers_naturally(chambers: &mut [String]) {
+    chambers.sort_by(|a, b| {
+        natural_chamber_key(a)
+            .cmp(&natural_chamber_key(b))
+            .then_with(|| a.cmp(b))
+    });
+}
+
+// 解析任意受支持格式的时间并转换为标准格式，保证字符串顺序与时间顺序一致
+pub fn normalize_time(time_str: &str) -> Result<String, CokeError> {
+    parse_time(time_str).map(format_time)
 }
 
 // 辅助函数：根据前后两个记录插值指定时间点的温度
+//
+// 目标时间超出 [prev, next] 时（数据异常）截断到最近端点，不做线性外推；
+// 结焦周期平均温度计算使用此版本
 pub fn interpolate_temp(
     prev: &Option<TempRecord>,
     next: &Option<TempRecord>,
     target: NaiveDateTime,
+) -> Option<(f64, f64)> {
+    interpolate_temp_with(prev, next, target, false)
+}
+
+// 严格模式插值：前后记录都存在且目标时间超出 [prev, next] 时返回 None，
+// 只有一侧记录时仍取该记录的温度
+pub fn interpolate_temp_strict(
+    prev: &Option<TempRecord>,
+    next: &Option<TempRecord>,
+    target: NaiveDateTime,
+) -> Option<(f64, f64)> {
+    interpolate_temp_with(prev, next, target, true)
+}
+
+fn interpolate_temp_with(
+    prev: &Option<TempRecord>,
+    next: &Option<TempRecord>,
+    target: NaiveDateTime,
+    strict: bool,
 ) -> Option<(f64, f64)> {
     match (prev, next) {
         (Some(prev_rec), Some(next_rec)) => {
+            if strict && (target < prev_rec.time || target > next_rec.time) {
+                return None;
+            }
             let total_secs = (next_rec.time - prev_rec.time).num_seconds() as f64;
             if total_secs == 0.0 {
                 return Some((prev_rec.machine_side, prev_rec.coke_side));
             }
             let secs_from_prev = (target - prev_rec.time).num_seconds() as f64;
-            let ratio = secs_from_prev / total_secs;
+            let ratio = (secs_from_prev / total_secs).clamp(0.0, 1.0);
             let machine =
                 prev_rec.machine_side + (next_rec.machine_side - prev_rec.machine_side) * ratio;
             let coke = prev_rec.coke_side + (next_rec.coke_side - prev_rec.coke_side) * ratio;
@@ -107,6 +414,89 @@ mod tests {
         assert!(parse_time(time_str).is_err());
     }
 
+    #[test]
+    fn test_parse_time_iso8601() {
+        // ISO 8601 带秒格式与空格分隔格式结果一致
+        assert_eq!(
+            parse_time("2025-06-18T08:16:30").unwrap(),
+            parse_time("2025-06-18 08:16:30").unwrap()
+        );
+
+        // ISO 8601 不带秒格式
+        assert_eq!(
+            parse_time("2025-06-18T08:16").unwrap(),
+            parse_time("2025-06-18 08:16").unwrap()
+        );
+
+        assert!(parse_time("2025-06-18T").is_err());
+    }
+
+    #[test]
+    fn test_parse_time_slash() {
+        assert_eq!(
+            parse_time("2025/06/18 08:16:30").unwrap(),
+            parse_time("2025-06-18 08:16:30").unwrap()
+        );
+        assert_eq!(
+            parse_time("2025/06/18 08:16").unwrap(),
+            parse_time("2025-06-18 08:16").unwrap()
+        );
+        assert_eq!(
+            parse_time("2025/06/18").unwrap(),
+            parse_time("2025-06-18").unwrap()
+        );
+
+        // 反斜杠与混合分隔符仍然无效
+        assert!(parse_time("2025\\06\\18").is_err());
+        assert!(parse_time("2025/06-18").is_err());
+    }
+
+    #[test]
+    fn test_normalize_time() {
+        assert_eq!(
+            normalize_time("2025-06-18 08:16").unwrap(),
+            "2025-06-18 08:16:00"
+        );
+        assert_eq!(
+            normalize_time("2025-06-18 08:16:00").unwrap(),
+            "2025-06-18 08:16:00"
+        );
+        assert_eq!(normalize_time("2025/06/18").unwrap(), "2025-06-18 00:00:00");
+        assert!(normalize_time("invalid").is_err());
+    }
+
+    #[test]
+    fn test_parse_time_with_offset() {
+        let offset = FixedOffset::east_opt(8 * 3600).unwrap();
+        let utc = parse_time_with_offset("2025-06-18 08:00", offset).unwrap();
+        assert_eq!(format_time(utc), "2025-06-18 00:00:00");
+        assert_eq!(
+            format_time(utc_to_offset(utc, offset)),
+            "2025-06-18 08:00:00"
+        );
+
+        // 跨日
+        let utc = parse_time_with_offset("2025-06-18 05:30", offset).unwrap();
+        assert_eq!(format_time(utc), "2025-06-17 21:30:00");
+        assert!(parse_time_with_offset("invalid", offset).is_err());
+    }
+
+    #[test]
+    fn test_natural_chamber_key() {
+        assert_eq!(natural_chamber_key("2#"), 2);
+        assert_eq!(natural_chamber_key("10#"), 10);
+        assert!(natural_chamber_key("10#") > natural_chamber_key("2#"));
+        assert_eq!(natural_chamber_key("A#"), u32::MAX);
+        assert_eq!(natural_chamber_key("12"), u32::MAX);
+
+        let mut chambers: Vec<String> = ["10#", "B#", "2#", "A#", "1#"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        sort_chambers_naturally(&mut chambers);
+        assert_eq!(chambers, vec!["1#", "2#", "10#", "A#", "B#"]);
+    }
+
     #[test]
     fn test_interpolate_temp() {
         let prev = Some(TempRecord {
@@ -142,4 +532,40 @@ mod tests {
         let result = interpolate_temp(&prev, &next, next.as_ref().unwrap().time).unwrap();
         assert_eq!(result, (200.0, 300.0));
     }
+
+    #[test]
+    fn test_interpolate_temp_out_of_bracket() {
+        let at = |h: u32| {
+            NaiveDateTime::new(
+                NaiveDate::from_ymd_opt(2025, 6, 18).unwrap(),
+                NaiveTime::from_hms_opt(h, 0, 0).unwrap(),
+            )
+        };
+        let prev = Some(TempRecord {
+            time: at(8),
+            machine_side: 100.0,
+            coke_side: 200.0,
+        });
+        let next = Some(TempRecord {
+            time: at(9),
+            machine_side: 200.0,
+            coke_side: 300.0,
+        });
+
+        // 默认截断到最近端点
+        assert_eq!(interpolate_temp(&prev, &next, at(6)), Some((100.0, 200.0)));
+        assert_eq!(interpolate_temp(&prev, &next, at(12)), Some((200.0, 300.0)));
+
+        // 严格模式返回 None
+        assert_eq!(interpolate_temp_strict(&prev, &next, at(6)), None);
+        assert_eq!(interpolate_temp_strict(&prev, &next, at(12)), None);
+        assert_eq!(
+            interpolate_temp_strict(&prev, &next, at(9)),
+            Some((200.0, 300.0))
+        );
+        assert_eq!(
+            interpolate_temp_strict(&prev, &None, at(12)),
+            Some((100.0, 200.0))
+        );
+    }
 }
@@ -4,20 +4,37 @@ use std::collections::HashMap;
 #[derive(Debug)]
 pub struct CokeOven {
     pub chambers: Vec<String>,
+    // 额定结焦时间（分钟），未配置时为 None
+    pub expected_cycle_minutes: Option<i32>,
 }
 
 impl CokeOven {
     pub fn new(chambers: &[&str]) -> Self {
         CokeOven {
             chambers: chambers.iter().map(|s| s.to_string()).collect(),
+            expected_cycle_minutes: None,
         }
     }
 
+    // 设置额定结焦时间，用于自定义布局
+    pub fn with_expected_cycle_minutes(mut self, minutes: i32) -> Self {
+        self.expected_cycle_minutes = Some(minutes);
+        self
+    }
+
     pub fn is_valid_chamber(&self, chamber: &str) -> bool {
         self.chambers.iter().any(|c| c == chamber)
     }
 }
 
+// 检查炭化室名称是否为“数字+#”格式（如 12#），自定义布局只接受此格式
+pub fn is_well_formed_chamber_name(name: &str) -> bool {
+    match name.strip_suffix('#') {
+        Some(digits) => !digits.is_empty() && digits.bytes().all(|b| b.is_ascii_digit()),
+        None => false,
+    }
+}
+
 // 初始化焦炉数据
 pub fn initialize_ovens() -> HashMap<i32, CokeOven> {
     let mut ovens = HashMap::new();
@@ -87,4 +104,25 @@ mod tests {
         assert!(oven3.is_valid_chamber("75#"));
         assert!(!oven3.is_valid_chamber("76#"));
     }
+
+    #[test]
+    fn test_expected_cycle_minutes() {
+        let oven = CokeOven::new(&["1#", "2#"]);
+        assert_eq!(oven.expected_cycle_minutes, None);
+
+        let oven = oven.with_expected_cycle_minutes(19 * 60);
+        assert_eq!(oven.expected_cycle_minutes, Some(1140));
+        assert!(oven.is_valid_chamber("2#"));
+    }
+
+    #[test]
+    fn test_chamber_name_format() {
+        assert!(is_well_formed_chamber_name("1#"));
+        assert!(is_well_formed_chamber_name("151#"));
+        assert!(!is_well_formed_chamber_name("#"));
+        assert!(!is_well_formed_chamber_name("1"));
+        assert!(!is_well_formed_chamber_name("1; DROP"));
+        assert!(!is_well_formed_chamber_name("1#2#"));
+        assert!(!is_well_formed_chamber_name("１#"));
+    }
 }
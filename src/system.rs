@@ -1,21 +1,251 @@
-use crate::db::initialize_db;
-use crate::models::{TempRecord, TimeTempPoint};
-use crate::oven::{initialize_ovens, CokeOven};
-use rusqlite::{params, Connection, OptionalExtension};
-use std::collections::HashMap;
+use crate::clock::{Clock, SystemClock};
+use crate::db::{initialize_db, normalize_time_columns};
+use crate::error::CokeError;
+use crate::models::{
+    AuditEntry, ChamberState, CokingCycle, DailySummary, IntegrationMethod, IntegrityReport,
+    IntervalBounds, JournalMode, LoadPairing, OperationRecord, OvenComparison, PruneReport, Side,
+    TempRecord, TempStats, TimeTempPoint,
+};
+use crate::oven::{initialize_ovens, is_well_formed_chamber_name, CokeOven};
+use chrono::{Duration, FixedOffset, Local, NaiveDateTime, TimeZone, Timelike};
+use rusqlite::backup::Backup;
+use rusqlite::{params, Connection, OpenFlags, OptionalExtension};
+use std::collections::{BTreeMap, HashMap};
+use std::io::{Read, Write};
+use std::mem::ManuallyDrop;
+use std::sync::{Arc, Mutex};
+
+// 完整性检查中每类问题保留的示例行数
+const INTEGRITY_SAMPLE_LIMIT: usize = 5;
+
+// 焦炭成熟度的默认目标温度（℃）
+const DEFAULT_READINESS_TARGET: f64 = 1000.0;
+
+// 计算结焦周期平均温度所需的最少内部温度记录数，默认 0 表示不检查
+const DEFAULT_MIN_INTERIOR_SAMPLES: usize = 0;
+
+// 二进制导出格式：魔数与版本号
+const BINARY_MAGIC: &[u8; 8] = b"COKEBIN\0";
+const BINARY_VERSION: u16 = 1;
+
+// metadata 表中增量计算结焦周期的水位键
+const CYCLE_WATERMARK_KEY: &str = "cycle_watermark";
+
+// 焖炉阶段：推焦前的时长（分钟）
+const END_SOAK_MINUTES: i64 = 120;
 
 // 系统状态
 pub struct CokeOvenSystem {
-    pub conn: Connection,
+    // 由 Drop 或 close 负责释放
+    pub conn: ManuallyDrop<Connection>,
     pub ovens: HashMap<i32, CokeOven>,
+    readiness_target: f64,
+    target_temperatures: HashMap<i32, f64>,
+    // 为 true 时 duration_hhmm 按 HH:MM:SS 存储，保留不足一分钟的秒数
+    duration_seconds_precision: bool,
+    load_pairing: LoadPairing,
+    integration_method: IntegrationMethod,
+    // 输入时间所在的时区偏移；设置后记录时按此偏移解析并以 UTC 存储
+    time_offset: Option<FixedOffset>,
+    // 允许记录的温度范围（℃，含端点），None 时不限制
+    temperature_limits: Option<(f64, f64)>,
+    min_interior_samples: usize,
+    clock: Box<dyn Clock>,
+    // close 已取出并关闭连接，Drop 时不再访问
+    closed: bool,
 }
 
 impl CokeOvenSystem {
-    pub fn new(db_path: &str) -> Result<Self, String> {
-        let conn = Connection::open(db_path).map_err(|e| format!("无法打开数据库: {}", e))?;
-        initialize_db(&conn).map_err(|e| format!("数据库初始化失败: {}", e))?;
-        let ovens = initialize_ovens();
-        Ok(Self { conn, ovens })
+    pub fn new(db_path: &str) -> Result<Self, CokeError> {
+        let conn = Connection::open(db_path)?;
+        initialize_db(&conn, JournalMode::Wal)?;
+        Ok(Self::from_parts(conn, initialize_ovens()))
+    }
+
+    // 使用自定义焦炉布局创建系统，炭化室名称必须为“数字+#”格式，否则返回错误
+    pub fn new_with_layout(
+        db_path: &str,
+        ovens: HashMap<i32, CokeOven>,
+    ) -> Result<Self, CokeError> {
+        for (&oven, layout) in &ovens {
+            if let Some(chamber) = layout
+                .chambers
+                .iter()
+                .find(|c| !is_well_formed_chamber_name(c))
+            {
+                return Err(CokeError::InvalidChamber {
+                    oven,
+                    chamber: chamber.clone(),
+                });
+            }
+        }
+
+        let conn = Connection::open(db_path)?;
+        initialize_db(&conn, JournalMode::Wal)?;
+        Ok(Self::from_parts(conn, ovens))
+    }
+
+    // 使用指定日志模式创建系统，默认布局；SQLite 拒绝切换模式时返回错误
+    pub fn new_with_options(db_path: &str, journal_mode: JournalMode) -> Result<Self, CokeError> {
+        let conn = Connection::open(db_path)?;
+        initialize_db(&conn, journal_mode)?;
+        Ok(Self::from_parts(conn, initialize_ovens()))
+    }
+
+    // 使用调用方已打开的连接创建系统（可带附加数据库或自定义 PRAGMA），
+    // 连接所有权转移给系统，在其上执行建表初始化并使用默认焦炉布局
+    pub fn from_connection(conn: Connection) -> Result<Self, CokeError> {
+        initialize_db(&conn, JournalMode::Wal)?;
+        Ok(Self::from_parts(conn, initialize_ovens()))
+    }
+
+    // 以只读方式打开数据库，不执行建表初始化，可与写入进程并发查询
+    pub fn open_readonly(db_path: &str) -> Result<ReadOnlySystem, CokeError> {
+        let conn = Connection::open_with_flags(
+            db_path,
+            OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX,
+        )?;
+        Ok(ReadOnlySystem {
+            inner: Self::from_parts(conn, initialize_ovens()),
+        })
+    }
+
+    fn from_parts(conn: Connection, ovens: HashMap<i32, CokeOven>) -> Self {
+        Self {
+            conn: ManuallyDrop::new(conn),
+            ovens,
+            readiness_target: DEFAULT_READINESS_TARGET,
+            target_temperatures: HashMap::new(),
+            duration_seconds_precision: false,
+            load_pairing: LoadPairing::default(),
+            integration_method: IntegrationMethod::default(),
+            time_offset: None,
+            temperature_limits: None,
+            min_interior_samples: DEFAULT_MIN_INTERIOR_SAMPLES,
+            clock: Box::new(SystemClock),
+            closed: false,
+        }
+    }
+
+    // 替换获取当前时间的时钟，默认使用系统时钟
+    pub fn with_clock(mut self, clock: impl Clock + 'static) -> Self {
+        self.clock = Box::new(clock);
+        self
+    }
+
+    // 设置推焦与装煤记录的配对策略，默认 LoadPairing::Latest
+    pub fn set_load_pairing(&mut self, policy: LoadPairing) {
+        self.load_pairing = policy;
+    }
+
+    // 设置计算平均温度的积分方法，默认 IntegrationMethod::Trapezoidal
+    pub fn set_integration_method(&mut self, method: IntegrationMethod) {
+        self.integration_method = method;
+    }
+
+    // 设置输入时间的时区偏移：设置后 record_* 接收的时间字符串视为该偏移下的本地时间，
+    // 转换为 UTC 存储；None（默认）时按原样存储本地时间
+    //
+    // 查询方法的时间参数与返回值均为存储时间，可用 to_stored_time / to_local_time 转换；
+    // *_dt 方法接收的也是存储时间
+    pub fn set_time_offset(&mut self, offset: Option<FixedOffset>) {
+        self.time_offset = offset;
+    }
+
+    // 将输入的本地时间转换为标准格式的存储时间，用于构造查询范围
+    pub fn to_stored_time(&self, time: &str) -> Result<String, CokeError> {
+        self.parse_input_time(time).map(crate::models::format_time)
+    }
+
+    // 将存储时间转换为输入时区的本地时间，用于显示
+    pub fn to_local_time(&self, stored: NaiveDateTime) -> NaiveDateTime {
+        match self.time_offset {
+            Some(offset) => crate::models::utc_to_offset(stored, offset),
+            None => stored,
+        }
+    }
+
+    // 检查机侧、焦侧温度是否在允许范围内
+    fn check_temperature_limits(&self, machine_temp: f64, coke_temp: f64) -> Result<(), CokeError> {
+        let Some((min, max)) = self.temperature_limits else {
+            return Ok(());
+        };
+        for (side, value) in [("机侧", machine_temp), ("焦侧", coke_temp)] {
+            if !(min..=max).contains(&value) {
+                return Err(CokeError::InvalidArgument(format!(
+                    "{}温度{}超出允许范围[{}, {}]",
+                    side, value, min, max
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    // 按配置的时区偏移解析输入时间，返回存储时间
+    fn parse_input_time(&self, time: &str) -> Result<NaiveDateTime, CokeError> {
+        match self.time_offset {
+            Some(offset) => crate::models::parse_time_with_offset(time, offset),
+            None => crate::models::parse_time(time),
+        }
+    }
+
+    // 设置写入审计日志的操作人，None 时不记录操作人；保存在数据库中，重新连接后仍然有效
+    pub fn set_current_user(&mut self, user: Option<&str>) -> Result<(), CokeError> {
+        self.conn.execute(
+            "INSERT INTO audit_user (id, name) VALUES (1, ?1)
+                ON CONFLICT(id) DO UPDATE SET name = excluded.name",
+            params![user],
+        )?;
+        Ok(())
+    }
+
+    // 审计日志以 UTC 记录时间：设置了时区偏移时存储时间本身即为 UTC，
+    // 否则存储时间按本机时区解释
+    fn stored_to_audit_time(&self, stored: NaiveDateTime) -> Option<NaiveDateTime> {
+        match self.time_offset {
+            Some(_) => Some(stored),
+            None => Local
+                .from_local_datetime(&stored)
+                .earliest()
+                .map(|dt| dt.naive_utc()),
+        }
+    }
+
+    // 将审计日志的 UTC 时间换算为存储时间
+    fn audit_time_to_stored(&self, utc: NaiveDateTime) -> NaiveDateTime {
+        match self.time_offset {
+            Some(_) => utc,
+            None => Local.from_utc_datetime(&utc).naive_local(),
+        }
+    }
+
+    // 设置计算结焦周期平均温度所需的最少内部温度记录数（装煤与推焦之间，不含端点）；
+    // 不足时平均温度存为 NULL 并标记为低置信度，避免仅凭首尾记录外推。默认 0 不检查
+    pub fn set_min_interior_samples(&mut self, samples: usize) {
+        self.min_interior_samples = samples;
+    }
+
+    // 设置结焦时间是否保留秒精度（默认 HH:mm）
+    pub fn set_duration_seconds_precision(&mut self, enabled: bool) {
+        self.duration_seconds_precision = enabled;
+    }
+
+    // 设置允许记录的温度范围（℃，含端点），超出范围的记录被拒绝，用于拦截漏写小数点等录入错误；
+    // 默认不限制
+    pub fn set_temperature_limits(&mut self, min: f64, max: f64) -> Result<(), CokeError> {
+        if !min.is_finite() || !max.is_finite() || min >= max {
+            return Err(CokeError::InvalidArgument(format!(
+                "无效温度范围: [{}, {}]",
+                min, max
+            )));
+        }
+        self.temperature_limits = Some((min, max));
+        Ok(())
+    }
+
+    // 取消温度范围限制
+    pub fn clear_temperature_limits(&mut self) {
+        self.temperature_limits = None;
     }
 
     pub fn record_temperature(
@@ -24,473 +254,7782 @@ impl CokeOvenSystem {
         time: &str,
         machine_temp: f64,
         coke_temp: f64,
-    ) -> Result<(), String> {
+    ) -> Result<(), CokeError> {
+        self.record_temperature_probe(coke_oven, time, machine_temp, coke_temp, None)
+    }
+
+    // 以已解析的时间记录温度，避免调用方格式化后再重复解析
+    pub fn record_temperature_dt(
+        &mut self,
+        coke_oven: i32,
+        time: NaiveDateTime,
+        machine_temp: f64,
+        coke_temp: f64,
+    ) -> Result<(), CokeError> {
+        self.insert_temperature(coke_oven, time, machine_temp, coke_temp, None)
+    }
+
+    // 写入温度记录，同一时刻（未指定探头）已有记录时更新为新值，用于重新导入修正后的数据；
+    // 返回 true 表示新插入，false 表示更新了已有记录
+    //
+    // 已生成的结焦周期平均温度不会自动更新，需要时调用 recompute_cycle_averages
+    pub fn upsert_temperature(
+        &mut self,
+        coke_oven: i32,
+        time: &str,
+        machine_temp: f64,
+        coke_temp: f64,
+    ) -> Result<bool, CokeError> {
+        if !self.ovens.contains_key(&coke_oven) {
+            return Err(CokeError::InvalidOven(coke_oven));
+        }
+        self.check_temperature_limits(machine_temp, coke_temp)?;
+
+        let time = self.to_stored_time(time)?;
+
+        let tx = self.conn.unchecked_transaction()?;
+        let exists: bool = tx.query_row(
+            "SELECT EXISTS(SELECT 1 FROM temperature_records
+                WHERE coke_oven = ?1 AND time = ?2 AND probe IS NULL)",
+            params![coke_oven, time],
+            |row| row.get(0),
+        )?;
+        tx.execute(
+            "INSERT INTO temperature_records (coke_oven, time, machine_side, coke_side)
+             VALUES (?1, ?2, ?3, ?4)
+             ON CONFLICT(coke_oven, time, COALESCE(probe, '')) DO UPDATE SET
+                 machine_side = excluded.machine_side,
+                 coke_side = excluded.coke_side",
+            params![coke_oven, time, machine_temp, coke_temp],
+        )?;
+        tx.commit()?;
+
+        Ok(!exists)
+    }
+
+    // 记录指定测温探头的温度，probe 为 None 时与 record_temperature 相同
+    //
+    // 同一时刻不同探头的记录互不冲突，同一探头（含未指定探头）同一时刻只能有一条
+    pub fn record_temperature_probe(
+        &mut self,
+        coke_oven: i32,
+        time: &str,
+        machine_temp: f64,
+        coke_temp: f64,
+        probe: Option<&str>,
+    ) -> Result<(), CokeError> {
+        let time = self.parse_input_time(time)?;
+        self.insert_temperature(coke_oven, time, machine_temp, coke_temp, probe)
+    }
+
+    // 写入一条温度记录（时间为存储时间），同一探头同一时刻已有记录时返回 Conflict
+    fn insert_temperature(
+        &self,
+        coke_oven: i32,
+        time: NaiveDateTime,
+        machine_temp: f64,
+        coke_temp: f64,
+        probe: Option<&str>,
+    ) -> Result<(), CokeError> {
         if !self.ovens.contains_key(&coke_oven) {
-            return Err(format!("无效焦炉编号: {}", coke_oven));
+            return Err(CokeError::InvalidOven(coke_oven));
         }
+        self.check_temperature_limits(machine_temp, coke_temp)?;
 
-        let _time_dt = crate::models::parse_time(time)?;
+        let time = crate::models::format_time(time);
 
         self.conn
-            .execute(
-                "INSERT INTO temperature_records (coke_oven, time, machine_side, coke_side)
-             VALUES (?1, ?2, ?3, ?4)",
-                params![coke_oven, time, machine_temp, coke_temp],
+            .prepare_cached(
+                "INSERT INTO temperature_records (coke_oven, time, machine_side, coke_side, probe)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
             )
-            .map_err(|e| e.to_string())?;
+            .and_then(|mut stmt| {
+                stmt.execute(params![coke_oven, time, machine_temp, coke_temp, probe])
+            })
+            .map_err(|e| {
+                unique_violation_as_conflict(e, || match probe {
+                    Some(probe) => format!("焦炉{}探头{}在{}已有温度记录", coke_oven, probe, time),
+                    None => format!("焦炉{}在{}已有温度记录", coke_oven, time),
+                })
+            })?;
 
         Ok(())
     }
 
+    // 查询指定探头在时间范围内（含边界）的温度记录，probe 为 None 时查询未指定探头的记录
+    pub fn probe_temperatures(
+        &self,
+        coke_oven: i32,
+        probe: Option<&str>,
+        start: &str,
+        end: &str,
+    ) -> Result<Vec<TempRecord>, CokeError> {
+        if !self.ovens.contains_key(&coke_oven) {
+            return Err(CokeError::InvalidOven(coke_oven));
+        }
+
+        let start = &crate::models::normalize_time(start)?;
+        let end = &crate::models::normalize_time(end)?;
+
+        let mut stmt = self.conn.prepare(
+            "SELECT time, machine_side, coke_side FROM temperature_records
+            WHERE coke_oven = ?1 AND probe IS ?2 AND time >= ?3 AND time <= ?4
+            ORDER BY time ASC",
+        )?;
+        let records = stmt
+            .query_map(params![coke_oven, probe, start, end], map_temp_row)?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(records)
+    }
+
+    // 记录装煤/推焦操作，返回本次推焦是否生成了结焦周期
     pub fn record_operation(
         &mut self,
         coke_oven: i32,
         chamber: &str,
         op_type: &str,
         time: &str,
-    ) -> Result<(), String> {
-        let oven = self
-            .ovens
-            .get(&coke_oven)
-            .ok_or_else(|| format!("无效焦炉编号: {}", coke_oven))?;
-
-        if !oven.is_valid_chamber(chamber) {
-            return Err(format!("焦炉{}中无效的炭化室: {}", coke_oven, chamber));
-        }
-
-        if op_type != "LOAD" && op_type != "PUSH" {
-            return Err("无效操作类型".to_string());
-        }
+    ) -> Result<bool, CokeError> {
+        let time = self.parse_input_time(time)?;
+        self.record_operation_dt(coke_oven, chamber, op_type, time)
+    }
 
-        let _time_dt = crate::models::parse_time(time)?;
+    // 以时钟的当前时间记录操作，返回是否生成了结焦周期
+    pub fn record_operation_now(
+        &mut self,
+        coke_oven: i32,
+        chamber: &str,
+        op_type: &str,
+    ) -> Result<bool, CokeError> {
+        let now = crate::models::format_time(self.clock.now());
+        self.record_operation(coke_oven, chamber, op_type, &now)
+    }
 
-        self.conn
-            .execute(
-                "INSERT INTO operation_records (coke_oven, chamber, operation_type, time)
-             VALUES (?1, ?2, ?3, ?4)",
-                params![coke_oven, chamber, op_type, time],
-            )
-            .map_err(|e| e.to_string())?;
+    // 以已解析的时间记录操作，推焦时计算结焦周期，返回是否生成了结焦周期
+    pub fn record_operation_dt(
+        &mut self,
+        coke_oven: i32,
+        chamber: &str,
+        op_type: &str,
+        time: NaiveDateTime,
+    ) -> Result<bool, CokeError> {
+        let time = &crate::models::format_time(time);
+        self.insert_operation(coke_oven, chamber, op_type, time)?;
 
         if op_type == "PUSH" {
-            self.try_calculate_coking_cycle(coke_oven, chamber, time)
-                .map_err(|e| e.to_string())?;
+            return Ok(self.try_calculate_coking_cycle(coke_oven, chamber, time)?);
         }
 
-        Ok(())
+        Ok(false)
     }
 
-    fn try_calculate_coking_cycle(
+    // 记录推焦操作，生成结焦周期时返回该周期（含平均温度），推焦前没有装煤记录时返回 None
+    pub fn record_push(
         &mut self,
         coke_oven: i32,
         chamber: &str,
-        push_time: &str,
-    ) -> Result<(), rusqlite::Error> {
-        let loading_time: Option<String> = self
+        time: &str,
+    ) -> Result<Option<CokingCycle>, CokeError> {
+        let time = self.parse_input_time(time)?;
+        if !self.record_operation_dt(coke_oven, chamber, "PUSH", time)? {
+            return Ok(None);
+        }
+
+        let cycle = self
             .conn
+            .prepare(&format!(
+                "SELECT {} FROM coking_cycles
+                WHERE coke_oven = ?1 AND chamber = ?2 AND push_time = ?3",
+                CYCLE_COLUMNS
+            ))?
             .query_row(
-                "SELECT time FROM operation_records 
-             WHERE coke_oven = ?1 
-               AND chamber = ?2 
-               AND operation_type = 'LOAD' 
-               AND time < ?3
-             ORDER BY time DESC LIMIT 1",
-                params![coke_oven, chamber, push_time],
-                |row| row.get(0),
-            )
-            .optional()?;
+                params![coke_oven, chamber, crate::models::format_time(time)],
+                map_cycle_row,
+            )?;
+
+        Ok(Some(cycle))
+    }
 
-        if let Some(loading_time) = loading_time {
-            let load_dt = crate::models::parse_time(&loading_time)
-                .map_err(|_| rusqlite::Error::InvalidQuery)?;
-            let push_dt =
-                crate::models::parse_time(push_time).map_err(|_| rusqlite::Error::InvalidQuery)?;
+    // 仅写入操作记录，推焦时不计算结焦周期，用于批量导入历史数据；
+    // 导入完成后调用 rebuild_all_cycles 统一计算
+    pub fn record_operation_deferred(
+        &mut self,
+        coke_oven: i32,
+        chamber: &str,
+        op_type: &str,
+        time: &str,
+    ) -> Result<(), CokeError> {
+        let time = &self.validate_operation(coke_oven, chamber, op_type, time)?;
+        self.insert_operation(coke_oven, chamber, op_type, time)?;
+        Ok(())
+    }
 
-            let duration = push_dt.signed_duration_since(load_dt);
-            let duration_minutes = duration.num_minutes() as i32;
+    // 根据操作记录重建焦炉的全部结焦周期，返回重建后的周期数
+    //
+    // 按炭化室、时间顺序一次遍历操作记录，按当前配对策略为每次推焦选择装煤记录，
+    // 与逐条记录时的计算结果一致；在单个事务中删除旧周期并写入新周期
+    pub fn rebuild_all_cycles(&mut self, coke_oven: i32) -> Result<usize, CokeError> {
+        if !self.ovens.contains_key(&coke_oven) {
+            return Err(CokeError::InvalidOven(coke_oven));
+        }
 
-            // 转换为 HH:mm 格式
-            let duration_hhmm = minutes_to_hhmm(duration_minutes);
+        let tx = self.conn.unchecked_transaction()?;
 
-            let (avg_machine, avg_coke) =
-                match self.calculate_avg_temperature(coke_oven, &loading_time, push_time) {
-                    Ok((m, c)) => (Some(m), Some(c)),
-                    Err(e) => {
-                        eprintln!("计算平均温度失败：{}", e);
-                        (None, None)
-                    }
-                };
+        tx.execute(
+            "DELETE FROM coking_cycles WHERE coke_oven = ?1",
+            params![coke_oven],
+        )?;
 
-            self.conn.execute(
-                "INSERT INTO coking_cycles (
-                    coke_oven, chamber, loading_time, push_time, 
-                    duration_hhmm, avg_temp_machine, avg_temp_coke
-                 ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
-                params![
-                    coke_oven,
-                    chamber,
-                    loading_time,
-                    push_time,
-                    duration_hhmm,
-                    avg_machine,
-                    avg_coke
-                ],
+        let operations: Vec<(i64, String, String, String)> = {
+            let mut stmt = tx.prepare(
+                "SELECT id, chamber, operation_type, time FROM operation_records
+                    WHERE coke_oven = ?1
+                    ORDER BY chamber ASC, time ASC",
             )?;
+            let rows = stmt.query_map(params![coke_oven], |row| {
+                Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?))
+            })?;
+            rows.collect::<Result<Vec<_>, _>>()?
+        };
+
+        let mut rebuilt = 0;
+        let mut last_load: Option<(String, i64, String)> = None;
+        for (id, chamber, op_type, time) in operations {
+            if last_load.as_ref().is_some_and(|(c, _, _)| *c != chamber) {
+                last_load = None;
+            }
+
+            if op_type == "LOAD" {
+                if self.load_pairing == LoadPairing::Latest || last_load.is_none() {
+                    last_load = Some((chamber, id, time));
+                }
+            } else {
+                if let Some((_, load_id, loading_time)) = &last_load {
+                    self.insert_cycle(coke_oven, &chamber, (*load_id, loading_time), (id, &time))?;
+                    rebuilt += 1;
+                }
+                if self.load_pairing == LoadPairing::Earliest {
+                    last_load = None;
+                }
+            }
         }
 
-        Ok(())
+        tx.commit()?;
+        Ok(rebuilt)
     }
 
-    // 计算装煤到推焦期间的平均温度（通过积分）
-    fn calculate_avg_temperature(
-        &self,
-        coke_oven: i32,
-        start_time: &str,
-        end_time: &str,
-    ) -> Result<(f64, f64), rusqlite::Error> {
-        let start_dt =
-            crate::models::parse_time(start_time).map_err(|_| rusqlite::Error::InvalidQuery)?;
-        let end_dt =
-            crate::models::parse_time(end_time).map_err(|_| rusqlite::Error::InvalidQuery)?;
-
-        // 查询装煤时间点前后的温度记录
-        let prev_start = self.get_nearest_temp_record(coke_oven, start_time, true)?;
-        let next_start = self.get_nearest_temp_record(coke_oven, start_time, false)?;
-        let prev_end = self.get_nearest_temp_record(coke_oven, end_time, true)?;
-        let next_end = self.get_nearest_temp_record(coke_oven, end_time, false)?;
+    // 增量计算结焦周期：只处理水位（已处理的最大操作记录 id）之后的新操作，
+    // 为尚无结焦周期的推焦按当前配对策略生成周期，并推进水位，返回处理的操作数
+    //
+    // 水位保存在 metadata 表中；晚于推焦补录的装煤不会回补已处理的推焦，需用 rebuild_all_cycles
+    pub fn process_pending_operations(&mut self) -> Result<usize, CokeError> {
+        let tx = self.conn.unchecked_transaction()?;
 
-        // 获取中间记录
-        let middle_records = self.get_temp_records_in_range(coke_oven, start_time, end_time)?;
+        let watermark: i64 = tx
+            .query_row(
+                "SELECT CAST(value AS INTEGER) FROM metadata WHERE key = ?1",
+                params![CYCLE_WATERMARK_KEY],
+                |row| row.get(0),
+            )
+            .optional()?
+            .unwrap_or(0);
 
-        // 计算边界点温度
-        let start_temp = crate::models::interpolate_temp(&prev_start, &next_start, start_dt)
-            .ok_or(rusqlite::Error::QueryReturnedNoRows)?;
-        let end_temp = crate::models::interpolate_temp(&prev_end, &next_end, end_dt)
-            .ok_or(rusqlite::Error::QueryReturnedNoRows)?;
+        let operations: Vec<(i64, i32, String, String, String)> = {
+            let mut stmt = tx.prepare(
+                "SELECT id, coke_oven, chamber, operation_type, time FROM operation_records
+                    WHERE id > ?1
+                    ORDER BY id ASC",
+            )?;
+            let rows = stmt.query_map(params![watermark], |row| {
+                Ok((
+                    row.get(0)?,
+                    row.get(1)?,
+                    row.get(2)?,
+                    row.get(3)?,
+                    row.get(4)?,
+                ))
+            })?;
+            rows.collect::<Result<Vec<_>, _>>()?
+        };
 
-        // 构建时间点序列
-        let mut points = Vec::new();
-        points.push(TimeTempPoint {
-            time: start_dt,
-            machine: start_temp.0,
-            coke: start_temp.1,
-        });
+        let Some(&(last_id, ..)) = operations.last() else {
+            return Ok(0);
+        };
 
-        for record in middle_records {
-            points.push(TimeTempPoint {
-                time: record.time,
-                machine: record.machine_side,
-                coke: record.coke_side,
-            });
+        for (id, coke_oven, chamber, op_type, time) in &operations {
+            if op_type == "PUSH" {
+                self.compute_cycle_for_push(*coke_oven, chamber, *id, time)?;
+            }
         }
 
-        points.push(TimeTempPoint {
-            time: end_dt,
-            machine: end_temp.0,
-            coke: end_temp.1,
-        });
+        tx.execute(
+            "INSERT INTO metadata (key, value) VALUES (?1, ?2)
+                ON CONFLICT(key) DO UPDATE SET value = excluded.value",
+            params![CYCLE_WATERMARK_KEY, last_id.to_string()],
+        )?;
+        tx.commit()?;
 
-        // 计算积分
-        let (total_machine_area, total_coke_area, total_duration) = calculate_integral(&points);
+        Ok(operations.len())
+    }
 
-        if total_duration == 0.0 {
-            Ok((points[0].machine, points[0].coke))
-        } else {
-            let avg_machine = total_machine_area / total_duration;
-            let avg_coke = total_coke_area / total_duration;
-            Ok((avg_machine, avg_coke))
+    // 为一次推焦按当前配对策略生成结焦周期，已有周期或找不到装煤记录时跳过，
+    // 返回是否生成了周期；在调用方的事务中执行
+    fn compute_cycle_for_push(
+        &self,
+        coke_oven: i32,
+        chamber: &str,
+        push_id: i64,
+        push_time: &str,
+    ) -> Result<bool, rusqlite::Error> {
+        // 逐条记录时已生成的周期不重复计算
+        let exists: bool = self.conn.query_row(
+            "SELECT EXISTS(
+                SELECT 1 FROM coking_cycles
+                WHERE coke_oven = ?1 AND chamber = ?2 AND push_time = ?3
+            )",
+            params![coke_oven, chamber, push_time],
+            |row| row.get(0),
+        )?;
+        if exists {
+            return Ok(false);
+        }
+
+        match self.find_loading_time(coke_oven, chamber, push_time)? {
+            Some((load_id, loading_time)) => {
+                self.insert_cycle(
+                    coke_oven,
+                    chamber,
+                    (load_id, &loading_time),
+                    (push_id, push_time),
+                )?;
+                Ok(true)
+            }
+            None => Ok(false),
         }
     }
 
-    // 辅助方法：获取最近温度记录
-    fn get_nearest_temp_record(
+    // 诊断两次推焦之间（或最后一次推焦之后）存在多次装煤的情况，
+    // 返回炭化室及该组装煤时间，按炭化室自然顺序、时间升序排列
+    pub fn multiple_unpaired_loads(
         &self,
         coke_oven: i32,
-        time: &str,
-        before: bool,
-    ) -> Result<Option<TempRecord>, rusqlite::Error> {
-        let query = if before {
-            "SELECT time, machine_side, coke_side FROM temperature_records
-             WHERE coke_oven = ?1 AND time <= ?2
-             ORDER BY time DESC LIMIT 1"
-        } else {
-            "SELECT time, machine_side, coke_side FROM temperature_records
-             WHERE coke_oven = ?1 AND time > ?2
-             ORDER BY time ASC LIMIT 1"
-        };
+    ) -> Result<Vec<(String, Vec<NaiveDateTime>)>, CokeError> {
+        if !self.ovens.contains_key(&coke_oven) {
+            return Err(CokeError::InvalidOven(coke_oven));
+        }
+
+        let mut stmt = self.conn.prepare(
+            "SELECT id, coke_oven, chamber, operation_type, time FROM operation_records
+                WHERE coke_oven = ?1
+                ORDER BY chamber ASC, time ASC",
+        )?;
+        let operations = stmt
+            .query_map(params![coke_oven], map_operation_row)?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut groups: Vec<(String, Vec<NaiveDateTime>)> = Vec::new();
+        let mut pending: Vec<NaiveDateTime> = Vec::new();
+        let mut current_chamber: Option<String> = None;
+        for op in operations {
+            if current_chamber.as_deref() != Some(op.chamber.as_str()) {
+                if let Some(chamber) = current_chamber.take() {
+                    if pending.len() > 1 {
+                        groups.push((chamber, std::mem::take(&mut pending)));
+                    }
+                }
+                pending.clear();
+                current_chamber = Some(op.chamber.clone());
+            }
+
+            if op.operation_type == "LOAD" {
+                pending.push(op.time);
+            } else {
+                if pending.len() > 1 {
+                    groups.push((op.chamber.clone(), std::mem::take(&mut pending)));
+                }
+                pending.clear();
+            }
+        }
+        if let Some(chamber) = current_chamber {
+            if pending.len() > 1 {
+                groups.push((chamber, pending));
+            }
+        }
+
+        groups.sort_by(|(a, a_times), (b, b_times)| {
+            crate::models::natural_chamber_key(a)
+                .cmp(&crate::models::natural_chamber_key(b))
+                .then_with(|| a.cmp(b))
+                .then_with(|| a_times.cmp(b_times))
+        });
+        Ok(groups)
+    }
+
+    // 检查炭化室在指定推焦时间是否已有结焦周期记录
+    pub fn cycle_exists(
+        &self,
+        coke_oven: i32,
+        chamber: &str,
+        push_time: &str,
+    ) -> Result<bool, CokeError> {
+        self.validate_chamber(coke_oven, chamber)?;
+
+        let push_time = &crate::models::normalize_time(push_time)?;
+
+        self.conn
+            .query_row(
+                "SELECT EXISTS(
+                    SELECT 1 FROM coking_cycles
+                    WHERE coke_oven = ?1 AND chamber = ?2 AND push_time = ?3
+                )",
+                params![coke_oven, chamber, push_time],
+                |row| row.get(0),
+            )
+            .map_err(CokeError::from)
+    }
+
+    // 为结焦周期添加备注（如“疑似炉门泄漏”），周期 id 取自 CokingCycle::id；
+    // 备注按（焦炉, 炭化室, 推焦时间）关联周期，重建周期后仍可读取
+    pub fn add_cycle_note(&mut self, cycle_id: i64, note: &str) -> Result<(), CokeError> {
+        let inserted = self.conn.execute(
+            "INSERT INTO cycle_notes (coke_oven, chamber, push_time, note)
+                SELECT coke_oven, chamber, push_time, ?2 FROM coking_cycles WHERE id = ?1",
+            params![cycle_id, note],
+        )?;
+        if inserted == 0 {
+            return Err(CokeError::NoData(format!("结焦周期{}不存在", cycle_id)));
+        }
+        Ok(())
+    }
+
+    // 获取结焦周期的全部备注，按添加顺序排列
+    pub fn cycle_notes(&self, cycle_id: i64) -> Result<Vec<String>, CokeError> {
+        let mut stmt = self.conn.prepare(
+            "SELECT n.note FROM cycle_notes n
+                JOIN coking_cycles c
+                  ON c.coke_oven = n.coke_oven AND c.chamber = n.chamber
+                 AND c.push_time = n.push_time
+                WHERE c.id = ?1
+                ORDER BY n.id ASC",
+        )?;
+        let notes = stmt
+            .query_map(params![cycle_id], |row| row.get(0))?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(notes)
+    }
+
+    // 查找结焦时长为负的周期（早期推焦先于装煤的错误数据），返回周期 id
+    pub fn find_negative_durations(&self) -> Result<Vec<i64>, CokeError> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT id FROM coking_cycles WHERE duration_minutes < 0 ORDER BY id")?;
+
+        let ids = stmt
+            .query_map([], |row| row.get(0))?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(ids)
+    }
+
+    // 删除结焦时长不大于 0 的无效周期，返回删除的行数
+    pub fn purge_invalid_cycles(&mut self) -> Result<usize, CokeError> {
+        self.conn
+            .execute("DELETE FROM coking_cycles WHERE duration_minutes <= 0", [])
+            .map_err(CokeError::from)
+    }
+
+    // 计算焦炉全部结焦周期的平均时长（分钟），没有周期时返回 None
+    pub fn average_cycle_duration_minutes(&self, coke_oven: i32) -> Result<Option<f64>, CokeError> {
+        if !self.ovens.contains_key(&coke_oven) {
+            return Err(CokeError::InvalidOven(coke_oven));
+        }
+
+        self.conn
+            .query_row(
+                "SELECT AVG(duration_minutes) FROM coking_cycles WHERE coke_oven = ?1",
+                params![coke_oven],
+                |row| row.get(0),
+            )
+            .map_err(CokeError::from)
+    }
+
+    // 获取焦炉配置的全部炭化室（按编号自然排序，"2#" 在 "10#" 之前）
+    pub fn chambers(&self, coke_oven: i32) -> Result<Vec<String>, CokeError> {
+        let oven = self
+            .ovens
+            .get(&coke_oven)
+            .ok_or(CokeError::InvalidOven(coke_oven))?;
+
+        let mut chambers = oven.chambers.clone();
+        crate::models::sort_chambers_naturally(&mut chambers);
+        Ok(chambers)
+    }
+
+    // 查询全部已配置炭化室的当前状态，按自然顺序排序；没有操作记录的炭化室为空炉
+    pub fn chamber_states(&self, coke_oven: i32) -> Result<Vec<(String, ChamberState)>, CokeError> {
+        let chambers = self.chambers(coke_oven)?;
+
+        let mut stmt = self.conn.prepare(
+            "SELECT o.chamber, o.operation_type, o.time FROM operation_records o
+                WHERE o.coke_oven = ?1
+                  AND o.time = (
+                      SELECT MAX(time) FROM operation_records
+                      WHERE coke_oven = o.coke_oven AND chamber = o.chamber
+                  )",
+        )?;
+        let latest = stmt
+            .query_map(params![coke_oven], |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, String>(2)?,
+                ))
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut loaded: HashMap<String, NaiveDateTime> = HashMap::new();
+        for (chamber, op_type, time) in latest {
+            if op_type == "LOAD" {
+                loaded.insert(chamber, crate::models::parse_time(&time)?);
+            }
+        }
+
+        Ok(chambers
+            .into_iter()
+            .map(|chamber| {
+                let state = match loaded.get(&chamber) {
+                    Some(&since) => ChamberState::Loaded { since },
+                    None => ChamberState::Empty,
+                };
+                (chamber, state)
+            })
+            .collect())
+    }
+
+    // 预计装煤中炭化室的推焦时间：最近一次装煤（其后没有推焦）时间加上额定结焦时间；
+    // 炭化室当前未装煤时返回 None，焦炉未配置额定结焦时间时返回错误
+    pub fn estimated_push_time(
+        &self,
+        coke_oven: i32,
+        chamber: &str,
+    ) -> Result<Option<NaiveDateTime>, CokeError> {
+        let oven = self.validate_chamber(coke_oven, chamber)?;
+
+        let expected = oven
+            .expected_cycle_minutes
+            .ok_or_else(|| CokeError::NoData(format!("焦炉{}未配置额定结焦时间", coke_oven)))?;
+
+        let latest: Option<(String, String)> = self
+            .conn
+            .query_row(
+                "SELECT operation_type, time FROM operation_records
+                    WHERE coke_oven = ?1 AND chamber = ?2
+                    ORDER BY time DESC LIMIT 1",
+                params![coke_oven, chamber],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .optional()?;
+
+        match latest {
+            Some((op_type, time)) if op_type == "LOAD" => Ok(Some(
+                crate::models::parse_time(&time)? + Duration::minutes(expected as i64),
+            )),
+            _ => Ok(None),
+        }
+    }
+
+    // 严格模式记录操作：推焦前没有装煤记录时返回错误且不写入推焦记录
+    pub fn record_operation_strict(
+        &mut self,
+        coke_oven: i32,
+        chamber: &str,
+        op_type: &str,
+        time: &str,
+    ) -> Result<bool, CokeError> {
+        let time = &self.validate_operation(coke_oven, chamber, op_type, time)?;
+
+        if op_type == "PUSH" && self.find_loading_time(coke_oven, chamber, time)?.is_none() {
+            return Err(CokeError::Conflict(format!(
+                "焦炉{}炭化室{}在{}之前没有装煤记录",
+                coke_oven, chamber, time
+            )));
+        }
+
+        let time = crate::models::parse_time(time)?;
+        self.record_operation_dt(coke_oven, chamber, op_type, time)
+    }
+
+    // 幂等记录操作：完全相同的重复记录视为成功但不插入，返回是否插入了新记录
+    //
+    // 同一时间已存在不同类型的操作时仍返回错误
+    pub fn record_operation_idempotent(
+        &mut self,
+        coke_oven: i32,
+        chamber: &str,
+        op_type: &str,
+        time: &str,
+    ) -> Result<bool, CokeError> {
+        let time = &self.validate_operation(coke_oven, chamber, op_type, time)?;
+
+        if let Err(e) = self.insert_operation(coke_oven, chamber, op_type, time) {
+            let CokeError::Conflict(_) = e else {
+                return Err(e);
+            };
+            let existing: String = self.conn.query_row(
+                "SELECT operation_type FROM operation_records
+                    WHERE coke_oven = ?1 AND chamber = ?2 AND time = ?3",
+                params![coke_oven, chamber, time],
+                |row| row.get(0),
+            )?;
+            if existing != op_type {
+                return Err(CokeError::Conflict(format!(
+                    "焦炉{}炭化室{}在{}已有{}操作",
+                    coke_oven, chamber, time, existing
+                )));
+            }
+            return Ok(false);
+        }
+
+        if op_type == "PUSH" {
+            self.try_calculate_coking_cycle(coke_oven, chamber, time)?;
+        }
+
+        Ok(true)
+    }
+
+    // 校验并写入一条操作记录（时间为标准格式的存储时间），返回新记录 id；
+    // 同一炭化室同一时刻已有记录时返回 Conflict。所有操作写入路径共用此方法
+    fn insert_operation(
+        &self,
+        coke_oven: i32,
+        chamber: &str,
+        op_type: &str,
+        time: &str,
+    ) -> Result<i64, CokeError> {
+        self.validate_operation_target(coke_oven, chamber, op_type)?;
 
         self.conn
-            .query_row(query, params![coke_oven, time], |row| {
-                let time_str: String = row.get(0)?;
-                let time_dt = crate::models::parse_time(&time_str)
-                    .map_err(|_| rusqlite::Error::InvalidQuery)?;
-                Ok(TempRecord {
-                    time: time_dt,
-                    machine_side: row.get(1)?,
-                    coke_side: row.get(2)?,
+            .prepare_cached(
+                "INSERT INTO operation_records (coke_oven, chamber, operation_type, time)
+             VALUES (?1, ?2, ?3, ?4)",
+            )
+            .and_then(|mut stmt| stmt.execute(params![coke_oven, chamber, op_type, time]))
+            .map_err(|e| {
+                unique_violation_as_conflict(e, || {
+                    format!("焦炉{}炭化室{}在{}已有操作记录", coke_oven, chamber, time)
                 })
+            })?;
+
+        Ok(self.conn.last_insert_rowid())
+    }
+
+    // 校验操作记录的焦炉、炭化室、操作类型和时间，返回标准格式的存储时间
+    fn validate_operation(
+        &self,
+        coke_oven: i32,
+        chamber: &str,
+        op_type: &str,
+        time: &str,
+    ) -> Result<String, CokeError> {
+        self.validate_operation_target(coke_oven, chamber, op_type)?;
+        self.to_stored_time(time)
+    }
+
+    // 校验焦炉与炭化室，返回炭化室所在的焦炉
+    fn validate_chamber(&self, coke_oven: i32, chamber: &str) -> Result<&CokeOven, CokeError> {
+        let oven = self
+            .ovens
+            .get(&coke_oven)
+            .ok_or(CokeError::InvalidOven(coke_oven))?;
+
+        if !oven.is_valid_chamber(chamber) {
+            return Err(CokeError::InvalidChamber {
+                oven: coke_oven,
+                chamber: chamber.to_string(),
+            });
+        }
+
+        Ok(oven)
+    }
+
+    // 校验焦炉、炭化室与操作类型
+    fn validate_operation_target(
+        &self,
+        coke_oven: i32,
+        chamber: &str,
+        op_type: &str,
+    ) -> Result<(), CokeError> {
+        self.validate_chamber(coke_oven, chamber)?;
+
+        if op_type != "LOAD" && op_type != "PUSH" {
+            return Err(CokeError::InvalidOperationType(op_type.to_string()));
+        }
+
+        Ok(())
+    }
+
+    // 查找炭化室中时间区间 [装煤, 推焦] 相互重叠的结焦周期对，仅首尾相接不算重叠；
+    // 每对按装煤时间先后排列
+    pub fn find_overlapping_cycles(
+        &self,
+        coke_oven: i32,
+        chamber: &str,
+    ) -> Result<Vec<(CokingCycle, CokingCycle)>, CokeError> {
+        self.validate_chamber(coke_oven, chamber)?;
+
+        let pairs: Vec<(i64, i64)> = {
+            let mut stmt = self.conn.prepare(
+                "SELECT a.id, b.id FROM coking_cycles a
+                    JOIN coking_cycles b
+                      ON b.coke_oven = a.coke_oven AND b.chamber = a.chamber AND b.id <> a.id
+                     AND (a.loading_time < b.loading_time
+                          OR (a.loading_time = b.loading_time AND a.id < b.id))
+                    WHERE a.coke_oven = ?1 AND a.chamber = ?2
+                      AND b.loading_time < a.push_time AND a.loading_time < b.push_time
+                    ORDER BY a.loading_time ASC, b.loading_time ASC",
+            )?;
+            let rows = stmt.query_map(params![coke_oven, chamber], |row| {
+                Ok((row.get(0)?, row.get(1)?))
+            })?;
+            rows.collect::<Result<Vec<_>, _>>()?
+        };
+        if pairs.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let cycles: HashMap<i64, CokingCycle> = self
+            .query_chamber_cycles(coke_oven, chamber)?
+            .into_iter()
+            .map(|c| (c.id, c))
+            .collect();
+
+        Ok(pairs
+            .into_iter()
+            .map(|(a, b)| (cycles[&a].clone(), cycles[&b].clone()))
+            .collect())
+    }
+
+    // 统计每个炭化室的结焦周期数，包含周期数为 0 的已配置炭化室，按自然顺序排序
+    pub fn cycle_counts(&self, coke_oven: i32) -> Result<Vec<(String, i64)>, CokeError> {
+        let oven = self
+            .ovens
+            .get(&coke_oven)
+            .ok_or(CokeError::InvalidOven(coke_oven))?;
+
+        let mut counts: HashMap<String, i64> =
+            oven.chambers.iter().map(|c| (c.clone(), 0)).collect();
+
+        let mut stmt = self.conn.prepare(
+            "SELECT chamber, COUNT(*) FROM coking_cycles
+                WHERE coke_oven = ?1
+                GROUP BY chamber",
+        )?;
+        let rows = stmt.query_map(params![coke_oven], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)?))
+        })?;
+        for row in rows {
+            let (chamber, count) = row?;
+            counts.insert(chamber, count);
+        }
+
+        let mut chambers: Vec<String> = counts.keys().cloned().collect();
+        crate::models::sort_chambers_naturally(&mut chambers);
+        Ok(chambers
+            .into_iter()
+            .map(|c| {
+                let count = counts[&c];
+                (c, count)
             })
-            .optional()
+            .collect())
     }
 
-    // 辅助方法：获取时间范围内的温度记录
-    fn get_temp_records_in_range(
+    // 查询推焦时间在 [start, end] 内完成结焦周期的炭化室（去重），按自然顺序排列，用于班报
+    pub fn chambers_pushed_between(
         &self,
         coke_oven: i32,
         start: &str,
         end: &str,
-    ) -> Result<Vec<TempRecord>, rusqlite::Error> {
+    ) -> Result<Vec<String>, CokeError> {
+        if !self.ovens.contains_key(&coke_oven) {
+            return Err(CokeError::InvalidOven(coke_oven));
+        }
+
+        let start = &crate::models::normalize_time(start)?;
+        let end = &crate::models::normalize_time(end)?;
+
         let mut stmt = self.conn.prepare(
-            "SELECT time, machine_side, coke_side FROM temperature_records
-            WHERE coke_oven = ?1 AND time > ?2 AND time < ?3
-            ORDER BY time ASC",
+            "SELECT DISTINCT chamber FROM coking_cycles
+                WHERE coke_oven = ?1 AND push_time >= ?2 AND push_time <= ?3",
+        )?;
+        let mut chambers = stmt
+            .query_map(params![coke_oven, start, end], |row| row.get(0))?
+            .collect::<Result<Vec<String>, _>>()?;
+
+        crate::models::sort_chambers_naturally(&mut chambers);
+        Ok(chambers)
+    }
+
+    // 查询结焦时间过短的周期（通常意味着装煤或推焦记录有误）
+    pub fn implausibly_short_cycles(
+        &self,
+        coke_oven: i32,
+        min_minutes: i32,
+    ) -> Result<Vec<CokingCycle>, CokeError> {
+        if !self.ovens.contains_key(&coke_oven) {
+            return Err(CokeError::InvalidOven(coke_oven));
+        }
+
+        let cycles = self.query_cycles(coke_oven)?;
+
+        Ok(cycles
+            .into_iter()
+            .filter(|c| c.duration_minutes < min_minutes)
+            .collect())
+    }
+
+    // 查询平均温度缺失的结焦周期（计算失败或样本不足），可针对这些周期调用 recompute_cycle_averages
+    pub fn cycles_missing_averages(&self, coke_oven: i32) -> Result<Vec<CokingCycle>, CokeError> {
+        if !self.ovens.contains_key(&coke_oven) {
+            return Err(CokeError::InvalidOven(coke_oven));
+        }
+
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT {} FROM coking_cycles
+            WHERE coke_oven = ?1 AND (avg_temp_machine IS NULL OR avg_temp_coke IS NULL)
+            ORDER BY push_time ASC",
+            CYCLE_COLUMNS
+        ))?;
+        let cycles = stmt
+            .query_map(params![coke_oven], map_cycle_row)?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(cycles)
+    }
+
+    // 重新计算炭化室全部结焦周期的平均温度（温度记录修正后使用）
+    pub fn recompute_cycle_averages(
+        &mut self,
+        coke_oven: i32,
+        chamber: &str,
+    ) -> Result<usize, CokeError> {
+        self.validate_chamber(coke_oven, chamber)?;
+
+        let cycles: Vec<(i64, String, String)> = {
+            let mut stmt = self.conn.prepare(
+                "SELECT id, loading_time, push_time FROM coking_cycles
+                    WHERE coke_oven = ?1 AND chamber = ?2
+                    ORDER BY push_time ASC",
+            )?;
+            let rows = stmt.query_map(params![coke_oven, chamber], |row| {
+                Ok((row.get(0)?, row.get(1)?, row.get(2)?))
+            })?;
+            rows.collect::<Result<Vec<_>, _>>()?
+        };
+
+        let mut updated = 0;
+        for (id, loading_time, push_time) in cycles {
+            let (avg_machine, avg_coke, low_confidence) =
+                self.cycle_averages(coke_oven, &loading_time, &push_time)?;
+
+            updated += self.conn.execute(
+                "UPDATE coking_cycles SET avg_temp_machine = ?1, avg_temp_coke = ?2,
+                    low_confidence = ?3
+                    WHERE id = ?4",
+                params![avg_machine, avg_coke, low_confidence, id],
+            )?;
+        }
+
+        Ok(updated)
+    }
+
+    // 计算时间窗口内各炭化室结焦周期数的基尼系数（0 表示完全均衡）
+    pub fn utilization_inequality(
+        &self,
+        coke_oven: i32,
+        start: &str,
+        end: &str,
+    ) -> Result<f64, CokeError> {
+        let oven = self
+            .ovens
+            .get(&coke_oven)
+            .ok_or(CokeError::InvalidOven(coke_oven))?;
+
+        let start = &crate::models::normalize_time(start)?;
+        let end = &crate::models::normalize_time(end)?;
+
+        let mut stmt = self.conn.prepare(
+            "SELECT chamber, COUNT(*) FROM coking_cycles
+                WHERE coke_oven = ?1 AND push_time >= ?2 AND push_time <= ?3
+                GROUP BY chamber",
         )?;
 
-        let records = stmt
-            .query_map(params![coke_oven, start, end], |row| {
-                let time_str: String = row.get(0)?;
-                let time_dt = crate::models::parse_time(&time_str)
-                    .map_err(|_| rusqlite::Error::InvalidQuery)?;
-                Ok(TempRecord {
-                    time: time_dt,
-                    machine_side: row.get(1)?,
-                    coke_side: row.get(2)?,
-                })
-            })?
-            .collect::<Result<Vec<_>, _>>()?;
+        let counts = stmt
+            .query_map(params![coke_oven, start, end], |row| {
+                Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)?))
+            })?
+            .collect::<Result<HashMap<_, _>, _>>()?;
+
+        // 未出现的炭化室计为 0 次
+        let values: Vec<f64> = oven
+            .chambers
+            .iter()
+            .map(|c| counts.get(c).copied().unwrap_or(0) as f64)
+            .collect();
+
+        Ok(gini_coefficient(&values))
+    }
+
+    // 获取时间范围内（含端点）的温度记录，转换为按时间升序的时间-温度点，供绘图使用
+    pub fn temperature_points(
+        &self,
+        coke_oven: i32,
+        start: &str,
+        end: &str,
+    ) -> Result<Vec<TimeTempPoint>, CokeError> {
+        let records = self.temperatures_in_range(coke_oven, start, end, IntervalBounds::Closed)?;
+
+        Ok(records
+            .into_iter()
+            .map(|r| TimeTempPoint {
+                time: r.time,
+                machine: r.machine_side,
+                coke: r.coke_side,
+            })
+            .collect())
+    }
+
+    // 获取单个结焦周期的温度曲线：插值的装煤时刻点、期间的温度记录、插值的推焦时刻点，
+    // 与计算周期平均温度时使用的点序列一致
+    pub fn cycle_profile(
+        &self,
+        coke_oven: i32,
+        chamber: &str,
+        push_time: &str,
+    ) -> Result<Vec<TimeTempPoint>, CokeError> {
+        self.validate_chamber(coke_oven, chamber)?;
+
+        let push_time = &crate::models::normalize_time(push_time)?;
+
+        let loading_time: String = self
+            .conn
+            .query_row(
+                "SELECT loading_time FROM coking_cycles
+                    WHERE coke_oven = ?1 AND chamber = ?2 AND push_time = ?3",
+                params![coke_oven, chamber, push_time],
+                |row| row.get(0),
+            )
+            .optional()?
+            .ok_or_else(|| {
+                CokeError::NoData(format!(
+                    "焦炉{}炭化室{}在{}没有结焦周期",
+                    coke_oven, chamber, push_time
+                ))
+            })?;
+
+        match self.build_temperature_points(coke_oven, &loading_time, push_time) {
+            Ok(points) => Ok(points),
+            Err(rusqlite::Error::QueryReturnedNoRows) => Err(CokeError::NoData(format!(
+                "焦炉{}在{}至{}之间没有温度记录",
+                coke_oven, loading_time, push_time
+            ))),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    // 按指定端点语义获取时间范围内的温度记录，按时间升序排列
+    pub fn temperatures_in_range(
+        &self,
+        coke_oven: i32,
+        start: &str,
+        end: &str,
+        bounds: IntervalBounds,
+    ) -> Result<Vec<TempRecord>, CokeError> {
+        if !self.ovens.contains_key(&coke_oven) {
+            return Err(CokeError::InvalidOven(coke_oven));
+        }
+
+        let start = &crate::models::normalize_time(start)?;
+        let end = &crate::models::normalize_time(end)?;
+
+        self.get_temp_records_bounded(coke_oven, start, end, bounds)
+            .map_err(CokeError::from)
+    }
+
+    // 按温度值条件筛选时间范围内的温度记录，未设置的边界不参与过滤
+    #[allow(clippy::too_many_arguments)]
+    pub fn temperatures_where(
+        &self,
+        coke_oven: i32,
+        start: &str,
+        end: &str,
+        min_machine: Option<f64>,
+        max_machine: Option<f64>,
+        min_coke: Option<f64>,
+        max_coke: Option<f64>,
+    ) -> Result<Vec<TempRecord>, CokeError> {
+        if !self.ovens.contains_key(&coke_oven) {
+            return Err(CokeError::InvalidOven(coke_oven));
+        }
+
+        let start = &crate::models::normalize_time(start)?;
+        let end = &crate::models::normalize_time(end)?;
+
+        let mut sql = String::from(
+            "SELECT time, machine_side, coke_side FROM temperature_records
+            WHERE coke_oven = ?1 AND probe IS NULL AND time >= ?2 AND time <= ?3",
+        );
+        let mut values: Vec<&dyn rusqlite::ToSql> = vec![&coke_oven, &start, &end];
+
+        let bounds = [
+            ("machine_side >=", &min_machine),
+            ("machine_side <=", &max_machine),
+            ("coke_side >=", &min_coke),
+            ("coke_side <=", &max_coke),
+        ];
+        for (clause, bound) in bounds.iter() {
+            if let Some(value) = bound {
+                values.push(value);
+                sql.push_str(&format!(" AND {} ?{}", clause, values.len()));
+            }
+        }
+        sql.push_str(" ORDER BY time ASC");
+
+        let mut stmt = self.conn.prepare(&sql)?;
+        let records = stmt
+            .query_map(values.as_slice(), map_temp_row)?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(records)
+    }
+
+    // 逐行读取时间范围内（含边界）的温度记录并交给回调处理，不在内存中汇总，
+    // 适合大范围导出；回调返回错误时立即停止
+    pub fn stream_temperatures<F: FnMut(TempRecord) -> Result<(), CokeError>>(
+        &self,
+        coke_oven: i32,
+        start: &str,
+        end: &str,
+        mut f: F,
+    ) -> Result<(), CokeError> {
+        if !self.ovens.contains_key(&coke_oven) {
+            return Err(CokeError::InvalidOven(coke_oven));
+        }
+
+        let start = &crate::models::normalize_time(start)?;
+        let end = &crate::models::normalize_time(end)?;
+
+        let mut stmt = self.conn.prepare(
+            "SELECT time, machine_side, coke_side FROM temperature_records
+            WHERE coke_oven = ?1 AND probe IS NULL AND time >= ?2 AND time <= ?3
+            ORDER BY time ASC",
+        )?;
+        for record in stmt.query_map(params![coke_oven, start, end], map_temp_row)? {
+            f(record?)?;
+        }
+        Ok(())
+    }
+
+    // 将时间范围内的温度记录以 CSV 格式流式写出，返回写出的数据行数
+    pub fn export_temperatures_csv<W: Write>(
+        &self,
+        coke_oven: i32,
+        start: &str,
+        end: &str,
+        writer: &mut W,
+    ) -> Result<usize, CokeError> {
+        writeln!(writer, "time,machine_side,coke_side")?;
+
+        let mut rows = 0;
+        self.stream_temperatures(coke_oven, start, end, |record| {
+            writeln!(
+                writer,
+                "{},{},{}",
+                crate::models::format_time(record.time),
+                record.machine_side,
+                record.coke_side
+            )?;
+            rows += 1;
+            Ok(())
+        })?;
+
+        writer.flush()?;
+        Ok(rows)
+    }
+
+    // 以紧凑二进制格式导出全部温度与操作记录，用于离线批量传输；结焦周期导入后重新计算
+    //
+    // 格式（小端序）：魔数 8 字节、版本 u16，随后为温度段与操作段，各段以 u64 记录数开头。
+    // 温度记录：焦炉 i32、时间 i64（秒）、机侧 f64、焦侧 f64、探头（u8 标记 + 字符串）；
+    // 操作记录：焦炉 i32、炭化室字符串、类型 u8（0 装煤 / 1 推焦）、时间 i64。
+    // 字符串为 u32 字节长度 + UTF-8 内容
+    pub fn export_binary(&self, writer: &mut dyn Write) -> Result<(), CokeError> {
+        // 在同一读事务中统计与读取，保证记录数与内容一致
+        let tx = self.conn.unchecked_transaction()?;
+
+        writer.write_all(BINARY_MAGIC)?;
+        writer.write_all(&BINARY_VERSION.to_le_bytes())?;
+
+        let count: i64 = tx.query_row("SELECT COUNT(*) FROM temperature_records", [], |row| {
+            row.get(0)
+        })?;
+        writer.write_all(&(count as u64).to_le_bytes())?;
+        {
+            let mut stmt = tx.prepare(
+                "SELECT coke_oven, time, machine_side, coke_side, probe FROM temperature_records
+                    ORDER BY id ASC",
+            )?;
+            let mut rows = stmt.query([])?;
+            while let Some(row) = rows.next()? {
+                let time: String = row.get(1)?;
+                let probe: Option<String> = row.get(4)?;
+                writer.write_all(&row.get::<_, i32>(0)?.to_le_bytes())?;
+                writer.write_all(&binary_timestamp(&time)?.to_le_bytes())?;
+                writer.write_all(&row.get::<_, f64>(2)?.to_le_bytes())?;
+                writer.write_all(&row.get::<_, f64>(3)?.to_le_bytes())?;
+                match probe {
+                    Some(probe) => {
+                        writer.write_all(&[1])?;
+                        write_binary_str(writer, &probe)?;
+                    }
+                    None => writer.write_all(&[0])?,
+                }
+            }
+        }
+
+        let count: i64 = tx.query_row("SELECT COUNT(*) FROM operation_records", [], |row| {
+            row.get(0)
+        })?;
+        writer.write_all(&(count as u64).to_le_bytes())?;
+        {
+            let mut stmt = tx.prepare(
+                "SELECT coke_oven, chamber, operation_type, time FROM operation_records
+                    ORDER BY id ASC",
+            )?;
+            let mut rows = stmt.query([])?;
+            while let Some(row) = rows.next()? {
+                let chamber: String = row.get(1)?;
+                let op_type: String = row.get(2)?;
+                let time: String = row.get(3)?;
+                writer.write_all(&row.get::<_, i32>(0)?.to_le_bytes())?;
+                write_binary_str(writer, &chamber)?;
+                writer.write_all(&[u8::from(op_type == "PUSH")])?;
+                writer.write_all(&binary_timestamp(&time)?.to_le_bytes())?;
+            }
+        }
+
+        writer.flush()?;
+        Ok(())
+    }
+
+    // 导入 export_binary 生成的数据，返回实际插入的（温度记录数, 操作记录数）
+    //
+    // 在单个事务中执行，任一记录无效时整体回滚；已存在的相同记录跳过。
+    // 在同一事务中为新插入的推焦计算结焦周期，不重建已有周期（保留清理后留存的周期）；
+    // 晚于已有推焦补录的装煤不会改变已有周期的配对，需要时调用 rebuild_all_cycles
+    pub fn import_binary(&mut self, reader: &mut dyn Read) -> Result<(usize, usize), CokeError> {
+        let invalid = |msg: &str| CokeError::InvalidArgument(msg.to_string());
+
+        if &read_binary::<8>(reader)? != BINARY_MAGIC {
+            return Err(invalid("不是有效的二进制导出文件"));
+        }
+        let version = u16::from_le_bytes(read_binary(reader)?);
+        if version != BINARY_VERSION {
+            return Err(CokeError::InvalidArgument(format!(
+                "不支持的二进制导出版本: {}",
+                version
+            )));
+        }
+
+        let tx = self.conn.unchecked_transaction()?;
+
+        let mut temperatures = 0;
+        let count = u64::from_le_bytes(read_binary(reader)?);
+        {
+            let mut stmt = tx.prepare(
+                "INSERT OR IGNORE INTO temperature_records
+                    (coke_oven, time, machine_side, coke_side, probe)
+                    VALUES (?1, ?2, ?3, ?4, ?5)",
+            )?;
+            for _ in 0..count {
+                let coke_oven = i32::from_le_bytes(read_binary(reader)?);
+                let time = binary_time(i64::from_le_bytes(read_binary(reader)?))?;
+                let machine = f64::from_le_bytes(read_binary(reader)?);
+                let coke = f64::from_le_bytes(read_binary(reader)?);
+                let probe = match read_binary::<1>(reader)? {
+                    [0] => None,
+                    [1] => Some(read_binary_str(reader)?),
+                    _ => return Err(invalid("二进制数据中的探头标记无效")),
+                };
+                if !self.ovens.contains_key(&coke_oven) {
+                    return Err(CokeError::InvalidOven(coke_oven));
+                }
+                temperatures += stmt.execute(params![coke_oven, time, machine, coke, probe])?;
+            }
+        }
+
+        let mut operations = 0;
+        let mut pushes = Vec::new();
+        let count = u64::from_le_bytes(read_binary(reader)?);
+        {
+            let mut stmt = tx.prepare(
+                "INSERT OR IGNORE INTO operation_records (coke_oven, chamber, operation_type, time)
+                    VALUES (?1, ?2, ?3, ?4)",
+            )?;
+            for _ in 0..count {
+                let coke_oven = i32::from_le_bytes(read_binary(reader)?);
+                let chamber = read_binary_str(reader)?;
+                let op_type = match read_binary::<1>(reader)? {
+                    [0] => "LOAD",
+                    [1] => "PUSH",
+                    _ => return Err(invalid("二进制数据中的操作类型无效")),
+                };
+                let time = binary_time(i64::from_le_bytes(read_binary(reader)?))?;
+                self.validate_operation_target(coke_oven, &chamber, op_type)?;
+                let inserted = stmt.execute(params![coke_oven, chamber, op_type, time])?;
+                if inserted > 0 && op_type == "PUSH" {
+                    pushes.push((coke_oven, chamber, tx.last_insert_rowid(), time));
+                }
+                operations += inserted;
+            }
+        }
+
+        // 装煤可能排在对应推焦之后导入，全部写入后再按推焦时间计算周期
+        pushes.sort_by(|a, b| a.3.cmp(&b.3));
+        for (coke_oven, chamber, push_id, push_time) in &pushes {
+            self.compute_cycle_for_push(*coke_oven, chamber, *push_id, push_time)?;
+        }
+
+        tx.commit()?;
+
+        Ok((temperatures, operations))
+    }
+
+    // 计算指定时刻各结焦中炭化室的温度极差（最大值 - 最小值），返回（机侧, 焦侧）
+    //
+    // 炭化室温度取以炭化室名称为探头（如 "12#"）的测点记录在该时刻的插值；
+    // 没有测点记录的炭化室不参与计算，所有结焦中炭化室都没有测点记录时返回 NoData
+    pub fn chamber_temperature_spread(
+        &self,
+        coke_oven: i32,
+        time: &str,
+    ) -> Result<(f64, f64), CokeError> {
+        if !self.ovens.contains_key(&coke_oven) {
+            return Err(CokeError::InvalidOven(coke_oven));
+        }
+
+        let time_dt = crate::models::parse_time(time)?;
+        let time = &crate::models::format_time(time_dt);
+
+        let chambers = self.in_progress_chambers(coke_oven, time)?;
+        if chambers.is_empty() {
+            return Err(CokeError::NoData(format!(
+                "焦炉{}在{}没有正在结焦的炭化室",
+                coke_oven, time
+            )));
+        }
+
+        let mut temps = Vec::new();
+        for (chamber, _) in &chambers {
+            let prev = self.get_nearest_probe_record(coke_oven, Some(chamber), time, true)?;
+            let next = self.get_nearest_probe_record(coke_oven, Some(chamber), time, false)?;
+            if let Some(temp) = crate::models::interpolate_temp(&prev, &next, time_dt) {
+                temps.push(temp);
+            }
+        }
+        if temps.is_empty() {
+            return Err(CokeError::NoData(format!(
+                "焦炉{}在{}没有结焦中炭化室的测点温度记录",
+                coke_oven, time
+            )));
+        }
+
+        let spread = |values: Vec<f64>| {
+            let max = values.iter().cloned().fold(f64::MIN, f64::max);
+            let min = values.iter().cloned().fold(f64::MAX, f64::min);
+            max - min
+        };
+
+        Ok((
+            spread(temps.iter().map(|t| t.0).collect()),
+            spread(temps.iter().map(|t| t.1).collect()),
+        ))
+    }
+
+    // 按推焦日期汇总日期范围内（含首尾两天）的推焦次数与平均结焦时间，
+    // 推焦次数以生成的结焦周期计，没有推焦的日期不出现在结果中
+    pub fn daily_summary(
+        &self,
+        coke_oven: i32,
+        start_date: &str,
+        end_date: &str,
+    ) -> Result<Vec<DailySummary>, CokeError> {
+        if !self.ovens.contains_key(&coke_oven) {
+            return Err(CokeError::InvalidOven(coke_oven));
+        }
+
+        let start_date = crate::models::parse_time(start_date)?.date();
+        let end_date = crate::models::parse_time(end_date)?.date();
+
+        let mut stmt = self.conn.prepare(
+            "SELECT substr(push_time, 1, 10) AS day, COUNT(*), AVG(duration_minutes)
+                FROM coking_cycles
+                WHERE coke_oven = ?1 AND substr(push_time, 1, 10) BETWEEN ?2 AND ?3
+                GROUP BY day
+                ORDER BY day ASC",
+        )?;
+
+        let rows = stmt
+            .query_map(
+                params![
+                    coke_oven,
+                    start_date.format("%Y-%m-%d").to_string(),
+                    end_date.format("%Y-%m-%d").to_string()
+                ],
+                |row| {
+                    Ok((
+                        row.get::<_, String>(0)?,
+                        row.get::<_, i64>(1)?,
+                        row.get::<_, f64>(2)?,
+                    ))
+                },
+            )?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        rows.into_iter()
+            .map(|(day, push_count, avg_duration_minutes)| {
+                Ok(DailySummary {
+                    date: crate::models::parse_time(&day)?.date(),
+                    push_count,
+                    avg_duration_minutes,
+                })
+            })
+            .collect()
+    }
+
+    // 统计推焦时间在范围内的结焦周期总时长（分钟），没有周期时返回 0
+    pub fn total_coking_minutes(
+        &self,
+        coke_oven: i32,
+        start: &str,
+        end: &str,
+    ) -> Result<i64, CokeError> {
+        if !self.ovens.contains_key(&coke_oven) {
+            return Err(CokeError::InvalidOven(coke_oven));
+        }
+
+        let start = &crate::models::normalize_time(start)?;
+        let end = &crate::models::normalize_time(end)?;
+
+        Ok(self.conn.query_row(
+            "SELECT COALESCE(SUM(duration_minutes), 0) FROM coking_cycles
+            WHERE coke_oven = ?1 AND push_time >= ?2 AND push_time <= ?3",
+            params![coke_oven, start, end],
+            |row| row.get(0),
+        )?)
+    }
+
+    // 按 bucket_minutes 分桶统计结焦时间分布，返回（桶起点分钟数, 周期数），按桶升序；
+    // 没有周期时返回空列表
+    pub fn duration_histogram(
+        &self,
+        coke_oven: i32,
+        bucket_minutes: i32,
+    ) -> Result<Vec<(i32, i64)>, CokeError> {
+        if !self.ovens.contains_key(&coke_oven) {
+            return Err(CokeError::InvalidOven(coke_oven));
+        }
+        if bucket_minutes <= 0 {
+            return Err(CokeError::InvalidArgument(format!(
+                "无效分桶宽度: {}",
+                bucket_minutes
+            )));
+        }
+
+        let mut stmt = self.conn.prepare(
+            "SELECT duration_minutes FROM coking_cycles
+            WHERE coke_oven = ?1 AND duration_minutes IS NOT NULL",
+        )?;
+        let durations = stmt
+            .query_map(params![coke_oven], |row| row.get::<_, i32>(0))?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        // 负时长也按向下取整归入对应的桶
+        let mut buckets: BTreeMap<i32, i64> = BTreeMap::new();
+        for minutes in durations {
+            *buckets
+                .entry(minutes.div_euclid(bucket_minutes) * bucket_minutes)
+                .or_insert(0) += 1;
+        }
+
+        Ok(buckets.into_iter().collect())
+    }
+
+    // 按结焦周期平均温度对炭化室排序（降序），跳过平均温度缺失的周期，
+    // 温度相同时按炭化室自然顺序排列
+    pub fn rank_chambers_by_avg_temp(
+        &self,
+        coke_oven: i32,
+        side: Side,
+    ) -> Result<Vec<(String, f64)>, CokeError> {
+        if !self.ovens.contains_key(&coke_oven) {
+            return Err(CokeError::InvalidOven(coke_oven));
+        }
+
+        let column = match side {
+            Side::Machine => "avg_temp_machine",
+            Side::Coke => "avg_temp_coke",
+        };
+
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT chamber, AVG({0}) FROM coking_cycles
+                WHERE coke_oven = ?1 AND {0} IS NOT NULL
+                GROUP BY chamber",
+            column
+        ))?;
+
+        let mut ranking = stmt
+            .query_map(params![coke_oven], |row| {
+                Ok((row.get::<_, String>(0)?, row.get::<_, f64>(1)?))
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        ranking.sort_by(|(a, a_temp), (b, b_temp)| {
+            b_temp
+                .total_cmp(a_temp)
+                .then_with(|| {
+                    crate::models::natural_chamber_key(a)
+                        .cmp(&crate::models::natural_chamber_key(b))
+                })
+                .then_with(|| a.cmp(b))
+        });
+        Ok(ranking)
+    }
+
+    // 计算焦炉温度记录与操作记录的确定性校验和，用于比较两个数据库的逻辑内容是否一致
+    //
+    // 记录按时间、探头排序，温度保留 3 位小数后参与计算，与插入顺序和行 id 无关
+    pub fn content_checksum(&self, coke_oven: i32) -> Result<u64, CokeError> {
+        if !self.ovens.contains_key(&coke_oven) {
+            return Err(CokeError::InvalidOven(coke_oven));
+        }
+
+        let mut hash = FNV_OFFSET_BASIS;
+
+        // 同一时刻可能有多个探头的记录，按探头排序保证顺序确定
+        let mut stmt = self.conn.prepare(
+            "SELECT time, machine_side, coke_side, probe FROM temperature_records
+                WHERE coke_oven = ?1
+                ORDER BY time ASC, COALESCE(probe, '') ASC",
+        )?;
+        let records = stmt.query_map(params![coke_oven], |row| {
+            Ok((map_temp_row(row)?, row.get::<_, Option<String>>(3)?))
+        })?;
+        for record in records {
+            let (record, probe) = record?;
+            let mut line = format!(
+                "T|{}|{:.3}|{:.3}",
+                crate::models::format_time(record.time),
+                record.machine_side,
+                record.coke_side
+            );
+            // 未指定探头的记录保持原格式，已有校验和不变
+            if let Some(probe) = probe {
+                line.push('|');
+                line.push_str(&probe);
+            }
+            line.push('\n');
+            hash = fnv1a(hash, line.as_bytes());
+        }
+
+        let mut stmt = self.conn.prepare(
+            "SELECT id, coke_oven, chamber, operation_type, time FROM operation_records
+                WHERE coke_oven = ?1
+                ORDER BY time ASC, chamber ASC, operation_type ASC",
+        )?;
+        let operations = stmt.query_map(params![coke_oven], map_operation_row)?;
+        for op in operations {
+            let op = op?;
+            let line = format!(
+                "O|{}|{}|{}\n",
+                crate::models::format_time(op.time),
+                op.chamber,
+                op.operation_type
+            );
+            hash = fnv1a(hash, line.as_bytes());
+        }
+
+        Ok(hash)
+    }
+
+    // 统计时间范围内的温度（最小值、最大值、平均值）
+    pub fn temperature_stats(
+        &self,
+        coke_oven: i32,
+        start: &str,
+        end: &str,
+    ) -> Result<TempStats, CokeError> {
+        if !self.ovens.contains_key(&coke_oven) {
+            return Err(CokeError::InvalidOven(coke_oven));
+        }
+
+        let start = &crate::models::normalize_time(start)?;
+        let end = &crate::models::normalize_time(end)?;
+
+        let stats = self.conn.query_row(
+            "SELECT COUNT(*),
+                        MIN(machine_side), MAX(machine_side), AVG(machine_side),
+                        MIN(coke_side), MAX(coke_side), AVG(coke_side)
+                FROM temperature_records
+                WHERE coke_oven = ?1 AND probe IS NULL AND time >= ?2 AND time <= ?3",
+            params![coke_oven, start, end],
+            |row| {
+                let count: i64 = row.get(0)?;
+                if count == 0 {
+                    return Ok(None);
+                }
+                Ok(Some(TempStats {
+                    count,
+                    min_machine: row.get(1)?,
+                    max_machine: row.get(2)?,
+                    avg_machine: row.get(3)?,
+                    min_coke: row.get(4)?,
+                    max_coke: row.get(5)?,
+                    avg_coke: row.get(6)?,
+                }))
+            },
+        )?;
+
+        stats.ok_or_else(|| {
+            CokeError::NoData(format!("焦炉{}在指定时间范围内没有温度记录", coke_oven))
+        })
+    }
+
+    // 一次查询按焦炉分组统计时间范围内的温度，窗口内没有记录的焦炉不出现在结果中
+    pub fn all_ovens_temperature_stats(
+        &self,
+        start: &str,
+        end: &str,
+    ) -> Result<HashMap<i32, TempStats>, CokeError> {
+        let start = &crate::models::normalize_time(start)?;
+        let end = &crate::models::normalize_time(end)?;
+
+        let mut stmt = self.conn.prepare(
+            "SELECT coke_oven, COUNT(*),
+                        MIN(machine_side), MAX(machine_side), AVG(machine_side),
+                        MIN(coke_side), MAX(coke_side), AVG(coke_side)
+                FROM temperature_records
+                WHERE probe IS NULL AND time >= ?1 AND time <= ?2
+                GROUP BY coke_oven",
+        )?;
+        let stats = stmt
+            .query_map(params![start, end], |row| {
+                Ok((
+                    row.get(0)?,
+                    TempStats {
+                        count: row.get(1)?,
+                        min_machine: row.get(2)?,
+                        max_machine: row.get(3)?,
+                        avg_machine: row.get(4)?,
+                        min_coke: row.get(5)?,
+                        max_coke: row.get(6)?,
+                        avg_coke: row.get(7)?,
+                    },
+                ))
+            })?
+            .collect::<Result<HashMap<_, _>, _>>()?;
+
+        Ok(stats)
+    }
+
+    // 查询焦炉的全部结焦周期（按推焦时间升序）
+    pub fn query_coking_cycles(&self, coke_oven: i32) -> Result<Vec<CokingCycle>, CokeError> {
+        if !self.ovens.contains_key(&coke_oven) {
+            return Err(CokeError::InvalidOven(coke_oven));
+        }
+
+        self.query_cycles(coke_oven).map_err(CokeError::from)
+    }
+
+    // 分页查询焦炉的结焦周期（按推焦时间降序），配合 coking_cycle_count 计算页数
+    pub fn query_coking_cycles_paged(
+        &self,
+        coke_oven: i32,
+        limit: i64,
+        offset: i64,
+    ) -> Result<Vec<CokingCycle>, CokeError> {
+        if !self.ovens.contains_key(&coke_oven) {
+            return Err(CokeError::InvalidOven(coke_oven));
+        }
+        if limit < 0 || offset < 0 {
+            return Err(CokeError::InvalidArgument(format!(
+                "无效分页参数: limit={}, offset={}",
+                limit, offset
+            )));
+        }
+
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT {} FROM coking_cycles
+            WHERE coke_oven = ?1
+            ORDER BY push_time DESC, id DESC
+            LIMIT ?2 OFFSET ?3",
+            CYCLE_COLUMNS
+        ))?;
+        let cycles = stmt
+            .query_map(params![coke_oven, limit, offset], map_cycle_row)?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(cycles)
+    }
+
+    // 统计焦炉的结焦周期总数
+    pub fn coking_cycle_count(&self, coke_oven: i32) -> Result<i64, CokeError> {
+        if !self.ovens.contains_key(&coke_oven) {
+            return Err(CokeError::InvalidOven(coke_oven));
+        }
+
+        Ok(self.conn.query_row(
+            "SELECT COUNT(*) FROM coking_cycles WHERE coke_oven = ?1",
+            params![coke_oven],
+            |row| row.get(0),
+        )?)
+    }
+
+    // 返回焦炉温度记录的最早与最晚时间，没有记录时返回 None
+    pub fn data_range(
+        &self,
+        coke_oven: i32,
+    ) -> Result<Option<(NaiveDateTime, NaiveDateTime)>, CokeError> {
+        if !self.ovens.contains_key(&coke_oven) {
+            return Err(CokeError::InvalidOven(coke_oven));
+        }
+
+        let (first, last): (Option<String>, Option<String>) = self.conn.query_row(
+            "SELECT MIN(time), MAX(time) FROM temperature_records
+                WHERE coke_oven = ?1 AND probe IS NULL",
+            params![coke_oven],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )?;
+
+        match (first, last) {
+            (Some(first), Some(last)) => Ok(Some((
+                crate::models::parse_time(&first)?,
+                crate::models::parse_time(&last)?,
+            ))),
+            _ => Ok(None),
+        }
+    }
+
+    // 返回有温度或操作记录的焦炉编号，按编号升序，供看板隐藏空闲焦炉
+    pub fn active_ovens(&self) -> Result<Vec<i32>, CokeError> {
+        let mut stmt = self.conn.prepare(
+            "SELECT coke_oven FROM temperature_records
+             UNION
+             SELECT coke_oven FROM operation_records
+             ORDER BY coke_oven ASC",
+        )?;
+        let ovens = stmt
+            .query_map([], |row| row.get(0))?
+            .collect::<Result<Vec<i32>, _>>()?;
+
+        Ok(ovens)
+    }
+
+    // 将推焦时间在范围内的结焦周期以 CSV 格式写出，返回写出的数据行数
+    //
+    // 平均温度缺失时对应字段留空
+    pub fn export_cycles_csv<W: Write>(
+        &self,
+        coke_oven: i32,
+        start: &str,
+        end: &str,
+        writer: &mut W,
+    ) -> Result<usize, CokeError> {
+        if !self.ovens.contains_key(&coke_oven) {
+            return Err(CokeError::InvalidOven(coke_oven));
+        }
+
+        let start = &crate::models::normalize_time(start)?;
+        let end = &crate::models::normalize_time(end)?;
+
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT {} FROM coking_cycles
+                WHERE coke_oven = ?1 AND push_time >= ?2 AND push_time <= ?3
+                ORDER BY push_time ASC, id ASC",
+            CYCLE_COLUMNS
+        ))?;
+        let cycles = stmt.query_map(params![coke_oven, start, end], map_cycle_row)?;
+
+        writeln!(writer, "{}", CYCLES_CSV_HEADER)?;
+
+        let mut rows = 0;
+        for cycle in cycles {
+            let cycle = cycle?;
+            let optional = |v: Option<f64>| v.map(|v| v.to_string()).unwrap_or_default();
+            writeln!(
+                writer,
+                "{},{},{},{},{},{},{},{}",
+                cycle.coke_oven,
+                csv_field(&cycle.chamber),
+                crate::models::format_time(cycle.loading_time),
+                crate::models::format_time(cycle.push_time),
+                cycle.duration_hhmm,
+                cycle.duration_minutes,
+                optional(cycle.avg_temp_machine),
+                optional(cycle.avg_temp_coke)
+            )?;
+            rows += 1;
+        }
+
+        writer.flush()?;
+        Ok(rows)
+    }
+
+    // 查询时间范围内的装煤/推焦操作历史（按时间升序）
+    pub fn operation_history(
+        &self,
+        coke_oven: i32,
+        start: &str,
+        end: &str,
+    ) -> Result<Vec<OperationRecord>, CokeError> {
+        if !self.ovens.contains_key(&coke_oven) {
+            return Err(CokeError::InvalidOven(coke_oven));
+        }
+
+        let start = &crate::models::normalize_time(start)?;
+        let end = &crate::models::normalize_time(end)?;
+
+        let mut stmt = self.conn.prepare(
+            "SELECT id, coke_oven, chamber, operation_type, time FROM operation_records
+                WHERE coke_oven = ?1 AND time >= ?2 AND time <= ?3
+                ORDER BY time ASC, id ASC",
+        )?;
+
+        let records = stmt
+            .query_map(params![coke_oven, start, end], map_operation_row)?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(records)
+    }
+
+    // 读取指定时间之后（含）的审计日志，按写入顺序排列；since 按输入时间解析，
+    // 返回的时间为存储时间，与温度、操作记录一致
+    pub fn audit_trail(&self, since: &str) -> Result<Vec<AuditEntry>, CokeError> {
+        let since_utc = self
+            .stored_to_audit_time(self.parse_input_time(since)?)
+            .ok_or_else(|| CokeError::InvalidTime(since.to_string()))?;
+
+        let mut stmt = self.conn.prepare(
+            "SELECT id, action, table_name, record_key, time, user FROM audit_log
+                WHERE time >= ?1
+                ORDER BY id ASC",
+        )?;
+        let rows = stmt
+            .query_map(
+                params![crate::models::format_time(since_utc)],
+                |row| -> rusqlite::Result<(i64, String, String, String, String, Option<String>)> {
+                    Ok((
+                        row.get(0)?,
+                        row.get(1)?,
+                        row.get(2)?,
+                        row.get(3)?,
+                        row.get(4)?,
+                        row.get(5)?,
+                    ))
+                },
+            )?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        rows.into_iter()
+            .map(|(id, action, table_name, record_key, time, user)| {
+                Ok(AuditEntry {
+                    id,
+                    action,
+                    table_name,
+                    record_key,
+                    time: self.audit_time_to_stored(crate::models::parse_time(&time)?),
+                    user,
+                })
+            })
+            .collect()
+    }
+
+    // 将时间范围内的操作记录按时间升序序列化为 JSON 数组，供审计使用；没有记录时返回 "[]"
+    #[cfg(feature = "serde")]
+    pub fn operations_json(
+        &self,
+        coke_oven: i32,
+        start: &str,
+        end: &str,
+    ) -> Result<String, CokeError> {
+        #[derive(serde::Serialize)]
+        struct OperationEntry<'a> {
+            chamber: &'a str,
+            operation_type: &'a str,
+            time: String,
+        }
+
+        let records = self.operation_history(coke_oven, start, end)?;
+        let entries: Vec<OperationEntry> = records
+            .iter()
+            .map(|r| OperationEntry {
+                chamber: &r.chamber,
+                operation_type: &r.operation_type,
+                time: crate::models::format_time(r.time),
+            })
+            .collect();
+
+        serde_json::to_string(&entries).map_err(|e| CokeError::InvalidArgument(e.to_string()))
+    }
+
+    // 获取任意时刻的插值温度（机侧, 焦侧），超出记录范围时取最近记录，没有任何记录时返回 None
+    pub fn temperature_at(
+        &self,
+        coke_oven: i32,
+        time: &str,
+    ) -> Result<Option<(f64, f64)>, CokeError> {
+        if !self.ovens.contains_key(&coke_oven) {
+            return Err(CokeError::InvalidOven(coke_oven));
+        }
+
+        let time_dt = crate::models::parse_time(time)?;
+        let time = &crate::models::format_time(time_dt);
+
+        Ok(self.interpolated_temp_at(coke_oven, time, time_dt)?)
+    }
+
+    // 计算时间窗口首尾之间的平均升温速率（℃/小时），返回（机侧, 焦侧）；
+    // 首尾温度按前后最近记录插值，窗口长度为零或没有温度记录时返回错误
+    pub fn heating_rate(
+        &self,
+        coke_oven: i32,
+        start: &str,
+        end: &str,
+    ) -> Result<(f64, f64), CokeError> {
+        if !self.ovens.contains_key(&coke_oven) {
+            return Err(CokeError::InvalidOven(coke_oven));
+        }
+
+        let start_dt = crate::models::parse_time(start)?;
+        let end_dt = crate::models::parse_time(end)?;
+        if end_dt <= start_dt {
+            return Err(CokeError::InvalidArgument(
+                "结束时间必须晚于开始时间".to_string(),
+            ));
+        }
+
+        let start = &crate::models::format_time(start_dt);
+        let end = &crate::models::format_time(end_dt);
+        let no_data = || CokeError::NoData(format!("焦炉{}没有温度记录", coke_oven));
+        let (start_machine, start_coke) = self
+            .interpolated_temp_at(coke_oven, start, start_dt)?
+            .ok_or_else(no_data)?;
+        let (end_machine, end_coke) = self
+            .interpolated_temp_at(coke_oven, end, end_dt)?
+            .ok_or_else(no_data)?;
+
+        let hours = (end_dt - start_dt).num_seconds() as f64 / 3600.0;
+        Ok((
+            (end_machine - start_machine) / hours,
+            (end_coke - start_coke) / hours,
+        ))
+    }
+
+    // 计算时间窗口内的积分平均温度，返回（机侧, 焦侧）；没有温度记录时返回 None
+    pub fn average_temperature(
+        &self,
+        coke_oven: i32,
+        start: &str,
+        end: &str,
+    ) -> Result<Option<(f64, f64)>, CokeError> {
+        if !self.ovens.contains_key(&coke_oven) {
+            return Err(CokeError::InvalidOven(coke_oven));
+        }
+
+        let start_dt = crate::models::parse_time(start)?;
+        let end_dt = crate::models::parse_time(end)?;
+        if end_dt < start_dt {
+            return Err(CokeError::InvalidArgument(
+                "结束时间不能早于开始时间".to_string(),
+            ));
+        }
+        let start = &crate::models::format_time(start_dt);
+        let end = &crate::models::format_time(end_dt);
+
+        match self.calculate_avg_temperature(coke_oven, start, end) {
+            Ok(avg) => Ok(Some(avg)),
+            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    // 对比两座焦炉在时间窗口内的平均温度，任一焦炉窗口内没有温度数据时返回错误
+    pub fn compare_ovens(
+        &self,
+        oven_a: i32,
+        oven_b: i32,
+        start: &str,
+        end: &str,
+    ) -> Result<OvenComparison, CokeError> {
+        let average = |oven: i32| {
+            self.average_temperature(oven, start, end)?.ok_or_else(|| {
+                CokeError::NoData(format!("焦炉{}在{}至{}之间没有温度记录", oven, start, end))
+            })
+        };
+        let (machine_a, coke_a) = average(oven_a)?;
+        let (machine_b, coke_b) = average(oven_b)?;
+
+        Ok(OvenComparison {
+            oven_a,
+            oven_b,
+            machine_a,
+            coke_a,
+            machine_b,
+            coke_b,
+            machine_delta: machine_a - machine_b,
+            coke_delta: coke_a - coke_b,
+        })
+    }
+
+    // 获取焦炉的额定结焦时间（分钟），未配置时返回 None
+    pub fn expected_cycle_minutes(&self, coke_oven: i32) -> Result<Option<i32>, CokeError> {
+        self.ovens
+            .get(&coke_oven)
+            .map(|oven| oven.expected_cycle_minutes)
+            .ok_or(CokeError::InvalidOven(coke_oven))
+    }
+
+    // 设置焦炉的额定结焦时间（分钟）
+    pub fn set_expected_cycle_minutes(
+        &mut self,
+        coke_oven: i32,
+        minutes: i32,
+    ) -> Result<(), CokeError> {
+        let oven = self
+            .ovens
+            .get_mut(&coke_oven)
+            .ok_or(CokeError::InvalidOven(coke_oven))?;
+        if minutes <= 0 {
+            return Err(CokeError::InvalidArgument(format!(
+                "无效目标结焦时间: {}",
+                minutes
+            )));
+        }
+        oven.expected_cycle_minutes = Some(minutes);
+        Ok(())
+    }
+
+    // 查找指定时刻仍处于装煤状态且已超过结焦时间阈值的炭化室，返回（炭化室, 已结焦分钟数）
+    //
+    // threshold_minutes 为 None 时使用焦炉的额定结焦时间
+    pub fn find_overdue_chambers(
+        &self,
+        coke_oven: i32,
+        now: &str,
+        threshold_minutes: Option<i32>,
+    ) -> Result<Vec<(String, i64)>, CokeError> {
+        let threshold = match threshold_minutes {
+            Some(minutes) => {
+                if !self.ovens.contains_key(&coke_oven) {
+                    return Err(CokeError::InvalidOven(coke_oven));
+                }
+                minutes
+            }
+            None => self
+                .expected_cycle_minutes(coke_oven)?
+                .ok_or_else(|| CokeError::NoData(format!("焦炉{}未设置目标结焦时间", coke_oven)))?,
+        };
+
+        let now_dt = crate::models::parse_time(now)?;
+        let now = &crate::models::format_time(now_dt);
+
+        let mut overdue = Vec::new();
+        for (chamber, loading_time) in self.in_progress_chambers(coke_oven, now)? {
+            let elapsed = (now_dt - crate::models::parse_time(&loading_time)?).num_minutes();
+            if elapsed > threshold as i64 {
+                overdue.push((chamber, elapsed));
+            }
+        }
+        Ok(overdue)
+    }
+
+    // 以时钟的当前时间查找超时的炭化室，参数与返回值同 find_overdue_chambers
+    pub fn find_overdue_chambers_now(
+        &self,
+        coke_oven: i32,
+        threshold_minutes: Option<i32>,
+    ) -> Result<Vec<(String, i64)>, CokeError> {
+        let now = self.to_stored_time(&crate::models::format_time(self.clock.now()))?;
+        self.find_overdue_chambers(coke_oven, &now, threshold_minutes)
+    }
+
+    // 按步长（HH:mm）滚动计算尾随窗口内按时推焦的比例
+    //
+    // 结焦时间与额定结焦时间（set_expected_cycle_minutes）相差不超过 tolerance_minutes 视为按时；
+    // 窗口为 (t - window_hours, t]，窗口内没有推焦的时间点不输出
+    pub fn rolling_on_time_ratio(
+        &self,
+        coke_oven: i32,
+        start: &str,
+        end: &str,
+        step: &str,
+        window_hours: i64,
+        tolerance_minutes: i32,
+    ) -> Result<Vec<(NaiveDateTime, f64)>, CokeError> {
+        if !self.ovens.contains_key(&coke_oven) {
+            return Err(CokeError::InvalidOven(coke_oven));
+        }
+        let target = self
+            .expected_cycle_minutes(coke_oven)?
+            .ok_or_else(|| CokeError::NoData(format!("焦炉{}未设置目标结焦时间", coke_oven)))?;
+
+        let start_dt = crate::models::parse_time(start)?;
+        let end_dt = crate::models::parse_time(end)?;
+        let step_minutes = hhmm_to_minutes(step)
+            .filter(|m| *m > 0)
+            .ok_or_else(|| CokeError::InvalidArgument(format!("无效步长: {}", step)))?;
+        if window_hours <= 0 {
+            return Err(CokeError::InvalidArgument(format!(
+                "无效窗口时长: {}",
+                window_hours
+            )));
+        }
+        if tolerance_minutes < 0 {
+            return Err(CokeError::InvalidArgument(format!(
+                "无效容差: {}",
+                tolerance_minutes
+            )));
+        }
+
+        let cycles = self.query_cycles(coke_oven)?;
+        let window = Duration::hours(window_hours);
+        let step = Duration::minutes(step_minutes as i64);
+
+        let mut ratios = Vec::new();
+        let mut t = start_dt;
+        while t <= end_dt {
+            let in_window: Vec<&CokingCycle> = cycles
+                .iter()
+                .filter(|c| c.push_time > t - window && c.push_time <= t)
+                .collect();
+            if !in_window.is_empty() {
+                let on_time = in_window
+                    .iter()
+                    .filter(|c| (c.duration_minutes - target).abs() <= tolerance_minutes)
+                    .count();
+                ratios.push((t, on_time as f64 / in_window.len() as f64));
+            }
+            t += step;
+        }
+
+        Ok(ratios)
+    }
+
+    // 将结焦周期按时间等分为三段，返回每段端点插值得到的机侧/焦侧升温速率（℃/小时）
+    pub fn phase_slopes(
+        &self,
+        coke_oven: i32,
+        loading_time: &str,
+        push_time: &str,
+    ) -> Result<[(f64, f64); 3], CokeError> {
+        if !self.ovens.contains_key(&coke_oven) {
+            return Err(CokeError::InvalidOven(coke_oven));
+        }
+
+        let load_dt = crate::models::parse_time(loading_time)?;
+        let push_dt = crate::models::parse_time(push_time)?;
+        if push_dt <= load_dt {
+            return Err(CokeError::InvalidArgument(
+                "推焦时间必须晚于装煤时间".to_string(),
+            ));
+        }
+
+        let third = (push_dt - load_dt) / 3;
+        let mut boundaries = Vec::with_capacity(4);
+        for i in 0..4 {
+            let time_dt = if i == 3 { push_dt } else { load_dt + third * i };
+            let temp = self
+                .interpolated_temp_at(coke_oven, &crate::models::format_time(time_dt), time_dt)?
+                .ok_or_else(|| CokeError::NoData(format!("焦炉{}没有温度记录", coke_oven)))?;
+            boundaries.push((time_dt, temp));
+        }
+
+        let mut slopes = [(0.0, 0.0); 3];
+        for (i, slope) in slopes.iter_mut().enumerate() {
+            let (t0, (m0, c0)) = boundaries[i];
+            let (t1, (m1, c1)) = boundaries[i + 1];
+            let hours = (t1 - t0).num_seconds() as f64 / 3600.0;
+            *slope = ((m1 - m0) / hours, (c1 - c0) / hours);
+        }
+
+        Ok(slopes)
+    }
+
+    // 设置焦炉的目标温度（℃）
+    pub fn set_target_temperature(&mut self, coke_oven: i32, target: f64) -> Result<(), CokeError> {
+        if !self.ovens.contains_key(&coke_oven) {
+            return Err(CokeError::InvalidOven(coke_oven));
+        }
+        if !target.is_finite() {
+            return Err(CokeError::InvalidArgument(format!(
+                "无效目标温度: {}",
+                target
+            )));
+        }
+        self.target_temperatures.insert(coke_oven, target);
+        Ok(())
+    }
+
+    // 统计推焦时间在窗口内的各炭化室结焦周期中，温度超出焦炉目标温度部分的积分（℃·小时）
+    //
+    // 温度取机侧与焦侧的平均值；结果按炭化室自然顺序排列，只包含窗口内有周期的炭化室
+    pub fn over_target_degree_hours(
+        &self,
+        coke_oven: i32,
+        start: &str,
+        end: &str,
+    ) -> Result<Vec<(String, f64)>, CokeError> {
+        if !self.ovens.contains_key(&coke_oven) {
+            return Err(CokeError::InvalidOven(coke_oven));
+        }
+        let target = *self
+            .target_temperatures
+            .get(&coke_oven)
+            .ok_or_else(|| CokeError::NoData(format!("焦炉{}未设置目标温度", coke_oven)))?;
+
+        let start_dt = crate::models::parse_time(start)?;
+        let end_dt = crate::models::parse_time(end)?;
+
+        let cycles = self.query_cycles(coke_oven)?;
+
+        let mut totals: HashMap<String, f64> = HashMap::new();
+        for cycle in cycles
+            .iter()
+            .filter(|c| c.push_time >= start_dt && c.push_time <= end_dt)
+        {
+            let points = self.build_temperature_points(
+                coke_oven,
+                &crate::models::format_time(cycle.loading_time),
+                &crate::models::format_time(cycle.push_time),
+            )?;
+
+            let degree_hours: f64 = points
+                .windows(2)
+                .map(|w| {
+                    let hours = (w[1].time - w[0].time).num_seconds() as f64 / 3600.0;
+                    let excess0 = (w[0].machine + w[0].coke) / 2.0 - target;
+                    let excess1 = (w[1].machine + w[1].coke) / 2.0 - target;
+                    positive_area(excess0, excess1, hours)
+                })
+                .sum();
+
+            *totals.entry(cycle.chamber.clone()).or_insert(0.0) += degree_hours;
+        }
+
+        let mut chambers: Vec<String> = totals.keys().cloned().collect();
+        crate::models::sort_chambers_naturally(&mut chambers);
+        Ok(chambers
+            .into_iter()
+            .map(|c| {
+                let total = totals[&c];
+                (c, total)
+            })
+            .collect())
+    }
+
+    // 设置焦炭成熟度评分的目标温度
+    pub fn set_readiness_target(&mut self, target: f64) -> Result<(), CokeError> {
+        if !target.is_finite() || target <= 0.0 {
+            return Err(CokeError::InvalidArgument(format!(
+                "无效目标温度: {}",
+                target
+            )));
+        }
+        self.readiness_target = target;
+        Ok(())
+    }
+
+    // 根据焖炉阶段（推焦前 END_SOAK_MINUTES 分钟）平均温度计算 0-1 的成熟度评分，
+    // 达到目标温度即为 1
+    pub fn coke_readiness_proxy(
+        &self,
+        coke_oven: i32,
+        loading_time: &str,
+        push_time: &str,
+    ) -> Result<f64, CokeError> {
+        if !self.ovens.contains_key(&coke_oven) {
+            return Err(CokeError::InvalidOven(coke_oven));
+        }
+
+        let load_dt = crate::models::parse_time(loading_time)?;
+        let push_dt = crate::models::parse_time(push_time)?;
+        if push_dt <= load_dt {
+            return Err(CokeError::InvalidArgument(
+                "推焦时间必须晚于装煤时间".to_string(),
+            ));
+        }
+
+        let soak_start = std::cmp::max(load_dt, push_dt - Duration::minutes(END_SOAK_MINUTES));
+        let (avg_machine, avg_coke) = self.calculate_avg_temperature(
+            coke_oven,
+            &crate::models::format_time(soak_start),
+            &crate::models::format_time(push_dt),
+        )?;
+
+        let soak_avg = (avg_machine + avg_coke) / 2.0;
+        Ok((soak_avg / self.readiness_target).clamp(0.0, 1.0))
+    }
+
+    // 以 Arrow RecordBatch 形式导出时间范围内的温度记录（time, machine_side, coke_side）
+    #[cfg(feature = "arrow")]
+    pub fn temperatures_arrow(
+        &self,
+        coke_oven: i32,
+        start: &str,
+        end: &str,
+    ) -> Result<arrow::record_batch::RecordBatch, CokeError> {
+        use arrow::array::{ArrayRef, Float64Array, TimestampSecondArray};
+        use arrow::datatypes::{DataType, Field, Schema, TimeUnit};
+        use std::sync::Arc;
+
+        let records = self.temperatures_in_range(coke_oven, start, end, IntervalBounds::Closed)?;
+
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("time", DataType::Timestamp(TimeUnit::Second, None), false),
+            Field::new("machine_side", DataType::Float64, false),
+            Field::new("coke_side", DataType::Float64, false),
+        ]));
+
+        let columns: Vec<ArrayRef> = vec![
+            Arc::new(TimestampSecondArray::from_iter_values(
+                records.iter().map(|r| r.time.and_utc().timestamp()),
+            )),
+            Arc::new(Float64Array::from_iter_values(
+                records.iter().map(|r| r.machine_side),
+            )),
+            Arc::new(Float64Array::from_iter_values(
+                records.iter().map(|r| r.coke_side),
+            )),
+        ];
+
+        arrow::record_batch::RecordBatch::try_new(schema, columns)
+            .map_err(|e| CokeError::InvalidArgument(e.to_string()))
+    }
+
+    // 计算指定时刻各焦炉温度相对全炉组平均值的偏差（正值表示偏热），无温度记录的焦炉跳过
+    pub fn oven_deviation_from_battery(
+        &self,
+        time: &str,
+        side: Side,
+    ) -> Result<Vec<(i32, f64)>, CokeError> {
+        let time_dt = crate::models::parse_time(time)?;
+        let time = &crate::models::format_time(time_dt);
+
+        let mut oven_ids: Vec<i32> = self.ovens.keys().copied().collect();
+        oven_ids.sort();
+
+        let mut temps = Vec::new();
+        for coke_oven in oven_ids {
+            let temp = self.interpolated_temp_at(coke_oven, time, time_dt)?;
+            if let Some((machine, coke)) = temp {
+                temps.push((coke_oven, side.select(machine, coke)));
+            }
+        }
+
+        if temps.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let mean = temps.iter().map(|(_, t)| t).sum::<f64>() / temps.len() as f64;
+        Ok(temps
+            .into_iter()
+            .map(|(coke_oven, t)| (coke_oven, t - mean))
+            .collect())
+    }
+
+    // 计算滚动平均温度序列：每条记录取 [t - window_minutes, t] 内的记录求平均，
+    // 窗口起始处历史不足时使用已有记录
+    pub fn rolling_average(
+        &self,
+        coke_oven: i32,
+        window_minutes: i32,
+        start: &str,
+        end: &str,
+    ) -> Result<Vec<(NaiveDateTime, f64, f64)>, CokeError> {
+        if !self.ovens.contains_key(&coke_oven) {
+            return Err(CokeError::InvalidOven(coke_oven));
+        }
+        if window_minutes < 0 {
+            return Err(CokeError::InvalidArgument(format!(
+                "无效窗口时长: {}",
+                window_minutes
+            )));
+        }
+
+        let start = &crate::models::normalize_time(start)?;
+        let end = &crate::models::normalize_time(end)?;
+
+        let records = self.get_temp_records_in_range(coke_oven, start, end)?;
+
+        let window = Duration::minutes(window_minutes as i64);
+        let mut series = Vec::with_capacity(records.len());
+        let mut first = 0;
+        for (i, record) in records.iter().enumerate() {
+            while records[first].time < record.time - window {
+                first += 1;
+            }
+            let in_window = &records[first..=i];
+            let n = in_window.len() as f64;
+            let machine = in_window.iter().map(|r| r.machine_side).sum::<f64>() / n;
+            let coke = in_window.iter().map(|r| r.coke_side).sum::<f64>() / n;
+            series.push((record.time, machine, coke));
+        }
+
+        Ok(series)
+    }
+
+    // 按 step_hours 小时分桶统计温度记录数，桶区间为 [t, t + step)，用于发现传感器掉线时段
+    pub fn record_density(
+        &self,
+        coke_oven: i32,
+        start: &str,
+        end: &str,
+        step_hours: i64,
+    ) -> Result<Vec<(NaiveDateTime, i64)>, CokeError> {
+        if step_hours <= 0 {
+            return Err(CokeError::InvalidArgument(format!(
+                "无效步长: {}",
+                step_hours
+            )));
+        }
+
+        let start_dt = crate::models::parse_time(start)?;
+        let end_dt = crate::models::parse_time(end)?;
+        let records = self.temperatures_in_range(coke_oven, start, end, IntervalBounds::Closed)?;
+
+        let step = Duration::hours(step_hours);
+        let mut buckets = Vec::new();
+        let mut t = start_dt;
+        while t < end_dt {
+            let bucket_end = t + step;
+            let count = records
+                .iter()
+                .filter(|r| r.time >= t && r.time < bucket_end)
+                .count() as i64;
+            buckets.push((t, count));
+            t = bucket_end;
+        }
+
+        Ok(buckets)
+    }
+
+    // 查找温度读数持续不变（传感器卡死）的时段：某一侧连续记录的值完全相同，
+    // 且首尾时间跨度不少于阈值；返回（起始, 结束, 列名 "machine_side"/"coke_side"），
+    // 按起始时间、机侧在前排列
+    pub fn flatline_intervals(
+        &self,
+        coke_oven: i32,
+        min_duration_minutes: i32,
+        start: &str,
+        end: &str,
+    ) -> Result<Vec<(NaiveDateTime, NaiveDateTime, &'static str)>, CokeError> {
+        if min_duration_minutes <= 0 {
+            return Err(CokeError::InvalidArgument(format!(
+                "无效时长阈值: {}",
+                min_duration_minutes
+            )));
+        }
+
+        let records = self.temperatures_in_range(coke_oven, start, end, IntervalBounds::Closed)?;
+        let min_duration = Duration::minutes(min_duration_minutes as i64);
+
+        let mut intervals = Vec::new();
+        for (side, label) in [(Side::Machine, "machine_side"), (Side::Coke, "coke_side")] {
+            let value = |r: &TempRecord| side.select(r.machine_side, r.coke_side);
+            let mut run_start = 0;
+            for i in 1..=records.len() {
+                if i < records.len() && value(&records[i]) == value(&records[run_start]) {
+                    continue;
+                }
+                let (first, last) = (records[run_start].time, records[i - 1].time);
+                if last - first >= min_duration {
+                    intervals.push((first, last, label));
+                }
+                run_start = i;
+            }
+        }
+
+        intervals.sort_by_key(|&(first, _, label)| (first, label == "coke_side"));
+        Ok(intervals)
+    }
+
+    // 查找时间窗口内相邻温度记录间隔超过阈值的缺口，返回缺口前后两条记录的时间
+    pub fn temperature_gaps(
+        &self,
+        coke_oven: i32,
+        max_gap_minutes: i32,
+        start: &str,
+        end: &str,
+    ) -> Result<Vec<(NaiveDateTime, NaiveDateTime)>, CokeError> {
+        if !self.ovens.contains_key(&coke_oven) {
+            return Err(CokeError::InvalidOven(coke_oven));
+        }
+        if max_gap_minutes <= 0 {
+            return Err(CokeError::InvalidArgument(format!(
+                "无效间隔阈值: {}",
+                max_gap_minutes
+            )));
+        }
+
+        let start = &crate::models::normalize_time(start)?;
+        let end = &crate::models::normalize_time(end)?;
+
+        let records = self.get_temp_records_in_range(coke_oven, start, end)?;
+
+        let max_gap = Duration::minutes(max_gap_minutes as i64);
+        Ok(records
+            .windows(2)
+            .filter(|pair| pair[1].time - pair[0].time > max_gap)
+            .map(|pair| (pair[0].time, pair[1].time))
+            .collect())
+    }
+
+    // 查找解析后落在同一分钟内的温度记录，返回该分钟及对应的原始时间字符串
+    pub fn find_precision_collisions(
+        &self,
+        coke_oven: i32,
+    ) -> Result<Vec<(NaiveDateTime, Vec<String>)>, CokeError> {
+        if !self.ovens.contains_key(&coke_oven) {
+            return Err(CokeError::InvalidOven(coke_oven));
+        }
+
+        let mut stmt = self.conn.prepare(
+            "SELECT time FROM temperature_records
+                WHERE coke_oven = ?1 AND probe IS NULL
+                ORDER BY time ASC",
+        )?;
+
+        let times = stmt
+            .query_map(params![coke_oven], |row| row.get::<_, String>(0))?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut groups: BTreeMap<NaiveDateTime, Vec<String>> = BTreeMap::new();
+        for raw in times {
+            let dt = crate::models::parse_time(&raw)?;
+            let minute = dt
+                .with_second(0)
+                .and_then(|d| d.with_nanosecond(0))
+                .unwrap_or(dt);
+            groups.entry(minute).or_default().push(raw);
+        }
+
+        Ok(groups
+            .into_iter()
+            .filter(|(_, raws)| raws.len() > 1)
+            .collect())
+    }
+
+    // 将所有表中的时间字段统一改写为 %Y-%m-%d %H:%M:%S 格式，返回更新的行数
+    //
+    // 在单个事务中执行；若存在无法解析的时间或改写后与已有记录发生唯一约束冲突，
+    // 则回滚并在错误中列出问题行
+    pub fn normalize_timestamps(&mut self) -> Result<usize, CokeError> {
+        let tx = self.conn.transaction()?;
+        let (updated, issues) = normalize_time_columns(&tx)?;
+
+        if !issues.is_empty() {
+            return Err(CokeError::Conflict(format!(
+                "时间标准化失败: {}",
+                issues.join("; ")
+            )));
+        }
+
+        tx.commit()?;
+        Ok(updated)
+    }
+
+    // 扫描全部表检查数据完整性：无法解析的时间、推焦早于装煤的周期、
+    // 不在当前布局中的焦炉/炭化室以及结焦分钟数为负的周期
+    pub fn integrity_report(&self) -> Result<IntegrityReport, CokeError> {
+        let mut report = IntegrityReport::default();
+
+        let mut stmt = self
+            .conn
+            .prepare("SELECT id, coke_oven, time FROM temperature_records ORDER BY id")?;
+        let rows = stmt.query_map([], |row| {
+            Ok((
+                row.get::<_, i64>(0)?,
+                row.get::<_, i32>(1)?,
+                row.get::<_, String>(2)?,
+            ))
+        })?;
+        for row in rows {
+            let (id, coke_oven, time) = row?;
+            if crate::models::parse_time(&time).is_err() {
+                report.unparseable_times.add(
+                    format!("temperature_records#{} (time={})", id, time),
+                    INTEGRITY_SAMPLE_LIMIT,
+                );
+            }
+            if !self.ovens.contains_key(&coke_oven) {
+                report.unknown_chambers.add(
+                    format!("temperature_records#{} (焦炉{})", id, coke_oven),
+                    INTEGRITY_SAMPLE_LIMIT,
+                );
+            }
+        }
+
+        let mut stmt = self
+            .conn
+            .prepare("SELECT id, coke_oven, chamber, time FROM operation_records ORDER BY id")?;
+        let rows = stmt.query_map([], |row| {
+            Ok((
+                row.get::<_, i64>(0)?,
+                row.get::<_, i32>(1)?,
+                row.get::<_, String>(2)?,
+                row.get::<_, String>(3)?,
+            ))
+        })?;
+        for row in rows {
+            let (id, coke_oven, chamber, time) = row?;
+            if crate::models::parse_time(&time).is_err() {
+                report.unparseable_times.add(
+                    format!("operation_records#{} (time={})", id, time),
+                    INTEGRITY_SAMPLE_LIMIT,
+                );
+            }
+            if !self.is_known_chamber(coke_oven, &chamber) {
+                report.unknown_chambers.add(
+                    format!("operation_records#{} (焦炉{} {})", id, coke_oven, chamber),
+                    INTEGRITY_SAMPLE_LIMIT,
+                );
+            }
+        }
+
+        let mut stmt = self.conn.prepare(
+            "SELECT id, coke_oven, chamber, loading_time, push_time, duration_minutes
+            FROM coking_cycles ORDER BY id",
+        )?;
+        let rows = stmt.query_map([], |row| {
+            Ok((
+                row.get::<_, i64>(0)?,
+                row.get::<_, i32>(1)?,
+                row.get::<_, String>(2)?,
+                row.get::<_, String>(3)?,
+                row.get::<_, String>(4)?,
+                row.get::<_, Option<i64>>(5)?,
+            ))
+        })?;
+        for row in rows {
+            let (id, coke_oven, chamber, loading_time, push_time, duration_minutes) = row?;
+            let load_dt = crate::models::parse_time(&loading_time);
+            let push_dt = crate::models::parse_time(&push_time);
+            if load_dt.is_err() || push_dt.is_err() {
+                report.unparseable_times.add(
+                    format!(
+                        "coking_cycles#{} (loading_time={}, push_time={})",
+                        id, loading_time, push_time
+                    ),
+                    INTEGRITY_SAMPLE_LIMIT,
+                );
+            }
+            if let (Ok(load_dt), Ok(push_dt)) = (load_dt, push_dt) {
+                if push_dt <= load_dt {
+                    report.push_before_load.add(
+                        format!(
+                            "coking_cycles#{} (loading_time={}, push_time={})",
+                            id, loading_time, push_time
+                        ),
+                        INTEGRITY_SAMPLE_LIMIT,
+                    );
+                }
+            }
+            if !self.is_known_chamber(coke_oven, &chamber) {
+                report.unknown_chambers.add(
+                    format!("coking_cycles#{} (焦炉{} {})", id, coke_oven, chamber),
+                    INTEGRITY_SAMPLE_LIMIT,
+                );
+            }
+            if let Some(minutes) = duration_minutes.filter(|m| *m < 0) {
+                report.negative_durations.add(
+                    format!("coking_cycles#{} (duration_minutes={})", id, minutes),
+                    INTEGRITY_SAMPLE_LIMIT,
+                );
+            }
+        }
+
+        Ok(report)
+    }
+
+    // 在单个事务中删除早于 cutoff 的温度记录与操作记录，返回各表删除的行数
+    //
+    // 结焦周期作为长期记录保留；引用被删除操作的周期将来源操作 id 置为 NULL
+    pub fn prune_before(&mut self, cutoff: &str) -> Result<PruneReport, CokeError> {
+        let cutoff = &crate::models::normalize_time(cutoff)?;
+
+        let tx = self.conn.transaction()?;
+        let temperature_records = tx.execute(
+            "DELETE FROM temperature_records WHERE time < ?1",
+            params![cutoff],
+        )?;
+        tx.execute(
+            "UPDATE coking_cycles SET loading_op_id = NULL
+            WHERE loading_op_id IN (SELECT id FROM operation_records WHERE time < ?1)",
+            params![cutoff],
+        )?;
+        tx.execute(
+            "UPDATE coking_cycles SET push_op_id = NULL
+            WHERE push_op_id IN (SELECT id FROM operation_records WHERE time < ?1)",
+            params![cutoff],
+        )?;
+        let operation_records = tx.execute(
+            "DELETE FROM operation_records WHERE time < ?1",
+            params![cutoff],
+        )?;
+        tx.commit()?;
+
+        Ok(PruneReport {
+            temperature_records,
+            operation_records,
+        })
+    }
+
+    // 使用 SQLite 在线备份 API 将当前数据库（含 WAL 中未检查点的数据）复制到目标文件，
+    // 备份期间无需停止写入
+    pub fn backup_to(&self, dest_path: &str) -> Result<(), CokeError> {
+        let mut dest = Connection::open(dest_path)?;
+        let backup = Backup::new(&self.conn, &mut dest)?;
+        backup.run_to_completion(100, std::time::Duration::from_millis(10), None)?;
+        Ok(())
+    }
+
+    // 执行 WAL 检查点并截断 -wal 文件以回收磁盘空间
+    //
+    // 若有其他连接正在读写导致检查点无法完成，返回错误，可稍后重试
+    pub fn checkpoint(&self) -> Result<(), CokeError> {
+        let busy: i64 = self
+            .conn
+            .query_row("PRAGMA wal_checkpoint(TRUNCATE)", [], |row| row.get(0))?;
+
+        if busy != 0 {
+            return Err(CokeError::Conflict("WAL 检查点被其他连接阻塞".to_string()));
+        }
+        Ok(())
+    }
+
+    // 以别名附加另一个焦炉数据库，用于跨厂对比查询；别名须为合法标识符且不能为 main/temp
+    pub fn attach(&self, alias: &str, path: &str) -> Result<(), CokeError> {
+        check_attach_alias(alias)?;
+        self.conn.execute(
+            &format!("ATTACH DATABASE ?1 AS \"{}\"", alias),
+            params![path],
+        )?;
+        Ok(())
+    }
+
+    // 分离 attach 附加的数据库
+    pub fn detach(&self, alias: &str) -> Result<(), CokeError> {
+        check_attach_alias(alias)?;
+        self.conn
+            .execute(&format!("DETACH DATABASE \"{}\"", alias), [])?;
+        Ok(())
+    }
+
+    // 统计本库与附加库的结焦周期数，返回（本库, 附加库）
+    pub fn combined_cycle_count(&self, alias: &str) -> Result<(i64, i64), CokeError> {
+        check_attach_alias(alias)?;
+        self.conn
+            .query_row(
+                &format!(
+                    "SELECT (SELECT COUNT(*) FROM main.coking_cycles),
+                            (SELECT COUNT(*) FROM \"{}\".coking_cycles)",
+                    alias
+                ),
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .map_err(CokeError::from)
+    }
+
+    // 计算时间范围内温度的总体标准差（机侧, 焦侧），记录少于两条时返回错误
+    pub fn temperature_stddev(
+        &self,
+        coke_oven: i32,
+        start: &str,
+        end: &str,
+    ) -> Result<(f64, f64), CokeError> {
+        let records = self.temperatures_in_range(coke_oven, start, end, IntervalBounds::Closed)?;
+        if records.len() < 2 {
+            return Err(CokeError::NoData(format!(
+                "焦炉{}在指定时间范围内温度记录不足两条",
+                coke_oven
+            )));
+        }
+
+        let stddev = |values: Vec<f64>| {
+            let n = values.len() as f64;
+            let mean = values.iter().sum::<f64>() / n;
+            (values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / n).sqrt()
+        };
+
+        Ok((
+            stddev(records.iter().map(|r| r.machine_side).collect()),
+            stddev(records.iter().map(|r| r.coke_side).collect()),
+        ))
+    }
+
+    // 查找时间范围内指定侧温度最高的记录，返回（时间, 机侧, 焦侧）；
+    // 最高值出现多次时取最早的一条，没有记录时返回错误
+    pub fn peak_temperature(
+        &self,
+        coke_oven: i32,
+        start: &str,
+        end: &str,
+        side: Side,
+    ) -> Result<(NaiveDateTime, f64, f64), CokeError> {
+        let records = self.temperatures_in_range(coke_oven, start, end, IntervalBounds::Closed)?;
+
+        // max_by 在相等时返回最后一个，逆序遍历使并列时取最早的记录
+        let peak = records.iter().rev().max_by(|a, b| {
+            side.select(a.machine_side, a.coke_side)
+                .total_cmp(&side.select(b.machine_side, b.coke_side))
+        });
+
+        peak.map(|r| (r.time, r.machine_side, r.coke_side))
+            .ok_or_else(|| {
+                CokeError::NoData(format!("焦炉{}在指定时间范围内没有温度记录", coke_oven))
+            })
+    }
+
+    // 计算时间范围内每条记录的机焦侧温差（机侧 - 焦侧）
+    pub fn temperature_differential(
+        &self,
+        coke_oven: i32,
+        start: &str,
+        end: &str,
+    ) -> Result<Vec<(NaiveDateTime, f64)>, CokeError> {
+        let records = self.temperatures_in_range(coke_oven, start, end, IntervalBounds::Closed)?;
+        Ok(records
+            .into_iter()
+            .map(|r| (r.time, r.machine_side - r.coke_side))
+            .collect())
+    }
+
+    // 获取时间范围内绝对值最大的机焦侧温差及其时间，没有记录时返回 None
+    pub fn max_differential(
+        &self,
+        coke_oven: i32,
+        start: &str,
+        end: &str,
+    ) -> Result<Option<(NaiveDateTime, f64)>, CokeError> {
+        let diffs = self.temperature_differential(coke_oven, start, end)?;
+        Ok(diffs
+            .into_iter()
+            .fold(
+                None,
+                |worst: Option<(NaiveDateTime, f64)>, (time, diff)| match worst {
+                    Some((_, w)) if w.abs() >= diff.abs() => worst,
+                    _ => Some((time, diff)),
+                },
+            ))
+    }
+
+    // 检测相邻温度记录间的突变：机侧或焦侧变化速率（℃/分钟）的绝对值超过阈值时，
+    // 返回该记录的时间及两侧中较大的速率绝对值
+    //
+    // 单个异常采样通常会在突变及恢复时各标记一次
+    pub fn spike_anomalies(
+        &self,
+        coke_oven: i32,
+        max_rate_per_min: f64,
+        start: &str,
+        end: &str,
+    ) -> Result<Vec<(NaiveDateTime, f64)>, CokeError> {
+        if !max_rate_per_min.is_finite() || max_rate_per_min <= 0.0 {
+            return Err(CokeError::InvalidArgument(format!(
+                "无效变化速率阈值: {}",
+                max_rate_per_min
+            )));
+        }
+
+        let records = self.temperatures_in_range(coke_oven, start, end, IntervalBounds::Closed)?;
+        Ok(records
+            .windows(2)
+            .filter_map(|pair| {
+                let minutes = (pair[1].time - pair[0].time).num_seconds() as f64 / 60.0;
+                if minutes <= 0.0 {
+                    return None;
+                }
+                let machine_rate = (pair[1].machine_side - pair[0].machine_side).abs() / minutes;
+                let coke_rate = (pair[1].coke_side - pair[0].coke_side).abs() / minutes;
+                let rate = machine_rate.max(coke_rate);
+                (rate > max_rate_per_min).then_some((pair[1].time, rate))
+            })
+            .collect())
+    }
+
+    // 计算炭化室相邻结焦周期机侧平均温度的移动极差（SPC MR 图），跳过平均温度缺失的周期
+    pub fn moving_range(&self, coke_oven: i32, chamber: &str) -> Result<Vec<f64>, CokeError> {
+        self.validate_chamber(coke_oven, chamber)?;
+
+        let averages: Vec<f64> = self
+            .query_chamber_cycles(coke_oven, chamber)?
+            .into_iter()
+            .filter_map(|c| c.avg_temp_machine)
+            .collect();
+
+        Ok(averages.windows(2).map(|w| (w[1] - w[0]).abs()).collect())
+    }
+
+    // 计算平均移动极差（用于控制限），周期不足两个时返回 None
+    pub fn average_moving_range(
+        &self,
+        coke_oven: i32,
+        chamber: &str,
+    ) -> Result<Option<f64>, CokeError> {
+        let ranges = self.moving_range(coke_oven, chamber)?;
+        if ranges.is_empty() {
+            return Ok(None);
+        }
+        Ok(Some(ranges.iter().sum::<f64>() / ranges.len() as f64))
+    }
+
+    fn try_calculate_coking_cycle(
+        &mut self,
+        coke_oven: i32,
+        chamber: &str,
+        push_time: &str,
+    ) -> Result<bool, rusqlite::Error> {
+        let loading = self.find_loading_time(coke_oven, chamber, push_time)?;
+
+        // 推焦前没有装煤记录时不生成结焦周期
+        let Some((load_id, loading_time)) = loading else {
+            return Ok(false);
+        };
+
+        let push_id: i64 = self
+            .conn
+            .prepare_cached(
+                "SELECT id FROM operation_records
+             WHERE coke_oven = ?1 AND chamber = ?2 AND operation_type = 'PUSH' AND time = ?3",
+            )?
+            .query_row(params![coke_oven, chamber, push_time], |row| row.get(0))?;
+        self.insert_cycle(
+            coke_oven,
+            chamber,
+            (load_id, &loading_time),
+            (push_id, push_time),
+        )?;
+
+        Ok(true)
+    }
+
+    // 计算结焦时间与平均温度并写入结焦周期，loading/push 为来源操作记录的（id, 时间）
+    fn insert_cycle(
+        &self,
+        coke_oven: i32,
+        chamber: &str,
+        (loading_op_id, loading_time): (i64, &str),
+        (push_op_id, push_time): (i64, &str),
+    ) -> Result<(), rusqlite::Error> {
+        let load_dt =
+            crate::models::parse_time(loading_time).map_err(|_| rusqlite::Error::InvalidQuery)?;
+        let push_dt =
+            crate::models::parse_time(push_time).map_err(|_| rusqlite::Error::InvalidQuery)?;
+
+        let duration_seconds = push_dt.signed_duration_since(load_dt).num_seconds();
+        let duration_minutes = (duration_seconds / 60) as i32;
+
+        // 转换为 HH:mm 格式，开启秒精度时为 HH:MM:SS
+        let duration_hhmm = if self.duration_seconds_precision {
+            duration_to_hhmmss(duration_seconds)
+        } else {
+            minutes_to_hhmm(duration_minutes)
+        };
+
+        let (avg_machine, avg_coke, low_confidence) =
+            self.cycle_averages(coke_oven, loading_time, push_time)?;
+
+        self.conn
+            .prepare_cached(
+                "INSERT INTO coking_cycles (
+                coke_oven, chamber, loading_time, push_time, 
+                duration_hhmm, duration_minutes, avg_temp_machine, avg_temp_coke,
+                loading_op_id, push_op_id, low_confidence
+             ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
+            )?
+            .execute(params![
+                coke_oven,
+                chamber,
+                loading_time,
+                push_time,
+                duration_hhmm,
+                duration_minutes,
+                avg_machine,
+                avg_coke,
+                loading_op_id,
+                push_op_id,
+                low_confidence
+            ])?;
+
+        Ok(())
+    }
+
+    // 计算结焦周期的平均温度，返回（机侧, 焦侧, 是否低置信度）
+    //
+    // 内部温度记录少于最少样本数时不计算平均温度；计算失败时平均温度为 None
+    fn cycle_averages(
+        &self,
+        coke_oven: i32,
+        loading_time: &str,
+        push_time: &str,
+    ) -> Result<(Option<f64>, Option<f64>, bool), rusqlite::Error> {
+        if self.min_interior_samples > 0 {
+            let interior: i64 = self
+                .conn
+                .prepare_cached(
+                    "SELECT COUNT(*) FROM temperature_records
+                     WHERE coke_oven = ?1 AND probe IS NULL AND time > ?2 AND time < ?3",
+                )?
+                .query_row(params![coke_oven, loading_time, push_time], |row| {
+                    row.get(0)
+                })?;
+            if (interior as usize) < self.min_interior_samples {
+                return Ok((None, None, true));
+            }
+        }
+
+        Ok(
+            match self.calculate_avg_temperature(coke_oven, loading_time, push_time) {
+                Ok((m, c)) => (Some(m), Some(c), false),
+                Err(e) => {
+                    log::warn!("计算平均温度失败：{}", e);
+                    (None, None, false)
+                }
+            },
+        )
+    }
+
+    // 按配对策略查找推焦对应的装煤记录（id, 时间）
+    fn find_loading_time(
+        &self,
+        coke_oven: i32,
+        chamber: &str,
+        push_time: &str,
+    ) -> Result<Option<(i64, String)>, rusqlite::Error> {
+        let sql = match self.load_pairing {
+            LoadPairing::Latest => {
+                "SELECT id, time FROM operation_records 
+             WHERE coke_oven = ?1 
+               AND chamber = ?2 
+               AND operation_type = 'LOAD' 
+               AND time < ?3
+             ORDER BY time DESC LIMIT 1"
+            }
+            LoadPairing::Earliest => {
+                "SELECT id, time FROM operation_records
+             WHERE coke_oven = ?1
+               AND chamber = ?2
+               AND operation_type = 'LOAD'
+               AND time < ?3
+               AND time > COALESCE((
+                   SELECT MAX(time) FROM operation_records
+                   WHERE coke_oven = ?1 AND chamber = ?2
+                     AND operation_type = 'PUSH' AND time < ?3
+               ), '')
+             ORDER BY time ASC LIMIT 1"
+            }
+        };
+
+        self.conn
+            .prepare_cached(sql)?
+            .query_row(params![coke_oven, chamber, push_time], |row| {
+                Ok((row.get(0)?, row.get(1)?))
+            })
+            .optional()
+    }
+
+    // 计算装煤到推焦期间的平均温度（通过积分）
+    fn calculate_avg_temperature(
+        &self,
+        coke_oven: i32,
+        start_time: &str,
+        end_time: &str,
+    ) -> Result<(f64, f64), rusqlite::Error> {
+        let points = self.build_temperature_points(coke_oven, start_time, end_time)?;
+
+        // 计算积分
+        let (total_machine_area, total_coke_area, total_duration) =
+            calculate_integral(&points, self.integration_method);
+
+        if total_duration == 0.0 {
+            Ok((points[0].machine, points[0].coke))
+        } else {
+            let avg_machine = total_machine_area / total_duration;
+            let avg_coke = total_coke_area / total_duration;
+            Ok((avg_machine, avg_coke))
+        }
+    }
+
+    // 构建时间窗口的温度点序列：插值起点、中间记录、插值终点
+    fn build_temperature_points(
+        &self,
+        coke_oven: i32,
+        start_time: &str,
+        end_time: &str,
+    ) -> Result<Vec<TimeTempPoint>, rusqlite::Error> {
+        let start_dt =
+            crate::models::parse_time(start_time).map_err(|_| rusqlite::Error::InvalidQuery)?;
+        let end_dt =
+            crate::models::parse_time(end_time).map_err(|_| rusqlite::Error::InvalidQuery)?;
+
+        // 查询装煤时间点前后的温度记录
+        let prev_start = self.get_nearest_temp_record(coke_oven, start_time, true)?;
+        let next_start = self.get_nearest_temp_record(coke_oven, start_time, false)?;
+        let prev_end = self.get_nearest_temp_record(coke_oven, end_time, true)?;
+        let next_end = self.get_nearest_temp_record(coke_oven, end_time, false)?;
+
+        // 获取中间记录
+        let middle_records = self.get_temp_records_in_range(coke_oven, start_time, end_time)?;
+
+        // 计算边界点温度
+        let start_temp = crate::models::interpolate_temp(&prev_start, &next_start, start_dt)
+            .ok_or(rusqlite::Error::QueryReturnedNoRows)?;
+        let end_temp = crate::models::interpolate_temp(&prev_end, &next_end, end_dt)
+            .ok_or(rusqlite::Error::QueryReturnedNoRows)?;
+
+        // 构建时间点序列
+        let mut points = Vec::new();
+        points.push(TimeTempPoint {
+            time: start_dt,
+            machine: start_temp.0,
+            coke: start_temp.1,
+        });
+
+        for record in middle_records {
+            points.push(TimeTempPoint {
+                time: record.time,
+                machine: record.machine_side,
+                coke: record.coke_side,
+            });
+        }
+
+        points.push(TimeTempPoint {
+            time: end_dt,
+            machine: end_temp.0,
+            coke: end_temp.1,
+        });
+
+        Ok(points)
+    }
+
+    // 辅助方法：根据前后最近记录插值指定时刻的温度，没有任何记录时返回 None
+    fn interpolated_temp_at(
+        &self,
+        coke_oven: i32,
+        time: &str,
+        time_dt: NaiveDateTime,
+    ) -> Result<Option<(f64, f64)>, rusqlite::Error> {
+        let prev = self.get_nearest_temp_record(coke_oven, time, true)?;
+        let next = self.get_nearest_temp_record(coke_oven, time, false)?;
+        Ok(crate::models::interpolate_temp(&prev, &next, time_dt))
+    }
+
+    // 辅助方法：获取最近温度记录（焦炉级，不含探头记录）
+    fn get_nearest_temp_record(
+        &self,
+        coke_oven: i32,
+        time: &str,
+        before: bool,
+    ) -> Result<Option<TempRecord>, rusqlite::Error> {
+        self.get_nearest_probe_record(coke_oven, None, time, before)
+    }
+
+    // 辅助方法：获取指定探头的最近温度记录，probe 为 None 时为焦炉级记录
+    fn get_nearest_probe_record(
+        &self,
+        coke_oven: i32,
+        probe: Option<&str>,
+        time: &str,
+        before: bool,
+    ) -> Result<Option<TempRecord>, rusqlite::Error> {
+        let query = if before {
+            "SELECT time, machine_side, coke_side FROM temperature_records
+             WHERE coke_oven = ?1 AND probe IS ?3 AND time <= ?2
+             ORDER BY time DESC LIMIT 1"
+        } else {
+            "SELECT time, machine_side, coke_side FROM temperature_records
+             WHERE coke_oven = ?1 AND probe IS ?3 AND time > ?2
+             ORDER BY time ASC LIMIT 1"
+        };
+
+        self.conn
+            .prepare_cached(query)?
+            .query_row(params![coke_oven, time, probe], map_temp_row)
+            .optional()
+    }
+
+    // 辅助方法：焦炉及炭化室是否在当前布局中
+    fn is_known_chamber(&self, coke_oven: i32, chamber: &str) -> bool {
+        self.ovens
+            .get(&coke_oven)
+            .is_some_and(|oven| oven.is_valid_chamber(chamber))
+    }
+
+    // 辅助方法：获取指定时刻最近一次操作为装煤的炭化室及其装煤时间，按炭化室自然顺序排序
+    fn in_progress_chambers(
+        &self,
+        coke_oven: i32,
+        time: &str,
+    ) -> Result<Vec<(String, String)>, rusqlite::Error> {
+        let mut stmt = self.conn.prepare(
+            "SELECT o.chamber, o.time FROM operation_records o
+            WHERE o.coke_oven = ?1
+              AND o.operation_type = 'LOAD'
+              AND o.time = (
+                  SELECT MAX(time) FROM operation_records
+                  WHERE coke_oven = o.coke_oven AND chamber = o.chamber AND time <= ?2
+              )",
+        )?;
+
+        let mut chambers = stmt
+            .query_map(params![coke_oven, time], |row| {
+                Ok((row.get(0)?, row.get(1)?))
+            })?
+            .collect::<Result<Vec<(String, String)>, _>>()?;
+        chambers.sort_by(|(a, _), (b, _)| {
+            crate::models::natural_chamber_key(a)
+                .cmp(&crate::models::natural_chamber_key(b))
+                .then_with(|| a.cmp(b))
+        });
+
+        Ok(chambers)
+    }
+
+    // 辅助方法：获取焦炉的全部结焦周期（按推焦时间升序）
+    fn query_cycles(&self, coke_oven: i32) -> Result<Vec<CokingCycle>, rusqlite::Error> {
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT {} FROM coking_cycles
+            WHERE coke_oven = ?1
+            ORDER BY push_time ASC",
+            CYCLE_COLUMNS
+        ))?;
+
+        let cycles = stmt
+            .query_map(params![coke_oven], map_cycle_row)?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(cycles)
+    }
+
+    // 辅助方法：获取炭化室的全部结焦周期（按推焦时间升序）
+    fn query_chamber_cycles(
+        &self,
+        coke_oven: i32,
+        chamber: &str,
+    ) -> Result<Vec<CokingCycle>, rusqlite::Error> {
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT {} FROM coking_cycles
+            WHERE coke_oven = ?1 AND chamber = ?2
+            ORDER BY push_time ASC",
+            CYCLE_COLUMNS
+        ))?;
+
+        let cycles = stmt
+            .query_map(params![coke_oven, chamber], map_cycle_row)?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(cycles)
+    }
+
+    // 辅助方法：获取时间范围内（不含端点）的温度记录，结焦周期计算的中间记录使用此语义
+    fn get_temp_records_in_range(
+        &self,
+        coke_oven: i32,
+        start: &str,
+        end: &str,
+    ) -> Result<Vec<TempRecord>, rusqlite::Error> {
+        self.get_temp_records_bounded(coke_oven, start, end, IntervalBounds::Open)
+    }
+
+    // 辅助方法：按指定端点语义获取时间范围内的温度记录
+    fn get_temp_records_bounded(
+        &self,
+        coke_oven: i32,
+        start: &str,
+        end: &str,
+        bounds: IntervalBounds,
+    ) -> Result<Vec<TempRecord>, rusqlite::Error> {
+        let (lower, upper) = bounds.operators();
+        let mut stmt = self.conn.prepare_cached(&format!(
+            "SELECT time, machine_side, coke_side FROM temperature_records
+            WHERE coke_oven = ?1 AND probe IS NULL AND time {} ?2 AND time {} ?3
+            ORDER BY time ASC",
+            lower, upper
+        ))?;
+
+        let records = stmt
+            .query_map(params![coke_oven, start, end], map_temp_row)?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(records)
+    }
+
+    // 显式关闭数据库：执行 WAL 检查点并关闭连接，失败时返回错误（检查点被其他连接
+    // 阻塞时返回 Conflict）；消耗 self，关闭后无法继续使用。仅依赖 Drop 时错误会被忽略
+    pub fn close(mut self) -> Result<(), CokeError> {
+        self.checkpoint()?;
+
+        self.closed = true;
+        // SAFETY: closed 已置位，Drop 不会再访问或释放 conn
+        let conn = unsafe { ManuallyDrop::take(&mut self.conn) };
+        conn.close().map_err(|(_, e)| CokeError::Db(e))
+    }
+}
+
+// 释放前执行被动 WAL 检查点，减少遗留的 -wal 文件；Drop 无法返回错误，失败时忽略
+impl Drop for CokeOvenSystem {
+    fn drop(&mut self) {
+        if self.closed {
+            return;
+        }
+        let _ = self
+            .conn
+            .query_row("PRAGMA wal_checkpoint(PASSIVE)", [], |_| Ok(()));
+        // SAFETY: 连接未被 close 取出，且此后不再使用
+        unsafe { ManuallyDrop::drop(&mut self.conn) };
+    }
+}
+
+// 只读系统句柄，仅开放查询接口
+pub struct ReadOnlySystem {
+    inner: CokeOvenSystem,
+}
+
+impl ReadOnlySystem {
+    pub fn temperature_stats(
+        &self,
+        coke_oven: i32,
+        start: &str,
+        end: &str,
+    ) -> Result<TempStats, CokeError> {
+        self.inner.temperature_stats(coke_oven, start, end)
+    }
+
+    pub fn all_ovens_temperature_stats(
+        &self,
+        start: &str,
+        end: &str,
+    ) -> Result<HashMap<i32, TempStats>, CokeError> {
+        self.inner.all_ovens_temperature_stats(start, end)
+    }
+
+    pub fn query_coking_cycles(&self, coke_oven: i32) -> Result<Vec<CokingCycle>, CokeError> {
+        self.inner.query_coking_cycles(coke_oven)
+    }
+
+    pub fn query_coking_cycles_paged(
+        &self,
+        coke_oven: i32,
+        limit: i64,
+        offset: i64,
+    ) -> Result<Vec<CokingCycle>, CokeError> {
+        self.inner
+            .query_coking_cycles_paged(coke_oven, limit, offset)
+    }
+
+    pub fn coking_cycle_count(&self, coke_oven: i32) -> Result<i64, CokeError> {
+        self.inner.coking_cycle_count(coke_oven)
+    }
+
+    pub fn active_ovens(&self) -> Result<Vec<i32>, CokeError> {
+        self.inner.active_ovens()
+    }
+
+    pub fn data_range(
+        &self,
+        coke_oven: i32,
+    ) -> Result<Option<(NaiveDateTime, NaiveDateTime)>, CokeError> {
+        self.inner.data_range(coke_oven)
+    }
+
+    pub fn audit_trail(&self, since: &str) -> Result<Vec<AuditEntry>, CokeError> {
+        self.inner.audit_trail(since)
+    }
+
+    pub fn operation_history(
+        &self,
+        coke_oven: i32,
+        start: &str,
+        end: &str,
+    ) -> Result<Vec<OperationRecord>, CokeError> {
+        self.inner.operation_history(coke_oven, start, end)
+    }
+}
+
+// 多线程共享的系统句柄，可克隆后分发给各采集线程
+//
+// 并发模型：所有线程共享同一个连接，由互斥锁串行化访问；SQLite 写入本身是串行的，
+// 单连接避免了多连接写入时的 SQLITE_BUSY 重试。每次调用持锁时间即一次记录的写入时间。
+// 某线程持锁时 panic 不影响其他线程继续使用（每次写入本身是原子的）
+#[derive(Clone)]
+pub struct SharedSystem {
+    inner: Arc<Mutex<CokeOvenSystem>>,
+}
+
+impl SharedSystem {
+    pub fn new(system: CokeOvenSystem) -> Self {
+        Self {
+            inner: Arc::new(Mutex::new(system)),
+        }
+    }
+
+    pub fn open(db_path: &str) -> Result<Self, CokeError> {
+        Ok(Self::new(CokeOvenSystem::new(db_path)?))
+    }
+
+    // 持锁执行任意操作
+    pub fn with<T, F>(&self, f: F) -> Result<T, CokeError>
+    where
+        F: FnOnce(&mut CokeOvenSystem) -> Result<T, CokeError>,
+    {
+        let mut guard = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+        f(&mut guard)
+    }
+
+    pub fn record_temperature(
+        &self,
+        coke_oven: i32,
+        time: &str,
+        machine_temp: f64,
+        coke_temp: f64,
+    ) -> Result<(), CokeError> {
+        self.with(|system| system.record_temperature(coke_oven, time, machine_temp, coke_temp))
+    }
+
+    pub fn record_operation(
+        &self,
+        coke_oven: i32,
+        chamber: &str,
+        op_type: &str,
+        time: &str,
+    ) -> Result<bool, CokeError> {
+        self.with(|system| system.record_operation(coke_oven, chamber, op_type, time))
+    }
+}
+
+// 将查询行（time, machine_side, coke_side）转换为温度记录
+fn map_temp_row(row: &rusqlite::Row) -> Result<TempRecord, rusqlite::Error> {
+    let time_str: String = row.get(0)?;
+    let time_dt =
+        crate::models::parse_time(&time_str).map_err(|_| rusqlite::Error::InvalidQuery)?;
+    Ok(TempRecord {
+        time: time_dt,
+        machine_side: row.get(1)?,
+        coke_side: row.get(2)?,
+    })
+}
+
+// 将查询行（id, coke_oven, chamber, operation_type, time）转换为操作记录
+fn map_operation_row(row: &rusqlite::Row) -> Result<OperationRecord, rusqlite::Error> {
+    let time_str: String = row.get(4)?;
+    let time = crate::models::parse_time(&time_str).map_err(|_| rusqlite::Error::InvalidQuery)?;
+    Ok(OperationRecord {
+        id: row.get(0)?,
+        coke_oven: row.get(1)?,
+        chamber: row.get(2)?,
+        operation_type: row.get(3)?,
+        time,
+    })
+}
+
+// 结焦周期查询列，与 map_cycle_row 的字段顺序一致
+const CYCLE_COLUMNS: &str = "id, coke_oven, chamber, loading_time, push_time, \
+     duration_hhmm, duration_minutes, avg_temp_machine, avg_temp_coke, loading_op_id, push_op_id, \
+     low_confidence";
+
+// export_cycles_csv 输出的表头
+const CYCLES_CSV_HEADER: &str = "coke_oven,chamber,loading_time,push_time,duration_hhmm,\
+     duration_minutes,avg_temp_machine,avg_temp_coke";
+
+// 将查询行转换为结焦周期
+fn map_cycle_row(row: &rusqlite::Row) -> Result<CokingCycle, rusqlite::Error> {
+    let loading_str: String = row.get(3)?;
+    let push_str: String = row.get(4)?;
+    let duration_hhmm: String = row.get(5)?;
+    let loading_time =
+        crate::models::parse_time(&loading_str).map_err(|_| rusqlite::Error::InvalidQuery)?;
+    let push_time =
+        crate::models::parse_time(&push_str).map_err(|_| rusqlite::Error::InvalidQuery)?;
+    let duration_minutes = match row.get::<_, Option<i32>>(6)? {
+        Some(minutes) => minutes,
+        None => hhmm_to_minutes(&duration_hhmm).ok_or(rusqlite::Error::InvalidQuery)?,
+    };
+
+    Ok(CokingCycle {
+        id: row.get(0)?,
+        coke_oven: row.get(1)?,
+        chamber: row.get(2)?,
+        loading_time,
+        push_time,
+        duration_hhmm,
+        duration_minutes,
+        avg_temp_machine: row.get(7)?,
+        avg_temp_coke: row.get(8)?,
+        loading_op_id: row.get(9)?,
+        push_op_id: row.get(10)?,
+        low_confidence: row.get(11)?,
+    })
+}
+
+// 计算积分面积和总时长
+//
+// 输入可以无序：先按时间排序再积分，时间完全相同的相邻点不计入面积，
+// 避免数据异常时出现负面积
+fn calculate_integral(points: &[TimeTempPoint], method: IntegrationMethod) -> (f64, f64, f64) {
+    let mut sorted: Vec<&TimeTempPoint> = points.iter().collect();
+    sorted.sort_by_key(|p| p.time);
+
+    if method == IntegrationMethod::Simpson {
+        return simpson_integral(&sorted);
+    }
+
+    let mut total_machine = 0.0;
+    let mut total_coke = 0.0;
+    let mut total_duration = 0.0;
+
+    for pair in sorted.windows(2) {
+        let (p1, p2) = (pair[0], pair[1]);
+        if p1.time == p2.time {
+            continue;
+        }
+        let duration = (p2.time - p1.time).num_seconds() as f64 / 60.0;
+        total_machine += (p1.machine + p2.machine) * duration / 2.0;
+        total_coke += (p1.coke + p2.coke) * duration / 2.0;
+        total_duration += duration;
+    }
+
+    (total_machine, total_coke, total_duration)
+}
+
+// 复合辛普森法（不等间距）：每两个相邻区间按过三点的抛物线积分，
+// 区间数为奇数时最后一个区间用梯形法；时间相同的点只保留最后一个
+fn simpson_integral(sorted: &[&TimeTempPoint]) -> (f64, f64, f64) {
+    let mut distinct: Vec<&TimeTempPoint> = Vec::with_capacity(sorted.len());
+    for &p in sorted {
+        match distinct.last_mut() {
+            Some(last) if last.time == p.time => *last = p,
+            _ => distinct.push(p),
+        }
+    }
+
+    let minutes =
+        |a: &TimeTempPoint, b: &TimeTempPoint| (b.time - a.time).num_seconds() as f64 / 60.0;
+
+    let mut total_machine = 0.0;
+    let mut total_coke = 0.0;
+    let mut i = 0;
+    while i + 2 < distinct.len() {
+        let (p0, p1, p2) = (distinct[i], distinct[i + 1], distinct[i + 2]);
+        let (h0, h1) = (minutes(p0, p1), minutes(p1, p2));
+        let parabola = |f0: f64, f1: f64, f2: f64| {
+            (h0 + h1) / 6.0
+                * ((2.0 - h1 / h0) * f0 + (h0 + h1).powi(2) / (h0 * h1) * f1 + (2.0 - h0 / h1) * f2)
+        };
+        total_machine += parabola(p0.machine, p1.machine, p2.machine);
+        total_coke += parabola(p0.coke, p1.coke, p2.coke);
+        i += 2;
+    }
+    if i + 1 < distinct.len() {
+        let (p1, p2) = (distinct[i], distinct[i + 1]);
+        let duration = minutes(p1, p2);
+        total_machine += (p1.machine + p2.machine) * duration / 2.0;
+        total_coke += (p1.coke + p2.coke) * duration / 2.0;
+    }
+
+    let total_duration = match (distinct.first(), distinct.last()) {
+        (Some(first), Some(last)) => minutes(first, last),
+        _ => 0.0,
+    };
+
+    (total_machine, total_coke, total_duration)
+}
+
+// 检查附加数据库别名：字母或下划线开头，仅含 ASCII 字母、数字、下划线，
+// 且不是内置库名，保证可安全拼接进 SQL
+fn check_attach_alias(alias: &str) -> Result<(), CokeError> {
+    let mut chars = alias.chars();
+    let well_formed = chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_');
+    let reserved = alias.eq_ignore_ascii_case("main") || alias.eq_ignore_ascii_case("temp");
+
+    if !well_formed || reserved {
+        return Err(CokeError::InvalidArgument(format!(
+            "无效数据库别名: {}",
+            alias
+        )));
+    }
+    Ok(())
+}
+
+// 唯一约束冲突转换为 Conflict 错误（重复记录），其他数据库错误原样返回
+fn unique_violation_as_conflict(e: rusqlite::Error, message: impl FnOnce() -> String) -> CokeError {
+    match e {
+        rusqlite::Error::SqliteFailure(err, _)
+            if err.extended_code == rusqlite::ffi::SQLITE_CONSTRAINT_UNIQUE =>
+        {
+            CokeError::Conflict(message())
+        }
+        e => CokeError::Db(e),
+    }
+}
+
+// 二进制格式的时间：标准格式时间字符串与秒数互转
+fn binary_timestamp(time: &str) -> Result<i64, CokeError> {
+    Ok(crate::models::parse_time(time)?.and_utc().timestamp())
+}
+
+fn binary_time(timestamp: i64) -> Result<String, CokeError> {
+    chrono::DateTime::from_timestamp(timestamp, 0)
+        .map(|dt| crate::models::format_time(dt.naive_utc()))
+        .ok_or_else(|| CokeError::InvalidArgument(format!("二进制数据中的时间无效: {}", timestamp)))
+}
+
+// 二进制格式中的字符串：u32 字节长度 + UTF-8 内容
+fn write_binary_str(writer: &mut dyn Write, value: &str) -> std::io::Result<()> {
+    writer.write_all(&(value.len() as u32).to_le_bytes())?;
+    writer.write_all(value.as_bytes())
+}
+
+fn read_binary<const N: usize>(reader: &mut dyn Read) -> std::io::Result<[u8; N]> {
+    let mut buf = [0u8; N];
+    reader.read_exact(&mut buf)?;
+    Ok(buf)
+}
+
+fn read_binary_str(reader: &mut dyn Read) -> Result<String, CokeError> {
+    // 炭化室与探头名称都很短，超长说明数据已损坏
+    const MAX_LEN: usize = 1024;
+
+    let len = u32::from_le_bytes(read_binary(reader)?) as usize;
+    if len > MAX_LEN {
+        return Err(CokeError::InvalidArgument(format!(
+            "二进制数据中的字符串过长: {}",
+            len
+        )));
+    }
+    let mut buf = vec![0u8; len];
+    reader.read_exact(&mut buf)?;
+    String::from_utf8(buf)
+        .map_err(|_| CokeError::InvalidArgument("二进制数据中的字符串不是有效 UTF-8".to_string()))
+}
+
+// CSV 字段转义：包含逗号、引号或换行时加双引号并转义内部引号
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+// FNV-1a 64 位哈希参数，算法固定，不随编译器版本变化
+const FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+fn fnv1a(mut hash: u64, bytes: &[u8]) -> u64 {
+    for byte in bytes {
+        hash ^= *byte as u64;
+        hash = hash.wrapping_mul(FNV_PRIME);
+    }
+    hash
+}
+
+// 计算线性段 y0 -> y1 在 0 以上部分的面积
+fn positive_area(y0: f64, y1: f64, duration: f64) -> f64 {
+    if y0 >= 0.0 && y1 >= 0.0 {
+        (y0 + y1) * duration / 2.0
+    } else if y0 <= 0.0 && y1 <= 0.0 {
+        0.0
+    } else {
+        // 线段穿过 0，只计算正值一侧的三角形
+        let positive = y0.max(y1);
+        positive * positive / (y0 - y1).abs() * duration / 2.0
+    }
+}
+
+// 计算基尼系数，总量为 0 时视为完全均衡
+fn gini_coefficient(values: &[f64]) -> f64 {
+    let n = values.len() as f64;
+    let total: f64 = values.iter().sum();
+    if values.is_empty() || total == 0.0 {
+        return 0.0;
+    }
+
+    let mut sorted = values.to_vec();
+    sorted.sort_by(|a, b| a.total_cmp(b));
+
+    let weighted: f64 = sorted
+        .iter()
+        .enumerate()
+        .map(|(i, x)| (i as f64 + 1.0) * x)
+        .sum();
+
+    2.0 * weighted / (n * total) - (n + 1.0) / n
+}
+
+// 辅助函数：分钟转 HH:mm
+fn minutes_to_hhmm(minutes: i32) -> String {
+    let hours = minutes / 60;
+    let minutes = minutes % 60;
+    format!("{:02}:{:02}", hours, minutes)
+}
+
+// 辅助函数：秒转 HH:MM:SS，负值带前导负号
+fn duration_to_hhmmss(seconds: i64) -> String {
+    let sign = if seconds < 0 { "-" } else { "" };
+    let seconds = seconds.unsigned_abs();
+    format!(
+        "{}{:02}:{:02}:{:02}",
+        sign,
+        seconds / 3600,
+        seconds / 60 % 60,
+        seconds % 60
+    )
+}
+
+// 辅助函数：HH:mm（或 HH:MM:SS，秒数舍去）转分钟
+fn hhmm_to_minutes(hhmm: &str) -> Option<i32> {
+    let mut parts = hhmm.split(':');
+    let hours: i32 = parts.next()?.parse().ok()?;
+    let minutes: i32 = parts.next()?.parse().ok()?;
+    if let Some(seconds) = parts.next() {
+        seconds.parse::<i32>().ok()?;
+    }
+    if parts.next().is_some() {
+        return None;
+    }
+    Some(hours * 60 + minutes)
+}
+
+// 测试代码
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::FixedClock;
+    use tempfile::NamedTempFile;
+
+    // 创建测试临时数据库
+    fn setup_test_db() -> (NamedTempFile, CokeOvenSystem) {
+        let temp_db = NamedTempFile::new().unwrap();
+        let db_path = temp_db.path().to_str().unwrap();
+        let system = CokeOvenSystem::new(db_path).unwrap();
+        (temp_db, system)
+    }
+
+    #[test]
+    fn test_minutes_to_hhmm() {
+        assert_eq!(minutes_to_hhmm(0), "00:00");
+        assert_eq!(minutes_to_hhmm(59), "00:59");
+        assert_eq!(minutes_to_hhmm(60), "01:00");
+        assert_eq!(minutes_to_hhmm(119), "01:59");
+        assert_eq!(minutes_to_hhmm(120), "02:00");
+        assert_eq!(minutes_to_hhmm(1709), "28:29"); // 样本中正确的 28 小时 29 分钟
+        assert_eq!(minutes_to_hhmm(1724), "28:44"); // 样本中错误的 28 小时 44 分钟
+    }
+
+    #[test]
+    fn test_duration_to_hhmmss() {
+        assert_eq!(duration_to_hhmmss(0), "00:00:00");
+        assert_eq!(duration_to_hhmmss(59), "00:00:59");
+        assert_eq!(duration_to_hhmmss(60), "00:01:00");
+        assert_eq!(duration_to_hhmmss(3599), "00:59:59");
+        assert_eq!(duration_to_hhmmss(3600), "01:00:00");
+        assert_eq!(duration_to_hhmmss(3661), "01:01:01");
+        assert_eq!(duration_to_hhmmss(102_570), "28:29:30");
+        assert_eq!(duration_to_hhmmss(-90), "-00:01:30");
+
+        assert_eq!(hhmm_to_minutes("28:29:30"), Some(1709));
+        assert_eq!(hhmm_to_minutes("28:29"), Some(1709));
+        assert_eq!(hhmm_to_minutes("28:29:xx"), None);
+        assert_eq!(hhmm_to_minutes("1:2:3:4"), None);
+    }
+
+    #[test]
+    fn test_duration_seconds_precision() {
+        let (_temp_db, mut system) = setup_test_db();
+
+        system
+            .record_operation(1, "1#", "LOAD", "2025-06-18 08:00:00")
+            .unwrap();
+        system
+            .record_operation(1, "1#", "PUSH", "2025-06-18 09:01:01")
+            .unwrap();
+
+        system.set_duration_seconds_precision(true);
+        system
+            .record_operation(1, "2#", "LOAD", "2025-06-18 08:00:00")
+            .unwrap();
+        system
+            .record_operation(1, "2#", "PUSH", "2025-06-18 09:01:01")
+            .unwrap();
+
+        let cycles = system.query_coking_cycles(1).unwrap();
+        let by_chamber = |c: &str| cycles.iter().find(|x| x.chamber == c).unwrap().clone();
+        assert_eq!(by_chamber("1#").duration_hhmm, "01:01");
+        assert_eq!(by_chamber("2#").duration_hhmm, "01:01:01");
+        assert_eq!(by_chamber("2#").duration_minutes, 61);
+    }
+
+    #[test]
+    fn test_record_temperature() {
+        let (_temp_db, mut system) = setup_test_db();
+
+        // 有效记录
+        assert!(system
+            .record_temperature(1, "2025-06-18 08:00", 1350.0, 1360.0)
+            .is_ok());
+
+        // 无效焦炉编号
+        assert!(system
+            .record_temperature(4, "2025-06-18 08:00", 1350.0, 1360.0)
+            .is_err());
+
+        // 无效时间格式
+        assert!(system
+            .record_temperature(1, "invalid_time", 1350.0, 1360.0)
+            .is_err());
+    }
+
+    #[test]
+    fn test_record_operation() {
+        let (_temp_db, mut system) = setup_test_db();
+
+        // 有效装煤操作，不生成周期
+        assert!(!system
+            .record_operation(1, "1#", "LOAD", "2025-06-18 08:00")
+            .unwrap());
+
+        // 有效推焦操作，与之前的装煤生成周期
+        assert!(system
+            .record_operation(1, "1#", "PUSH", "2025-06-19 12:45")
+            .unwrap());
+
+        // 没有装煤的推焦被记录但不生成周期
+        assert!(!system
+            .record_operation(1, "2#", "PUSH", "2025-06-19 12:45")
+            .unwrap());
+        assert!(!system.cycle_exists(1, "2#", "2025-06-19 12:45").unwrap());
+
+        // 无效炭化室
+        assert!(system
+            .record_operation(1, "999#", "LOAD", "2025-06-18 08:00")
+            .is_err());
+
+        // 无效操作类型
+        assert!(system
+            .record_operation(1, "1#", "INVALID", "2025-06-18 08:00")
+            .is_err());
+    }
+
+    #[test]
+    fn test_coking_cycle_calculation() {
+        let (_temp_db, mut system) = setup_test_db();
+
+        // 添加温度记录
+        system
+            .record_temperature(1, "2025-06-18 08:00", 1350.0, 1360.0)
+            .unwrap();
+        system
+            .record_temperature(1, "2025-06-19 12:00", 1400.0, 1410.0)
+            .unwrap();
+        system
+            .record_temperature(1, "2025-06-19 13:00", 1420.0, 1430.0)
+            .unwrap();
+
+        // 添加装煤和推焦操作
+        system
+            .record_operation(1, "48#", "LOAD", "2025-06-18 08:16")
+            .unwrap();
+        system
+            .record_operation(1, "48#", "PUSH", "2025-06-19 12:45")
+            .unwrap();
+
+        // 检查结焦周期是否正确计算
+        let conn = &system.conn;
+        let mut stmt = conn
+            .prepare(
+                "SELECT loading_time, push_time, duration_hhmm
+            FROM coking_cycles
+            WHERE chamber = '48#'",
+            )
+            .unwrap();
+
+        let mut rows = stmt.query([]).unwrap();
+        let row = rows.next().unwrap().unwrap();
+
+        let loading_time: String = row.get(0).unwrap();
+        let push_time: String = row.get(1).unwrap();
+        let duration_hhmm: String = row.get(2).unwrap();
+
+        assert_eq!(loading_time, "2025-06-18 08:16:00");
+        assert_eq!(push_time, "2025-06-19 12:45:00");
+
+        // 验证时间差计算
+        let load_dt = crate::models::parse_time(&loading_time).unwrap();
+        let push_dt = crate::models::parse_time(&push_time).unwrap();
+        let duration = push_dt.signed_duration_since(load_dt);
+        let minutes = duration.num_minutes() as i32;
+
+        // 计算预期值：28 小时 29 分钟
+        let expected_minutes = 28 * 60 + 29;
+        assert_eq!(
+            minutes, expected_minutes,
+            "实际分钟数：{}，预期分钟数：{}",
+            minutes, expected_minutes
+        );
+
+        let expected_duration = minutes_to_hhmm(expected_minutes);
+        assert_eq!(
+            duration_hhmm, expected_duration,
+            "实际持续时间：{}，预期持续时间：{}",
+            duration_hhmm, expected_duration
+        )
+    }
+
+    #[test]
+    fn test_get_nearest_temp_record() {
+        let (_temp_db, mut system) = setup_test_db();
+
+        // 添加温度记录
+        system
+            .record_temperature(1, "2025-06-18 08:00", 1350.0, 1360.0)
+            .unwrap();
+        system
+            .record_temperature(1, "2025-06-18 10:00", 1360.0, 1370.0)
+            .unwrap();
+        system
+            .record_temperature(1, "2025-06-18 12:00", 1370.0, 1380.0)
+            .unwrap();
+
+        // 测试在时间点之前的最近记录
+        let record = system
+            .get_nearest_temp_record(1, "2025-06-18 09:00", true)
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            record.time,
+            crate::models::parse_time("2025-06-18 08:00").unwrap()
+        );
+
+        // 测试在时间点之后的最近温度
+        let record = system
+            .get_nearest_temp_record(1, "2025-06-18 09:00", false)
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            record.time,
+            crate::models::parse_time("2025-06-18 10:00").unwrap()
+        );
+    }
+
+    #[test]
+    fn test_calculate_avg_temperature() {
+        let (_temp_db, mut system) = setup_test_db();
+
+        // 添加温度记录
+        system
+            .record_temperature(1, "2025-06-18 08:00", 100.0, 200.0)
+            .unwrap();
+        system
+            .record_temperature(1, "2025-06-18 10:00", 200.0, 300.0)
+            .unwrap();
+        system
+            .record_temperature(1, "2025-06-18 12:00", 300.0, 400.0)
+            .unwrap();
+
+        // 计算平均值
+        let (avg_machine, avg_coke) = system
+            .calculate_avg_temperature(1, "2025-06-18 09:00", "2025-06-18 11:00")
+            .unwrap();
+
+        // 预期平均值：梯形面积法计算
+        // 09:00-10:00: (150+200)/2 = 175 (机侧), (250+300)/2 = 275 (焦侧)
+        // 10:00-11:00: (200+250)/2 = 225 (机侧), (300+350)/2 = 325 (焦侧)
+        // 平均：(175+225)/2 = 200 (机侧), (275+325)/2 = 300 (焦侧)
+        assert!(
+            (avg_machine - 200.0).abs() < 0.1,
+            "机侧平均温度：{}",
+            avg_machine
+        );
+        assert!((avg_coke - 300.0).abs() < 0.1, "焦侧平均温度：{}", avg_coke);
+    }
+
+    #[test]
+    fn test_implausibly_short_cycles() {
+        let (_temp_db, mut system) = setup_test_db();
+
+        // 10 分钟的异常周期
+        system
+            .record_operation(1, "1#", "LOAD", "2025-06-18 08:00")
+            .unwrap();
+        system
+            .record_operation(1, "1#", "PUSH", "2025-06-18 08:10")
+            .unwrap();
+
+        // 正常周期
+        system
+            .record_operation(1, "2#", "LOAD", "2025-06-18 08:00")
+            .unwrap();
+        system
+            .record_operation(1, "2#", "PUSH", "2025-06-19 12:29")
+            .unwrap();
+
+        let short = system.implausibly_short_cycles(1, 60).unwrap();
+        assert_eq!(short.len(), 1);
+        assert_eq!(short[0].chamber, "1#");
+        assert_eq!(short[0].duration_minutes, 10);
+        assert_eq!(short[0].duration_hhmm, "00:10");
+
+        // 无效焦炉编号
+        assert!(system.implausibly_short_cycles(4, 60).is_err());
+    }
+
+    #[test]
+    fn test_recompute_cycle_averages() {
+        let (_temp_db, mut system) = setup_test_db();
+
+        system
+            .record_temperature(1, "2025-06-18 08:00", 100.0, 200.0)
+            .unwrap();
+        system
+            .record_temperature(1, "2025-06-18 10:00", 200.0, 300.0)
+            .unwrap();
+        system
+            .record_temperature(1, "2025-06-18 12:00", 100.0, 200.0)
+            .unwrap();
+        system
+            .record_operation(1, "5#", "LOAD", "2025-06-18 08:00")
+            .unwrap();
+        system
+            .record_operation(1, "5#", "PUSH", "2025-06-18 12:00")
+            .unwrap();
+
+        let read_avg = |system: &CokeOvenSystem| -> (f64, f64) {
+            system
+                .conn
+                .query_row(
+                    "SELECT avg_temp_machine, avg_temp_coke FROM coking_cycles
+                    WHERE chamber = '5#'",
+                    [],
+                    |row| Ok((row.get(0)?, row.get(1)?)),
+                )
+                .unwrap()
+        };
+
+        let (before_machine, before_coke) = read_avg(&system);
+        assert!((before_machine - 150.0).abs() < 0.1);
+        assert!((before_coke - 250.0).abs() < 0.1);
+
+        // 修正中间时刻的温度记录
+        system
+            .conn
+            .execute(
+                "UPDATE temperature_records SET machine_side = 300.0, coke_side = 400.0
+                WHERE coke_oven = 1 AND time = '2025-06-18 10:00:00'",
+                [],
+            )
+            .unwrap();
+
+        assert_eq!(system.recompute_cycle_averages(1, "5#").unwrap(), 1);
+
+        let (after_machine, after_coke) = read_avg(&system);
+        assert!(
+            (after_machine - 200.0).abs() < 0.1,
+            "机侧平均温度：{}",
+            after_machine
+        );
+        assert!(
+            (after_coke - 300.0).abs() < 0.1,
+            "焦侧平均温度：{}",
+            after_coke
+        );
+
+        // 无效炭化室
+        assert!(system.recompute_cycle_averages(1, "999#").is_err());
+    }
+
+    #[test]
+    fn test_utilization_inequality() {
+        // 所有炭化室各一个周期：完全均衡
+        let (_temp_db, mut system) = setup_test_db();
+        let chambers = system.ovens.get(&1).unwrap().chambers.clone();
+        for chamber in &chambers {
+            system
+                .record_operation(1, chamber, "LOAD", "2025-06-18 08:00")
+                .unwrap();
+            system
+                .record_operation(1, chamber, "PUSH", "2025-06-19 12:00")
+                .unwrap();
+        }
+        let gini = system
+            .utilization_inequality(1, "2025-06-18 00:00", "2025-06-20 00:00")
+            .unwrap();
+        assert!(gini.abs() < 1e-9, "基尼系数：{}", gini);
+
+        // 单个炭化室承担全部周期：高度不均
+        let (_temp_db2, mut system) = setup_test_db();
+        for day in 10..15 {
+            system
+                .record_operation(1, "1#", "LOAD", &format!("2025-06-{} 00:00", day))
+                .unwrap();
+            system
+                .record_operation(1, "1#", "PUSH", &format!("2025-06-{} 20:00", day))
+                .unwrap();
+        }
+        let gini = system
+            .utilization_inequality(1, "2025-06-01 00:00", "2025-06-30 00:00")
+            .unwrap();
+        assert!(gini > 0.9, "基尼系数：{}", gini);
+
+        // 无效时间
+        assert!(system
+            .utilization_inequality(1, "invalid", "2025-06-30 00:00")
+            .is_err());
+    }
+
+    #[test]
+    fn test_temperatures_where() {
+        let (_temp_db, mut system) = setup_test_db();
+
+        system
+            .record_temperature(1, "2025-06-18 08:00", 1300.0, 1310.0)
+            .unwrap();
+        system
+            .record_temperature(1, "2025-06-18 09:00", 1360.0, 1300.0)
+            .unwrap();
+        system
+            .record_temperature(1, "2025-06-18 10:00", 1380.0, 1390.0)
+            .unwrap();
+
+        // 仅筛选机侧下限
+        let records = system
+            .temperatures_where(
+                1,
+                "2025-06-18 00:00",
+                "2025-06-19 00:00",
+                Some(1350.0),
+                None,
+                None,
+                None,
+            )
+            .unwrap();
+        assert_eq!(records.len(), 2);
+        assert!(records.iter().all(|r| r.machine_side >= 1350.0));
+        assert_eq!(
+            records[0].time,
+            crate::models::parse_time("2025-06-18 09:00").unwrap()
+        );
+
+        // 组合焦侧上限
+        let records = system
+            .temperatures_where(
+                1,
+                "2025-06-18 00:00",
+                "2025-06-19 00:00",
+                Some(1350.0),
+                None,
+                None,
+                Some(1350.0),
+            )
+            .unwrap();
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].coke_side, 1300.0);
+    }
+
+    #[test]
+    fn test_chamber_temperature_spread() {
+        let (_temp_db, mut system) = setup_test_db();
+
+        system
+            .record_temperature(1, "2025-06-18 08:00", 1300.0, 1310.0)
+            .unwrap();
+        system
+            .record_temperature(1, "2025-06-18 12:00", 1340.0, 1350.0)
+            .unwrap();
+
+        // 此时没有结焦中的炭化室
+        assert!(system
+            .chamber_temperature_spread(1, "2025-06-18 10:00")
+            .is_err());
+
+        system
+            .record_operation(1, "1#", "LOAD", "2025-06-18 08:00")
+            .unwrap();
+        system
+            .record_operation(1, "2#", "LOAD", "2025-06-18 09:00")
+            .unwrap();
+        system
+            .record_operation(1, "3#", "LOAD", "2025-06-18 07:00")
+            .unwrap();
+        system
+            .record_operation(1, "3#", "PUSH", "2025-06-18 09:30")
+            .unwrap();
+
+        let chambers: Vec<String> = system
+            .in_progress_chambers(1, "2025-06-18 10:00")
+            .unwrap()
+            .into_iter()
+            .map(|(chamber, _)| chamber)
+            .collect();
+        assert_eq!(chambers, vec!["1#".to_string(), "2#".to_string()]);
+
+        // 只有焦炉级温度、没有炭化室测点时无法计算极差
+        assert!(matches!(
+            system.chamber_temperature_spread(1, "2025-06-18 10:00"),
+            Err(CokeError::NoData(_))
+        ));
+
+        // 炭化室测点：1# 10:00 插值为 (1320, 1330)，2# 为 (1290, 1345)；
+        // 已推焦的 3# 测点不参与
+        for (probe, time, machine, coke) in [
+            ("1#", "2025-06-18 08:00", 1300.0, 1320.0),
+            ("1#", "2025-06-18 12:00", 1340.0, 1340.0),
+            ("2#", "2025-06-18 09:00", 1290.0, 1345.0),
+            ("3#", "2025-06-18 10:00", 1500.0, 1500.0),
+        ] {
+            system
+                .record_temperature_probe(1, time, machine, coke, Some(probe))
+                .unwrap();
+        }
+        let (machine, coke) = system
+            .chamber_temperature_spread(1, "2025-06-18 10:00")
+            .unwrap();
+        assert!((machine - 30.0).abs() < 1e-9);
+        assert!((coke - 15.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_open_readonly() {
+        let (temp_db, mut system) = setup_test_db();
+        let db_path = temp_db.path().to_str().unwrap();
+
+        system
+            .record_temperature(1, "2025-06-18 08:00", 1300.0, 1310.0)
+            .unwrap();
+        system
+            .record_temperature(1, "2025-06-18 10:00", 1340.0, 1350.0)
+            .unwrap();
+        system
+            .record_operation(1, "1#", "LOAD", "2025-06-18 08:00")
+            .unwrap();
+        system
+            .record_operation(1, "1#", "PUSH", "2025-06-18 10:00")
+            .unwrap();
+
+        // 写入句柄仍打开时通过只读句柄查询
+        let readonly = CokeOvenSystem::open_readonly(db_path).unwrap();
+
+        let stats = readonly
+            .temperature_stats(1, "2025-06-18 00:00", "2025-06-19 00:00")
+            .unwrap();
+        assert_eq!(stats.count, 2);
+        assert_eq!(stats.min_machine, 1300.0);
+        assert_eq!(stats.max_coke, 1350.0);
+        assert!((stats.avg_machine - 1320.0).abs() < 1e-9);
+
+        let cycles = readonly.query_coking_cycles(1).unwrap();
+        assert_eq!(cycles.len(), 1);
+        assert_eq!(cycles[0].duration_hhmm, "02:00");
+
+        let ops = readonly
+            .operation_history(1, "2025-06-18 00:00", "2025-06-19 00:00")
+            .unwrap();
+        assert_eq!(ops.len(), 2);
+        assert_eq!(ops[0].operation_type, "LOAD");
+        assert_eq!(ops[1].operation_type, "PUSH");
+
+        // 只读连接拒绝写入
+        assert!(readonly
+            .inner
+            .conn
+            .execute("DELETE FROM temperature_records", [])
+            .is_err());
+
+        // 空窗口没有统计结果
+        assert!(readonly
+            .temperature_stats(1, "2025-07-01 00:00", "2025-07-02 00:00")
+            .is_err());
+    }
+
+    #[test]
+    fn test_coke_readiness_proxy() {
+        let (_temp_db, mut system) = setup_test_db();
+
+        system
+            .record_temperature(1, "2025-06-18 08:00", 900.0, 900.0)
+            .unwrap();
+        system
+            .record_temperature(1, "2025-06-19 08:00", 1100.0, 1100.0)
+            .unwrap();
+        system
+            .record_temperature(1, "2025-06-19 12:00", 1100.0, 1100.0)
+            .unwrap();
+        system
+            .record_temperature(1, "2025-06-20 08:00", 800.0, 800.0)
+            .unwrap();
+        system
+            .record_temperature(1, "2025-06-20 12:00", 800.0, 800.0)
+            .unwrap();
+        system.set_readiness_target(1000.0).unwrap();
+
+        // 焖炉阶段高于目标温度
+        let score = system
+            .coke_readiness_proxy(1, "2025-06-18 08:00", "2025-06-19 12:00")
+            .unwrap();
+        assert!((score - 1.0).abs() < 1e-9, "成熟度评分：{}", score);
+
+        // 焖炉阶段低于目标温度
+        let score = system
+            .coke_readiness_proxy(1, "2025-06-19 12:00", "2025-06-20 12:00")
+            .unwrap();
+        assert!((score - 0.8).abs() < 1e-9, "成熟度评分：{}", score);
+
+        assert!(system.set_readiness_target(0.0).is_err());
+        assert!(system
+            .coke_readiness_proxy(1, "2025-06-19 12:00", "2025-06-18 08:00")
+            .is_err());
+    }
+
+    #[test]
+    fn test_average_temperature() {
+        let (_temp_db, mut system) = setup_test_db();
+
+        // 没有温度记录
+        assert_eq!(
+            system
+                .average_temperature(1, "2025-06-18 09:00", "2025-06-18 11:00")
+                .unwrap(),
+            None
+        );
+
+        system
+            .record_temperature(1, "2025-06-18 08:00", 100.0, 200.0)
+            .unwrap();
+        system
+            .record_temperature(1, "2025-06-18 12:00", 300.0, 400.0)
+            .unwrap();
+
+        let (machine, coke) = system
+            .average_temperature(1, "2025-06-18 09:00", "2025-06-18 11:00")
+            .unwrap()
+            .unwrap();
+        assert!((machine - 200.0).abs() < 0.1);
+        assert!((coke - 300.0).abs() < 0.1);
+
+        assert!(system
+            .average_temperature(4, "2025-06-18 09:00", "2025-06-18 11:00")
+            .is_err());
+        assert!(system
+            .average_temperature(1, "2025-06-18 11:00", "2025-06-18 09:00")
+            .is_err());
+    }
+
+    #[test]
+    fn test_chambers_natural_order() {
+        let temp_db = NamedTempFile::new().unwrap();
+        let mut layout = HashMap::new();
+        layout.insert(1, CokeOven::new(&["10#", "2#", "1#"]));
+        let system =
+            CokeOvenSystem::new_with_layout(temp_db.path().to_str().unwrap(), layout).unwrap();
+
+        assert_eq!(system.chambers(1).unwrap(), vec!["1#", "2#", "10#"]);
+        assert!(system.chambers(2).is_err());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_operations_json() {
+        let (_temp_db, mut system) = setup_test_db();
+        assert_eq!(
+            system
+                .operations_json(1, "2025-06-18 00:00", "2025-06-20 00:00")
+                .unwrap(),
+            "[]"
+        );
+
+        system
+            .record_operation(1, "1#", "PUSH", "2025-06-19 08:00")
+            .unwrap();
+        system
+            .record_operation(1, "1#", "LOAD", "2025-06-18 20:00")
+            .unwrap();
+
+        let json = system
+            .operations_json(1, "2025-06-18 00:00", "2025-06-20 00:00")
+            .unwrap();
+        let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        let entries = value.as_array().unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0]["operation_type"], "LOAD");
+        assert_eq!(entries[0]["chamber"], "1#");
+        assert_eq!(entries[0]["time"], "2025-06-18 20:00:00");
+        assert_eq!(entries[1]["operation_type"], "PUSH");
+    }
+
+    #[cfg(feature = "arrow")]
+    #[test]
+    fn test_temperatures_arrow() {
+        use arrow::datatypes::{DataType, TimeUnit};
+
+        let (_temp_db, mut system) = setup_test_db();
+
+        system
+            .record_temperature(1, "2025-06-18 08:00", 1300.0, 1310.0)
+            .unwrap();
+        system
+            .record_temperature(1, "2025-06-18 09:00", 1320.0, 1330.0)
+            .unwrap();
+        system
+            .record_temperature(1, "2025-06-18 12:00", 1340.0, 1350.0)
+            .unwrap();
+
+        let batch = system
+            .temperatures_arrow(1, "2025-06-18 08:00", "2025-06-18 10:00")
+            .unwrap();
+
+        let schema = batch.schema();
+        assert_eq!(schema.fields().len(), 3);
+        assert_eq!(schema.field(0).name(), "time");
+        assert_eq!(
+            schema.field(0).data_type(),
+            &DataType::Timestamp(TimeUnit::Second, None)
+        );
+        assert_eq!(schema.field(1).name(), "machine_side");
+        assert_eq!(schema.field(2).name(), "coke_side");
+        assert_eq!(batch.num_rows(), 2);
+    }
+
+    #[test]
+    fn test_oven_deviation_from_battery() {
+        let (_temp_db, mut system) = setup_test_db();
+
+        system
+            .record_temperature(1, "2025-06-18 08:00", 1300.0, 1280.0)
+            .unwrap();
+        system
+            .record_temperature(2, "2025-06-18 08:00", 1400.0, 1300.0)
+            .unwrap();
+
+        let deviations = system
+            .oven_deviation_from_battery("2025-06-18 08:00", Side::Machine)
+            .unwrap();
+        assert_eq!(deviations, vec![(1, -50.0), (2, 50.0)]);
+
+        let deviations = system
+            .oven_deviation_from_battery("2025-06-18 08:00", Side::Coke)
+            .unwrap();
+        assert_eq!(deviations, vec![(1, -10.0), (2, 10.0)]);
+    }
+
+    #[test]
+    fn test_temperature_gaps() {
+        let (_temp_db, mut system) = setup_test_db();
+
+        for time in [
+            "2025-06-18 08:00",
+            "2025-06-18 08:30",
+            "2025-06-18 09:00",
+            "2025-06-18 11:00",
+            "2025-06-18 11:30",
+        ] {
+            system.record_temperature(1, time, 1300.0, 1310.0).unwrap();
+        }
+
+        let gaps = system
+            .temperature_gaps(1, 60, "2025-06-18 00:00", "2025-06-19 00:00")
+            .unwrap();
+        assert_eq!(
+            gaps,
+            vec![(
+                crate::models::parse_time("2025-06-18 09:00").unwrap(),
+                crate::models::parse_time("2025-06-18 11:00").unwrap()
+            )]
+        );
+
+        // 阈值足够大时没有缺口
+        assert!(system
+            .temperature_gaps(1, 180, "2025-06-18 00:00", "2025-06-19 00:00")
+            .unwrap()
+            .is_empty());
+
+        assert!(system
+            .temperature_gaps(1, 0, "2025-06-18 00:00", "2025-06-19 00:00")
+            .is_err());
+    }
+
+    #[test]
+    fn test_find_precision_collisions() {
+        let (_temp_db, system) = setup_test_db();
+
+        // 模拟标准化之前写入的原始时间字符串
+        insert_raw_temperature(&system, "2025-06-18 08:00", 1300.0);
+        insert_raw_temperature(&system, "2025-06-18 08:00:30", 1301.0);
+        insert_raw_temperature(&system, "2025-06-18 09:00", 1320.0);
+
+        let collisions = system.find_precision_collisions(1).unwrap();
+        assert_eq!(collisions.len(), 1);
+        assert_eq!(
+            collisions[0].0,
+            crate::models::parse_time("2025-06-18 08:00").unwrap()
+        );
+        assert_eq!(
+            collisions[0].1,
+            vec![
+                "2025-06-18 08:00".to_string(),
+                "2025-06-18 08:00:30".to_string()
+            ]
+        );
+    }
+
+    #[test]
+    fn test_normalize_timestamps() {
+        let (_temp_db, mut system) = setup_test_db();
+
+        // 模拟标准化之前写入的原始时间字符串
+        insert_raw_temperature(&system, "2025-06-18 08:00", 1300.0);
+        insert_raw_temperature(&system, "2025-06-18 09:00:30", 1320.0);
+        system
+            .conn
+            .execute_batch(
+                "INSERT INTO operation_records (coke_oven, chamber, operation_type, time)
+                VALUES (1, '1#', 'LOAD', '2025-06-18'), (1, '1#', 'PUSH', '2025-06-19 08:00');",
+            )
+            .unwrap();
+        insert_test_cycle(&system, "1#", "2025-06-18", "2025-06-19 08:00", None);
+
+        // 温度 1 行、操作 2 行、周期 1 行
+        assert_eq!(system.normalize_timestamps().unwrap(), 4);
+
+        let times: Vec<String> = system
+            .conn
+            .prepare(
+                "SELECT time FROM temperature_records
+                UNION ALL SELECT time FROM operation_records
+                UNION ALL SELECT loading_time FROM coking_cycles
+                UNION ALL SELECT push_time FROM coking_cycles",
+            )
+            .unwrap()
+            .query_map([], |row| row.get(0))
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(times.len(), 6);
+        for time in &times {
+            assert_eq!(
+                &crate::models::format_time(crate::models::parse_time(time).unwrap()),
+                time
+            );
+        }
+
+        // 已标准化后再次执行不更新任何行
+        assert_eq!(system.normalize_timestamps().unwrap(), 0);
+    }
+
+    #[test]
+    fn test_normalize_timestamps_collision() {
+        let (_temp_db, mut system) = setup_test_db();
+
+        insert_raw_temperature(&system, "2025-06-18 08:00", 1300.0);
+        insert_raw_temperature(&system, "2025-06-18 08:00:00", 1301.0);
+
+        match system.normalize_timestamps() {
+            Err(CokeError::Conflict(msg)) => {
+                assert!(msg.contains("temperature_records"), "错误信息：{}", msg)
+            }
+            other => panic!("预期冲突错误，实际为 {:?}", other),
+        }
+
+        // 冲突时回滚，原始数据保持不变
+        let count: i64 = system
+            .conn
+            .query_row(
+                "SELECT COUNT(*) FROM temperature_records WHERE time = '2025-06-18 08:00'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(count, 1);
+    }
+
+    #[test]
+    fn test_temperature_differential() {
+        let (_temp_db, mut system) = setup_test_db();
+
+        system
+            .record_temperature(1, "2025-06-18 08:00", 1300.0, 1310.0)
+            .unwrap();
+        system
+            .record_temperature(1, "2025-06-18 09:00", 1340.0, 1310.0)
+            .unwrap();
+        system
+            .record_temperature(1, "2025-06-18 10:00", 1300.0, 1350.0)
+            .unwrap();
+
+        let diffs = system
+            .temperature_differential(1, "2025-06-18 00:00", "2025-06-19 00:00")
+            .unwrap();
+        let values: Vec<f64> = diffs.iter().map(|d| d.1).collect();
+        assert_eq!(values, vec![-10.0, 30.0, -50.0]);
+
+        let (time, diff) = system
+            .max_differential(1, "2025-06-18 00:00", "2025-06-19 00:00")
+            .unwrap()
+            .unwrap();
+        assert_eq!(time, crate::models::parse_time("2025-06-18 10:00").unwrap());
+        assert_eq!(diff, -50.0);
+
+        assert_eq!(
+            system
+                .max_differential(1, "2025-07-01 00:00", "2025-07-02 00:00")
+                .unwrap(),
+            None
+        );
+    }
+
+    // 直接写入一条原始时间格式的温度记录（绕过标准化）
+    fn insert_raw_temperature(system: &CokeOvenSystem, time: &str, machine: f64) {
+        system
+            .conn
+            .execute(
+                "INSERT INTO temperature_records (coke_oven, time, machine_side, coke_side)
+                VALUES (1, ?1, ?2, ?2)",
+                params![time, machine],
+            )
+            .unwrap();
+    }
+
+    // 直接写入一条结焦周期记录
+    fn insert_test_cycle(
+        system: &CokeOvenSystem,
+        chamber: &str,
+        loading_time: &str,
+        push_time: &str,
+        avg_machine: Option<f64>,
+    ) {
+        let minutes = (crate::models::parse_time(push_time).unwrap()
+            - crate::models::parse_time(loading_time).unwrap())
+        .num_minutes() as i32;
+        system
+            .conn
+            .execute(
+                "INSERT INTO coking_cycles (
+                    coke_oven, chamber, loading_time, push_time,
+                    duration_hhmm, duration_minutes, avg_temp_machine, avg_temp_coke
+                ) VALUES (1, ?1, ?2, ?3, ?4, ?5, ?6, ?6)",
+                params![
+                    chamber,
+                    loading_time,
+                    push_time,
+                    minutes_to_hhmm(minutes),
+                    minutes,
+                    avg_machine
+                ],
+            )
+            .unwrap();
+    }
+
+    #[test]
+    fn test_moving_range() {
+        let (_temp_db, system) = setup_test_db();
+
+        insert_test_cycle(
+            &system,
+            "3#",
+            "2025-06-01 08:00",
+            "2025-06-02 08:00",
+            Some(1300.0),
+        );
+        insert_test_cycle(
+            &system,
+            "3#",
+            "2025-06-02 09:00",
+            "2025-06-03 09:00",
+            Some(1310.0),
+        );
+        insert_test_cycle(&system, "3#", "2025-06-03 10:00", "2025-06-04 10:00", None);
+        insert_test_cycle(
+            &system,
+            "3#",
+            "2025-06-04 11:00",
+            "2025-06-05 11:00",
+            Some(1290.0),
+        );
+        insert_test_cycle(
+            &system,
+            "3#",
+            "2025-06-05 12:00",
+            "2025-06-06 12:00",
+            Some(1295.0),
+        );
+
+        let ranges = system.moving_range(1, "3#").unwrap();
+        assert_eq!(ranges, vec![10.0, 20.0, 5.0]);
+
+        let mean = system.average_moving_range(1, "3#").unwrap().unwrap();
+        assert!((mean - 35.0 / 3.0).abs() < 1e-9);
+
+        assert_eq!(system.average_moving_range(1, "4#").unwrap(), None);
+        assert!(system.moving_range(1, "999#").is_err());
+    }
+
+    #[test]
+    fn test_cached_queries_stable() {
+        let (_temp_db, mut system) = setup_test_db();
+
+        system
+            .record_temperature(1, "2025-06-18 08:00", 100.0, 200.0)
+            .unwrap();
+        system
+            .record_temperature(1, "2025-06-18 10:00", 200.0, 300.0)
+            .unwrap();
+        system
+            .record_temperature(1, "2025-06-18 12:00", 300.0, 400.0)
+            .unwrap();
+
+        // 重复调用复用缓存语句，结果保持一致
+        let first = system
+            .calculate_avg_temperature(1, "2025-06-18 09:00", "2025-06-18 11:00")
+            .unwrap();
+        for _ in 0..10 {
+            let again = system
+                .calculate_avg_temperature(1, "2025-06-18 09:00", "2025-06-18 11:00")
+                .unwrap();
+            assert_eq!(first, again);
+        }
+
+        // 缓存中的插入语句可重复执行
+        for hour in 13..20 {
+            system
+                .record_temperature(1, &format!("2025-06-18 {}:00", hour), 300.0, 400.0)
+                .unwrap();
+        }
+        let stats = system
+            .temperature_stats(1, "2025-06-18 00:00", "2025-06-19 00:00")
+            .unwrap();
+        assert_eq!(stats.count, 10);
+
+        // 清空缓存后结果不变
+        system.conn.flush_prepared_statement_cache();
+        let after_flush = system
+            .calculate_avg_temperature(1, "2025-06-18 09:00", "2025-06-18 11:00")
+            .unwrap();
+        assert_eq!(first, after_flush);
+    }
+
+    #[test]
+    fn test_duration_minutes_stored() {
+        let (_temp_db, mut system) = setup_test_db();
+
+        system
+            .record_operation(1, "48#", "LOAD", "2025-06-18 08:16")
+            .unwrap();
+        system
+            .record_operation(1, "48#", "PUSH", "2025-06-19 12:45")
+            .unwrap();
+        system
+            .record_operation(1, "49#", "LOAD", "2025-06-18 08:00")
+            .unwrap();
+        system
+            .record_operation(1, "49#", "PUSH", "2025-06-19 12:00")
+            .unwrap();
+
+        let (hhmm, minutes): (String, i32) = system
+            .conn
+            .query_row(
+                "SELECT duration_hhmm, duration_minutes FROM coking_cycles WHERE chamber = '48#'",
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .unwrap();
+        assert_eq!(minutes, 28 * 60 + 29);
+        assert_eq!(hhmm, minutes_to_hhmm(minutes));
+        assert_eq!(hhmm_to_minutes(&hhmm), Some(minutes));
+
+        let avg = system.average_cycle_duration_minutes(1).unwrap().unwrap();
+        assert!((avg - (1709.0 + 1680.0) / 2.0).abs() < 1e-9);
+        assert_eq!(system.average_cycle_duration_minutes(2).unwrap(), None);
+    }
+
+    #[test]
+    fn test_duration_minutes_migration() {
+        let temp_db = NamedTempFile::new().unwrap();
+        let db_path = temp_db.path().to_str().unwrap();
+
+        // 旧版本结构：没有 duration_minutes 字段
+        {
+            let conn = Connection::open(db_path).unwrap();
+            conn.execute_batch(
+                "CREATE TABLE coking_cycles (
+                    id INTEGER PRIMARY KEY,
+                    coke_oven INTEGER NOT NULL,
+                    chamber TEXT NOT NULL,
+                    loading_time TEXT NOT NULL,
+                    push_time TEXT NOT NULL,
+                    duration_hhmm TEXT NOT NULL,
+                    avg_temp_machine REAL,
+                    avg_temp_coke REAL,
+                    UNIQUE(coke_oven, chamber, push_time)
+                );
+                INSERT INTO coking_cycles (coke_oven, chamber, loading_time, push_time, duration_hhmm)
+                VALUES (1, '1#', '2025-06-18 08:16', '2025-06-19 12:45', '28:29');",
+            )
+            .unwrap();
+        }
+
+        let system = CokeOvenSystem::new(db_path).unwrap();
+        let minutes: i32 = system
+            .conn
+            .query_row(
+                "SELECT duration_minutes FROM coking_cycles WHERE chamber = '1#'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(minutes, 1709);
+
+        let cycles = system.query_coking_cycles(1).unwrap();
+        assert_eq!(cycles[0].duration_minutes, 1709);
+    }
+
+    #[test]
+    fn test_cycle_exists() {
+        let (_temp_db, mut system) = setup_test_db();
+
+        system
+            .record_operation(1, "7#", "LOAD", "2025-06-18 08:00")
+            .unwrap();
+        system
+            .record_operation(1, "7#", "PUSH", "2025-06-19 12:00")
+            .unwrap();
+
+        assert!(system.cycle_exists(1, "7#", "2025-06-19 12:00").unwrap());
+        assert!(!system.cycle_exists(1, "7#", "2025-06-19 13:00").unwrap());
+        assert!(!system.cycle_exists(1, "8#", "2025-06-19 12:00").unwrap());
+
+        assert!(system.cycle_exists(4, "7#", "2025-06-19 12:00").is_err());
+        assert!(system.cycle_exists(1, "999#", "2025-06-19 12:00").is_err());
+        assert!(system.cycle_exists(1, "7#", "invalid").is_err());
+    }
+
+    #[test]
+    fn test_rolling_on_time_ratio() {
+        let (_temp_db, mut system) = setup_test_db();
+
+        // 未设置目标结焦时间
+        assert!(system
+            .rolling_on_time_ratio(1, "2025-06-02", "2025-06-08", "24:00", 48, 30)
+            .is_err());
+        system.set_expected_cycle_minutes(1, 24 * 60).unwrap();
+
+        // 前期按时（24 小时），后期超时（26 小时）
+        insert_test_cycle(&system, "1#", "2025-06-01 06:00", "2025-06-02 06:00", None);
+        insert_test_cycle(&system, "2#", "2025-06-01 12:00", "2025-06-02 12:10", None);
+        insert_test_cycle(&system, "3#", "2025-06-05 00:00", "2025-06-06 02:00", None);
+        insert_test_cycle(&system, "4#", "2025-06-05 06:00", "2025-06-06 08:00", None);
+
+        let ratios = system
+            .rolling_on_time_ratio(1, "2025-06-03", "2025-06-08", "24:00", 48, 30)
+            .unwrap();
+        let expected = vec![
+            (crate::models::parse_time("2025-06-03").unwrap(), 1.0),
+            (crate::models::parse_time("2025-06-04").unwrap(), 1.0),
+            (crate::models::parse_time("2025-06-07").unwrap(), 0.0),
+            (crate::models::parse_time("2025-06-08").unwrap(), 0.0),
+        ];
+        assert_eq!(ratios, expected);
+
+        // 窗口跨越好坏两个时期
+        let ratios = system
+            .rolling_on_time_ratio(1, "2025-06-07", "2025-06-07", "24:00", 24 * 7, 30)
+            .unwrap();
+        assert_eq!(ratios[0].1, 0.5);
+
+        assert!(system
+            .rolling_on_time_ratio(1, "2025-06-03", "2025-06-08", "00:00", 48, 30)
+            .is_err());
+    }
+
+    #[test]
+    fn test_record_operation_idempotent() {
+        let (_temp_db, mut system) = setup_test_db();
+
+        assert!(system
+            .record_operation_idempotent(1, "1#", "LOAD", "2025-06-18 08:00")
+            .unwrap());
+
+        // 重复装煤视为成功但不插入
+        assert!(!system
+            .record_operation_idempotent(1, "1#", "LOAD", "2025-06-18 08:00")
+            .unwrap());
+
+        let count: i64 = system
+            .conn
+            .query_row("SELECT COUNT(*) FROM operation_records", [], |row| {
+                row.get(0)
+            })
+            .unwrap();
+        assert_eq!(count, 1);
+
+        // 同一时间不同操作类型仍报错
+        assert!(system
+            .record_operation_idempotent(1, "1#", "PUSH", "2025-06-18 08:00")
+            .is_err());
+
+        // 推焦照常生成结焦周期
+        assert!(system
+            .record_operation_idempotent(1, "1#", "PUSH", "2025-06-19 08:00")
+            .unwrap());
+        assert!(system.cycle_exists(1, "1#", "2025-06-19 08:00").unwrap());
+    }
+
+    #[test]
+    fn test_purge_invalid_cycles() {
+        let (_temp_db, mut system) = setup_test_db();
+
+        insert_test_cycle(&system, "1#", "2025-06-18 08:00", "2025-06-19 08:00", None);
+        insert_test_cycle(&system, "2#", "2025-06-19 08:00", "2025-06-18 06:50", None);
+        insert_test_cycle(&system, "3#", "2025-06-18 08:00", "2025-06-18 08:00", None);
+
+        let negative = system.find_negative_durations().unwrap();
+        assert_eq!(negative.len(), 1);
+        let chamber: String = system
+            .conn
+            .query_row(
+                "SELECT chamber FROM coking_cycles WHERE id = ?1",
+                [negative[0]],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(chamber, "2#");
+
+        // 负时长与零时长均被删除
+        assert_eq!(system.purge_invalid_cycles().unwrap(), 2);
+        assert!(system.find_negative_durations().unwrap().is_empty());
+        assert_eq!(system.query_coking_cycles(1).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_record_operation_strict() {
+        let (_temp_db, mut system) = setup_test_db();
+
+        // 宽松模式：没有装煤的推焦被记录，但不生成周期
+        assert!(!system
+            .record_operation(1, "1#", "PUSH", "2025-06-18 08:00")
+            .unwrap());
+        assert!(!system.cycle_exists(1, "1#", "2025-06-18 08:00").unwrap());
+
+        // 严格模式：没有装煤的推焦报错且不写入
+        assert!(system
+            .record_operation_strict(1, "2#", "PUSH", "2025-06-18 08:00")
+            .is_err());
+        let count: i64 = system
+            .conn
+            .query_row(
+                "SELECT COUNT(*) FROM operation_records WHERE chamber = '2#'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(count, 0);
+
+        // 严格模式：装煤后推焦正常
+        system
+            .record_operation_strict(1, "2#", "LOAD", "2025-06-18 09:00")
+            .unwrap();
+        assert!(system
+            .record_operation_strict(1, "2#", "PUSH", "2025-06-19 09:00")
+            .unwrap());
+        assert!(system.cycle_exists(1, "2#", "2025-06-19 09:00").unwrap());
+    }
+
+    #[test]
+    fn test_phase_slopes() {
+        let (_temp_db, mut system) = setup_test_db();
+
+        // 先快后慢的凸形升温曲线
+        system
+            .record_temperature(1, "2025-06-18 00:00", 0.0, 100.0)
+            .unwrap();
+        system
+            .record_temperature(1, "2025-06-18 10:00", 800.0, 900.0)
+            .unwrap();
+        system
+            .record_temperature(1, "2025-06-18 20:00", 1100.0, 1200.0)
+            .unwrap();
+        system
+            .record_temperature(1, "2025-06-19 06:00", 1200.0, 1300.0)
+            .unwrap();
+
+        let slopes = system
+            .phase_slopes(1, "2025-06-18 00:00", "2025-06-19 06:00")
+            .unwrap();
+        assert!((slopes[0].0 - 80.0).abs() < 1e-9);
+        assert!((slopes[1].0 - 30.0).abs() < 1e-9);
+        assert!((slopes[2].0 - 10.0).abs() < 1e-9);
+        assert!(slopes[0].1 > slopes[1].1 && slopes[1].1 > slopes[2].1);
+
+        assert!(system
+            .phase_slopes(1, "2025-06-19 06:00", "2025-06-18 00:00")
+            .is_err());
+    }
+
+    #[test]
+    fn test_cycle_counts() {
+        let (_temp_db, system) = setup_test_db();
+
+        insert_test_cycle(&system, "11#", "2025-06-01 08:00", "2025-06-02 08:00", None);
+        insert_test_cycle(&system, "11#", "2025-06-02 09:00", "2025-06-03 09:00", None);
+        insert_test_cycle(&system, "2#", "2025-06-01 08:00", "2025-06-02 08:00", None);
+
+        let counts = system.cycle_counts(1).unwrap();
+        assert_eq!(counts.len(), system.ovens[&1].chambers.len());
+        assert_eq!(counts[0], ("1#".to_string(), 0));
+        assert_eq!(counts[1], ("2#".to_string(), 1));
+
+        let eleven = counts.iter().position(|(c, _)| c == "11#").unwrap();
+        let nine = counts.iter().position(|(c, _)| c == "9#").unwrap();
+        assert!(nine < eleven);
+        assert_eq!(counts[eleven].1, 2);
+
+        assert!(system.cycle_counts(4).is_err());
+    }
+
+    #[test]
+    fn test_record_density() {
+        let (_temp_db, mut system) = setup_test_db();
+
+        for minute in [0, 15, 30, 45] {
+            system
+                .record_temperature(1, &format!("2025-06-18 08:{:02}", minute), 1300.0, 1310.0)
+                .unwrap();
+        }
+        system
+            .record_temperature(1, "2025-06-18 10:30", 1300.0, 1310.0)
+            .unwrap();
+
+        let density = system
+            .record_density(1, "2025-06-18 08:00", "2025-06-18 11:00", 1)
+            .unwrap();
+        let counts: Vec<i64> = density.iter().map(|d| d.1).collect();
+        assert_eq!(counts, vec![4, 0, 1]);
+        assert_eq!(
+            density[1].0,
+            crate::models::parse_time("2025-06-18 09:00").unwrap()
+        );
+
+        assert!(system
+            .record_density(1, "2025-06-18 08:00", "2025-06-18 11:00", 0)
+            .is_err());
+    }
+
+    #[test]
+    fn test_timestamps_normalized_on_insert() {
+        let (_temp_db, mut system) = setup_test_db();
+
+        system
+            .record_temperature(1, "2025-06-18 08:00", 1300.0, 1310.0)
+            .unwrap();
+        system
+            .record_temperature(2, "2025-06-18 08:00:00", 1300.0, 1310.0)
+            .unwrap();
+        system
+            .record_operation(1, "1#", "LOAD", "2025-06-18T08:00")
+            .unwrap();
+        system
+            .record_operation(2, "76#", "LOAD", "2025/06/18 08:00:00")
+            .unwrap();
+
+        let times: Vec<String> = system
+            .conn
+            .prepare(
+                "SELECT time FROM temperature_records
+                UNION ALL SELECT time FROM operation_records",
+            )
+            .unwrap()
+            .query_map([], |row| row.get(0))
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(times, vec!["2025-06-18 08:00:00"; 4]);
+
+        // 带秒与不带秒的输入视为同一时刻
+        assert!(system
+            .record_temperature(1, "2025-06-18 08:00:00", 1301.0, 1311.0)
+            .is_err());
+
+        // 查询边界同样按标准格式比较
+        let stats = system
+            .temperature_stats(1, "2025-06-18 00:00", "2025-06-18 08:00")
+            .unwrap();
+        assert_eq!(stats.count, 1);
+    }
+
+    #[test]
+    fn test_timestamp_normalization_migration() {
+        let temp_db = NamedTempFile::new().unwrap();
+        let db_path = temp_db.path().to_str().unwrap();
+
+        // 旧版本数据库中混合格式的时间
+        {
+            let conn = Connection::open(db_path).unwrap();
+            initialize_db(&conn, JournalMode::Wal).unwrap();
+            conn.execute_batch(
+                "PRAGMA user_version = 0;
+                INSERT INTO temperature_records (coke_oven, time, machine_side, coke_side)
+                VALUES (1, '2025-06-18 08:00', 1300.0, 1310.0),
+                       (1, '2025-06-18 09:00:30', 1320.0, 1330.0);",
+            )
+            .unwrap();
+        }
+
+        let system = CokeOvenSystem::new(db_path).unwrap();
+        let times: Vec<String> = system
+            .conn
+            .prepare("SELECT time FROM temperature_records ORDER BY time")
+            .unwrap()
+            .query_map([], |row| row.get(0))
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(times, vec!["2025-06-18 08:00:00", "2025-06-18 09:00:30"]);
+    }
+
+    #[test]
+    fn test_over_target_degree_hours() {
+        let (_temp_db, mut system) = setup_test_db();
+
+        // 前一天持续高于目标 100℃，后一天低于目标
+        system
+            .record_temperature(1, "2025-06-01 00:00", 1400.0, 1400.0)
+            .unwrap();
+        system
+            .record_temperature(1, "2025-06-02 00:00", 1400.0, 1400.0)
+            .unwrap();
+        system
+            .record_temperature(1, "2025-06-03 00:00", 1200.0, 1200.0)
+            .unwrap();
+        system
+            .record_temperature(1, "2025-06-04 00:00", 1200.0, 1200.0)
+            .unwrap();
+
+        // 未设置目标温度
+        assert!(system
+            .over_target_degree_hours(1, "2025-06-01", "2025-06-05")
+            .is_err());
+        system.set_target_temperature(1, 1300.0).unwrap();
+
+        insert_test_cycle(&system, "1#", "2025-06-01 00:00", "2025-06-02 00:00", None);
+        insert_test_cycle(&system, "2#", "2025-06-03 00:00", "2025-06-04 00:00", None);
+
+        let totals = system
+            .over_target_degree_hours(1, "2025-06-01", "2025-06-05")
+            .unwrap();
+        assert_eq!(totals.len(), 2);
+        assert_eq!(totals[0].0, "1#");
+        assert!((totals[0].1 - 2400.0).abs() < 1e-6, "度时：{}", totals[0].1);
+        assert_eq!(totals[1], ("2#".to_string(), 0.0));
+    }
+
+    #[test]
+    fn test_positive_area() {
+        assert_eq!(positive_area(10.0, 20.0, 2.0), 30.0);
+        assert_eq!(positive_area(-10.0, -20.0, 2.0), 0.0);
+        // 从 -10 线性升至 10，正值部分为后半段的三角形
+        assert!((positive_area(-10.0, 10.0, 2.0) - 5.0).abs() < 1e-9);
+        assert!((positive_area(10.0, -10.0, 2.0) - 5.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_rolling_average() {
+        let (_temp_db, mut system) = setup_test_db();
+
+        for (i, minute) in [0, 10, 20, 30, 40].iter().enumerate() {
+            let value = i as f64 * 10.0;
+            system
+                .record_temperature(
+                    1,
+                    &format!("2025-06-18 08:{:02}", minute),
+                    value,
+                    value + 100.0,
+                )
+                .unwrap();
+        }
+
+        let series = system
+            .rolling_average(1, 20, "2025-06-18 07:00", "2025-06-18 09:00")
+            .unwrap();
+        let machine: Vec<f64> = series.iter().map(|p| p.1).collect();
+        // 前两条历史不足，使用已有记录
+        assert_eq!(machine, vec![0.0, 5.0, 10.0, 20.0, 30.0]);
+        assert_eq!(series[4].2, 130.0);
+        assert_eq!(
+            series[4].0,
+            crate::models::parse_time("2025-06-18 08:40").unwrap()
+        );
+
+        assert!(system
+            .rolling_average(1, -1, "2025-06-18 07:00", "2025-06-18 09:00")
+            .is_err());
+    }
+
+    #[test]
+    fn test_content_checksum() {
+        let (_temp_db_a, mut system_a) = setup_test_db();
+        let (_temp_db_b, mut system_b) = setup_test_db();
+
+        system_a
+            .record_temperature(1, "2025-06-18 08:00", 1300.0, 1310.0)
+            .unwrap();
+        system_a
+            .record_temperature(1, "2025-06-18 09:00", 1320.0, 1330.0)
+            .unwrap();
+        system_a
+            .record_operation(1, "1#", "LOAD", "2025-06-18 08:00")
+            .unwrap();
+        system_a
+            .record_operation(1, "2#", "LOAD", "2025-06-18 08:00")
+            .unwrap();
+
+        // 相同数据以不同顺序、不同时间格式写入
+        system_b
+            .record_operation(1, "2#", "LOAD", "2025-06-18 08:00:00")
+            .unwrap();
+        system_b
+            .record_temperature(1, "2025-06-18 09:00", 1320.0, 1330.0)
+            .unwrap();
+        system_b
+            .record_operation(1, "1#", "LOAD", "2025-06-18T08:00")
+            .unwrap();
+        system_b
+            .record_temperature(1, "2025-06-18 08:00", 1300.0, 1310.0)
+            .unwrap();
+
+        let checksum_a = system_a.content_checksum(1).unwrap();
+        assert_eq!(checksum_a, system_b.content_checksum(1).unwrap());
+
+        // 数据不同时校验和不同
+        system_b
+            .record_temperature(1, "2025-06-18 10:00", 1320.0, 1330.0)
+            .unwrap();
+        assert_ne!(checksum_a, system_b.content_checksum(1).unwrap());
+
+        assert!(system_a.content_checksum(4).is_err());
+    }
+
+    #[test]
+    fn test_content_checksum_with_probes() {
+        let (_temp_db_a, mut system_a) = setup_test_db();
+        let (_temp_db_b, mut system_b) = setup_test_db();
+
+        // 同一时刻两个探头的记录以相反顺序写入
+        system_a
+            .record_temperature_probe(1, "2025-06-18 08:00", 1300.0, 1310.0, Some("M1"))
+            .unwrap();
+        system_a
+            .record_temperature_probe(1, "2025-06-18 08:00", 1320.0, 1330.0, Some("M2"))
+            .unwrap();
+        system_b
+            .record_temperature_probe(1, "2025-06-18 08:00", 1320.0, 1330.0, Some("M2"))
+            .unwrap();
+        system_b
+            .record_temperature_probe(1, "2025-06-18 08:00", 1300.0, 1310.0, Some("M1"))
+            .unwrap();
+        let checksum = system_a.content_checksum(1).unwrap();
+        assert_eq!(checksum, system_b.content_checksum(1).unwrap());
+
+        // 读数归属的探头改变时校验和不同
+        let (_temp_db_c, mut system_c) = setup_test_db();
+        system_c
+            .record_temperature_probe(1, "2025-06-18 08:00", 1300.0, 1310.0, Some("M2"))
+            .unwrap();
+        system_c
+            .record_temperature_probe(1, "2025-06-18 08:00", 1320.0, 1330.0, Some("M1"))
+            .unwrap();
+        assert_ne!(checksum, system_c.content_checksum(1).unwrap());
+    }
+
+    #[test]
+    fn test_backup_to() {
+        let (_temp_db, mut system) = setup_test_db();
+        system
+            .record_temperature(1, "2025-06-18 08:00", 1300.0, 1310.0)
+            .unwrap();
+        system
+            .record_operation(1, "1#", "LOAD", "2025-06-18 08:00")
+            .unwrap();
+        system
+            .record_operation(1, "1#", "PUSH", "2025-06-18 10:00")
+            .unwrap();
+
+        let backup_dir = tempfile::tempdir().unwrap();
+        let backup_path = backup_dir.path().join("backup.db");
+        let backup_path = backup_path.to_str().unwrap();
+        system.backup_to(backup_path).unwrap();
+
+        // 原系统保持打开状态时读取备份
+        let readonly = CokeOvenSystem::open_readonly(backup_path).unwrap();
+        let stats = readonly
+            .temperature_stats(1, "2025-06-18 00:00", "2025-06-19 00:00")
+            .unwrap();
+        assert_eq!(stats.count, 1);
+        assert_eq!(readonly.query_coking_cycles(1).unwrap().len(), 1);
+
+        let missing_dir = backup_dir.path().join("missing").join("backup.db");
+        assert!(system.backup_to(missing_dir.to_str().unwrap()).is_err());
+    }
+
+    #[test]
+    fn test_checkpoint() {
+        let (temp_db, mut system) = setup_test_db();
+        for hour in 0..10 {
+            system
+                .record_temperature(1, &format!("2025-06-18 {:02}:00", hour), 1300.0, 1310.0)
+                .unwrap();
+        }
+
+        let wal_path = format!("{}-wal", temp_db.path().to_str().unwrap());
+        assert!(std::fs::metadata(&wal_path).unwrap().len() > 0);
+
+        system.checkpoint().unwrap();
+        assert_eq!(std::fs::metadata(&wal_path).unwrap().len(), 0);
+
+        // 检查点后数据仍可查询
+        let stats = system
+            .temperature_stats(1, "2025-06-18 00:00", "2025-06-19 00:00")
+            .unwrap();
+        assert_eq!(stats.count, 10);
+        system.checkpoint().unwrap();
+    }
+
+    #[test]
+    fn test_export_cycles_csv() {
+        let (_temp_db, mut system) = setup_test_db();
+        system
+            .record_temperature(1, "2025-06-18 08:00", 1300.0, 1310.0)
+            .unwrap();
+        system
+            .record_temperature(1, "2025-06-18 10:00", 1300.0, 1310.0)
+            .unwrap();
+        system
+            .record_operation(1, "1#", "LOAD", "2025-06-18 08:00")
+            .unwrap();
+        system
+            .record_operation(1, "1#", "PUSH", "2025-06-18 10:00")
+            .unwrap();
+        insert_test_cycle(
+            &system,
+            "2#",
+            "2025-06-19 08:00:00",
+            "2025-06-19 09:30:00",
+            None,
+        );
+
+        let mut out = Vec::new();
+        let rows = system
+            .export_cycles_csv(1, "2025-06-18 00:00", "2025-06-20 00:00", &mut out)
+            .unwrap();
+        assert_eq!(rows, 2);
+
+        let text = String::from_utf8(out).unwrap();
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert!(lines[0].starts_with("coke_oven,chamber,"));
+        assert_eq!(
+            lines[1],
+            "1,1#,2025-06-18 08:00:00,2025-06-18 10:00:00,02:00,120,1300,1310"
+        );
+        assert_eq!(
+            lines[2],
+            "1,2#,2025-06-19 08:00:00,2025-06-19 09:30:00,01:30,90,,"
+        );
+
+        // 范围外的周期不导出
+        let mut out = Vec::new();
+        let rows = system
+            .export_cycles_csv(1, "2025-06-19 00:00", "2025-06-20 00:00", &mut out)
+            .unwrap();
+        assert_eq!(rows, 1);
+
+        assert!(system
+            .export_cycles_csv(4, "2025-06-18 00:00", "2025-06-20 00:00", &mut Vec::new())
+            .is_err());
+        assert_eq!(csv_field("a,b"), "\"a,b\"");
+        assert_eq!(csv_field("a\"b"), "\"a\"\"b\"");
+    }
+
+    #[test]
+    fn test_spike_anomalies() {
+        let (_temp_db, mut system) = setup_test_db();
+
+        // 平稳升温：每 10 分钟升高 20℃（2℃/分钟）
+        for (i, minute) in [0, 10, 20, 30].iter().enumerate() {
+            let temp = 1200.0 + 20.0 * i as f64;
+            system
+                .record_temperature(1, &format!("2025-06-18 08:{:02}", minute), temp, temp)
+                .unwrap();
+        }
+        let clean = system
+            .spike_anomalies(1, 5.0, "2025-06-18 00:00", "2025-06-19 00:00")
+            .unwrap();
+        assert!(clean.is_empty());
+
+        // 08:31 焦侧跳变 300℃，08:40 恢复正常
+        system
+            .record_temperature(1, "2025-06-18 08:31", 1262.0, 1560.0)
+            .unwrap();
+        system
+            .record_temperature(1, "2025-06-18 08:40", 1280.0, 1280.0)
+            .unwrap();
+
+        let spikes = system
+            .spike_anomalies(1, 5.0, "2025-06-18 00:00", "2025-06-19 00:00")
+            .unwrap();
+        assert_eq!(spikes.len(), 2);
+        assert_eq!(
+            spikes[0].0,
+            crate::models::parse_time("2025-06-18 08:31").unwrap()
+        );
+        assert!((spikes[0].1 - 300.0).abs() < 1e-9);
+        assert!((spikes[1].1 - 280.0 / 9.0).abs() < 1e-9);
+
+        assert!(system
+            .spike_anomalies(1, 0.0, "2025-06-18 00:00", "2025-06-19 00:00")
+            .is_err());
+    }
+
+    #[test]
+    fn test_rebuild_all_cycles() {
+        let (_temp_immediate, mut immediate) = setup_test_db();
+        let (_temp_deferred, mut deferred) = setup_test_db();
+
+        let temps = [
+            ("2025-06-18 08:00", 1300.0),
+            ("2025-06-18 12:00", 1340.0),
+            ("2025-06-18 20:00", 1320.0),
+            ("2025-06-19 06:00", 1360.0),
+        ];
+        let operations = [
+            ("1#", "LOAD", "2025-06-18 08:00"),
+            ("2#", "LOAD", "2025-06-18 09:00"),
+            ("1#", "PUSH", "2025-06-18 20:00"),
+            ("1#", "LOAD", "2025-06-18 20:30"),
+            ("2#", "PUSH", "2025-06-18 21:15"),
+            ("1#", "PUSH", "2025-06-19 06:00"),
+            ("3#", "PUSH", "2025-06-19 06:00"),
+        ];
+
+        for system in [&mut immediate, &mut deferred] {
+            for (time, temp) in temps {
+                system
+                    .record_temperature(1, time, temp, temp + 10.0)
+                    .unwrap();
+            }
+        }
+        for (chamber, op_type, time) in operations {
+            immediate
+                .record_operation(1, chamber, op_type, time)
+                .unwrap();
+            deferred
+                .record_operation_deferred(1, chamber, op_type, time)
+                .unwrap();
+        }
+
+        assert!(deferred.query_coking_cycles(1).unwrap().is_empty());
+        assert_eq!(deferred.rebuild_all_cycles(1).unwrap(), 3);
+
+        // 除自增 id 外与逐条计算的结果一致
+        let strip_ids = |cycles: Vec<CokingCycle>| {
+            let mut cycles: Vec<CokingCycle> = cycles
+                .into_iter()
+                .map(|c| CokingCycle { id: 0, ..c })
+                .collect();
+            cycles.sort_by(|a, b| (a.push_time, &a.chamber).cmp(&(b.push_time, &b.chamber)));
+            cycles
+        };
+        let expected = strip_ids(immediate.query_coking_cycles(1).unwrap());
+        assert_eq!(expected.len(), 3);
+        assert_eq!(
+            strip_ids(deferred.query_coking_cycles(1).unwrap()),
+            expected
+        );
+
+        // 重复重建结果不变
+        assert_eq!(deferred.rebuild_all_cycles(1).unwrap(), 3);
+        assert_eq!(
+            strip_ids(deferred.query_coking_cycles(1).unwrap()),
+            expected
+        );
+
+        assert!(deferred.rebuild_all_cycles(4).is_err());
+    }
+
+    #[test]
+    fn test_load_pairing_policy() {
+        let operations = [
+            ("1#", "LOAD", "2025-06-18 08:00"),
+            ("1#", "LOAD", "2025-06-18 09:00"),
+            ("1#", "PUSH", "2025-06-18 20:00"),
+            ("1#", "PUSH", "2025-06-18 21:00"),
+        ];
+
+        // 默认策略：与最近一次装煤配对，重复推焦仍与同一次装煤配对
+        let (_temp_latest, mut latest) = setup_test_db();
+        for (chamber, op_type, time) in operations {
+            latest.record_operation(1, chamber, op_type, time).unwrap();
+        }
+        let cycles = latest.query_coking_cycles(1).unwrap();
+        assert_eq!(cycles.len(), 2);
+        assert_eq!(cycles[0].duration_hhmm, "11:00");
+        assert_eq!(cycles[1].duration_hhmm, "12:00");
+
+        // 最早策略：与上次推焦后的第一次装煤配对
+        let (_temp_earliest, mut earliest) = setup_test_db();
+        earliest.set_load_pairing(LoadPairing::Earliest);
+        for (chamber, op_type, time) in operations {
+            earliest
+                .record_operation(1, chamber, op_type, time)
+                .unwrap();
+        }
+        let cycles = earliest.query_coking_cycles(1).unwrap();
+        assert_eq!(cycles.len(), 1);
+        assert_eq!(cycles[0].duration_hhmm, "12:00");
+
+        // 重建周期遵循相同策略
+        assert_eq!(earliest.rebuild_all_cycles(1).unwrap(), 1);
+        assert_eq!(
+            earliest.query_coking_cycles(1).unwrap()[0].duration_hhmm,
+            "12:00"
+        );
+        assert_eq!(latest.rebuild_all_cycles(1).unwrap(), 2);
+    }
+
+    #[test]
+    fn test_multiple_unpaired_loads() {
+        let (_temp_db, mut system) = setup_test_db();
+        for (chamber, op_type, time) in [
+            ("2#", "LOAD", "2025-06-18 08:00"),
+            ("2#", "LOAD", "2025-06-18 09:00"),
+            ("2#", "PUSH", "2025-06-18 20:00"),
+            ("2#", "LOAD", "2025-06-18 21:00"),
+            ("1#", "LOAD", "2025-06-18 08:00"),
+            ("1#", "PUSH", "2025-06-18 20:00"),
+            ("11#", "LOAD", "2025-06-18 08:00"),
+            ("11#", "LOAD", "2025-06-18 10:00"),
+            ("11#", "LOAD", "2025-06-18 12:00"),
+        ] {
+            system
+                .record_operation_deferred(1, chamber, op_type, time)
+                .unwrap();
+        }
+
+        let groups = system.multiple_unpaired_loads(1).unwrap();
+        assert_eq!(groups.len(), 2);
+        assert_eq!(groups[0].0, "2#");
+        assert_eq!(groups[0].1.len(), 2);
+        assert_eq!(groups[1].0, "11#");
+        assert_eq!(groups[1].1.len(), 3);
+
+        assert!(system.multiple_unpaired_loads(4).is_err());
+    }
+
+    #[test]
+    fn test_record_dt_matches_string_path() {
+        let (_temp_str, mut by_str) = setup_test_db();
+        let (_temp_dt, mut by_dt) = setup_test_db();
+
+        let load = crate::models::parse_time("2025-06-18 08:00:30").unwrap();
+        let push = crate::models::parse_time("2025-06-18 10:00").unwrap();
+
+        by_str
+            .record_temperature(1, "2025-06-18T08:00:30", 1300.0, 1310.0)
+            .unwrap();
+        by_str
+            .record_temperature(1, "2025/06/18 10:00", 1320.0, 1330.0)
+            .unwrap();
+        by_str
+            .record_operation(1, "1#", "LOAD", "2025-06-18 08:00:30")
+            .unwrap();
+        by_str
+            .record_operation(1, "1#", "PUSH", "2025-06-18 10:00")
+            .unwrap();
+
+        by_dt
+            .record_temperature_dt(1, load, 1300.0, 1310.0)
+            .unwrap();
+        by_dt
+            .record_temperature_dt(1, push, 1320.0, 1330.0)
+            .unwrap();
+        by_dt.record_operation_dt(1, "1#", "LOAD", load).unwrap();
+        by_dt.record_operation_dt(1, "1#", "PUSH", push).unwrap();
+
+        let dump = |system: &CokeOvenSystem| -> Vec<String> {
+            let mut stmt = system
+                .conn
+                .prepare(
+                    "SELECT time || '|' || machine_side || '|' || coke_side FROM temperature_records
+                    UNION ALL
+                    SELECT chamber || '|' || operation_type || '|' || time FROM operation_records
+                    UNION ALL
+                    SELECT loading_time || '|' || push_time || '|' || duration_hhmm FROM coking_cycles",
+                )
+                .unwrap();
+            let rows = stmt.query_map([], |row| row.get(0)).unwrap();
+            rows.collect::<Result<Vec<_>, _>>().unwrap()
+        };
+        assert_eq!(dump(&by_str), dump(&by_dt));
+        assert_eq!(dump(&by_dt)[0], "2025-06-18 08:00:30|1300.0|1310.0");
+
+        assert!(by_dt
+            .record_temperature_dt(4, load, 1300.0, 1310.0)
+            .is_err());
+        assert!(by_dt.record_operation_dt(1, "1#", "OPEN", load).is_err());
+    }
+
+    #[test]
+    fn test_chamber_states() {
+        let (_temp_db, mut system) = setup_test_db();
+        system
+            .record_operation(1, "1#", "LOAD", "2025-06-18 08:00")
+            .unwrap();
+        system
+            .record_operation(1, "2#", "LOAD", "2025-06-18 06:00")
+            .unwrap();
+        system
+            .record_operation(1, "2#", "PUSH", "2025-06-18 09:00")
+            .unwrap();
+
+        let states = system.chamber_states(1).unwrap();
+        assert_eq!(states.len(), system.chambers(1).unwrap().len());
+        assert_eq!(
+            states[0],
+            (
+                "1#".to_string(),
+                ChamberState::Loaded {
+                    since: crate::models::parse_time("2025-06-18 08:00").unwrap()
+                }
+            )
+        );
+        assert_eq!(states[1], ("2#".to_string(), ChamberState::Empty));
+        assert_eq!(states[2], ("3#".to_string(), ChamberState::Empty));
+
+        assert!(system.chamber_states(4).is_err());
+    }
+
+    #[test]
+    fn test_cycle_operation_ids() {
+        let (_temp_db, mut system) = setup_test_db();
+        system
+            .record_operation(1, "1#", "LOAD", "2025-06-18 08:00")
+            .unwrap();
+        system
+            .record_operation(1, "2#", "LOAD", "2025-06-18 08:30")
+            .unwrap();
+        system
+            .record_operation(1, "1#", "PUSH", "2025-06-18 20:00")
+            .unwrap();
+
+        let op_id = |system: &CokeOvenSystem, chamber: &str, op_type: &str| -> i64 {
+            system
+                .conn
+                .query_row(
+                    "SELECT id FROM operation_records WHERE chamber = ?1 AND operation_type = ?2",
+                    params![chamber, op_type],
+                    |row| row.get(0),
+                )
+                .unwrap()
+        };
+
+        let cycles = system.query_coking_cycles(1).unwrap();
+        assert_eq!(cycles[0].loading_op_id, Some(op_id(&system, "1#", "LOAD")));
+        assert_eq!(cycles[0].push_op_id, Some(op_id(&system, "1#", "PUSH")));
+
+        // 可通过外键关联回来源操作
+        let joined: String = system
+            .conn
+            .query_row(
+                "SELECT o.time FROM coking_cycles c
+                JOIN operation_records o ON o.id = c.loading_op_id",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(joined, "2025-06-18 08:00:00");
+
+        // 重建后仍然填充
+        system.rebuild_all_cycles(1).unwrap();
+        let cycles = system.query_coking_cycles(1).unwrap();
+        assert_eq!(cycles[0].loading_op_id, Some(op_id(&system, "1#", "LOAD")));
+        assert_eq!(cycles[0].push_op_id, Some(op_id(&system, "1#", "PUSH")));
+
+        // 外键约束生效
+        assert!(system
+            .conn
+            .execute("UPDATE coking_cycles SET push_op_id = 9999", [])
+            .is_err());
+    }
+
+    #[test]
+    fn test_operation_id_migration() {
+        let temp_db = NamedTempFile::new().unwrap();
+        let db_path = temp_db.path().to_str().unwrap();
+
+        // 旧版本结构：结焦周期没有来源操作字段
+        {
+            let conn = Connection::open(db_path).unwrap();
+            conn.execute_batch(
+                "CREATE TABLE operation_records (
+                    id INTEGER PRIMARY KEY,
+                    coke_oven INTEGER NOT NULL,
+                    chamber TEXT NOT NULL,
+                    operation_type TEXT NOT NULL CHECK(operation_type IN ('LOAD', 'PUSH')),
+                    time TEXT NOT NULL,
+                    UNIQUE(coke_oven, chamber, time)
+                );
+                CREATE TABLE coking_cycles (
+                    id INTEGER PRIMARY KEY,
+                    coke_oven INTEGER NOT NULL,
+                    chamber TEXT NOT NULL,
+                    loading_time TEXT NOT NULL,
+                    push_time TEXT NOT NULL,
+                    duration_hhmm TEXT NOT NULL,
+                    duration_minutes INTEGER,
+                    avg_temp_machine REAL,
+                    avg_temp_coke REAL,
+                    UNIQUE(coke_oven, chamber, push_time)
+                );
+                INSERT INTO operation_records (id, coke_oven, chamber, operation_type, time)
+                VALUES (7, 1, '1#', 'LOAD', '2025-06-18 08:00'),
+                       (9, 1, '1#', 'PUSH', '2025-06-18 10:00');
+                INSERT INTO coking_cycles (coke_oven, chamber, loading_time, push_time, duration_hhmm, duration_minutes)
+                VALUES (1, '1#', '2025-06-18 08:00', '2025-06-18 10:00', '02:00', 120);",
+            )
+            .unwrap();
+        }
+
+        let system = CokeOvenSystem::new(db_path).unwrap();
+        let cycles = system.query_coking_cycles(1).unwrap();
+        assert_eq!(cycles[0].loading_op_id, Some(7));
+        assert_eq!(cycles[0].push_op_id, Some(9));
+    }
+
+    #[test]
+    fn test_rank_chambers_by_avg_temp() {
+        let (_temp_db, system) = setup_test_db();
+        insert_test_cycle(
+            &system,
+            "1#",
+            "2025-06-18 08:00:00",
+            "2025-06-18 20:00:00",
+            Some(1300.0),
+        );
+        insert_test_cycle(
+            &system,
+            "1#",
+            "2025-06-19 08:00:00",
+            "2025-06-19 20:00:00",
+            Some(1340.0),
+        );
+        insert_test_cycle(
+            &system,
+            "2#",
+            "2025-06-18 08:00:00",
+            "2025-06-18 20:00:00",
+            Some(1350.0),
+        );
+        insert_test_cycle(
+            &system,
+            "3#",
+            "2025-06-18 08:00:00",
+            "2025-06-18 20:00:00",
+            Some(1310.0),
+        );
+        // 平均温度缺失的周期不参与计算
+        insert_test_cycle(
+            &system,
+            "3#",
+            "2025-06-19 08:00:00",
+            "2025-06-19 20:00:00",
+            None,
+        );
+        insert_test_cycle(
+            &system,
+            "4#",
+            "2025-06-18 08:00:00",
+            "2025-06-18 20:00:00",
+            None,
+        );
+
+        let ranking = system.rank_chambers_by_avg_temp(1, Side::Machine).unwrap();
+        assert_eq!(
+            ranking,
+            vec![
+                ("2#".to_string(), 1350.0),
+                ("1#".to_string(), 1320.0),
+                ("3#".to_string(), 1310.0),
+            ]
+        );
+        assert_eq!(
+            system.rank_chambers_by_avg_temp(1, Side::Coke).unwrap(),
+            ranking
+        );
+
+        assert!(system.rank_chambers_by_avg_temp(4, Side::Machine).is_err());
+    }
+
+    #[test]
+    fn test_daily_summary() {
+        let (_temp_db, system) = setup_test_db();
+        insert_test_cycle(
+            &system,
+            "1#",
+            "2025-06-17 08:00:00",
+            "2025-06-18 08:00:00",
+            None,
+        );
+        insert_test_cycle(
+            &system,
+            "2#",
+            "2025-06-17 10:00:00",
+            "2025-06-18 12:00:00",
+            None,
+        );
+        insert_test_cycle(
+            &system,
+            "3#",
+            "2025-06-18 09:00:00",
+            "2025-06-19 08:00:00",
+            None,
+        );
+        // 范围之外
+        insert_test_cycle(
+            &system,
+            "4#",
+            "2025-06-19 09:00:00",
+            "2025-06-20 08:00:00",
+            None,
+        );
+
+        let summary = system.daily_summary(1, "2025-06-18", "2025-06-19").unwrap();
+        assert_eq!(summary.len(), 2);
+        assert_eq!(
+            summary[0].date,
+            chrono::NaiveDate::from_ymd_opt(2025, 6, 18).unwrap()
+        );
+        assert_eq!(summary[0].push_count, 2);
+        assert!((summary[0].avg_duration_minutes - 1500.0).abs() < 1e-9);
+        assert_eq!(summary[1].push_count, 1);
+        assert!((summary[1].avg_duration_minutes - 1380.0).abs() < 1e-9);
+
+        assert!(system.daily_summary(4, "2025-06-18", "2025-06-19").is_err());
+        assert!(system.daily_summary(1, "bad", "2025-06-19").is_err());
+    }
+
+    #[test]
+    fn test_error_variants() {
+        let (_temp_db, mut system) = setup_test_db();
+
+        assert!(matches!(
+            system.record_temperature(4, "2025-06-18 08:00", 1300.0, 1310.0),
+            Err(CokeError::InvalidOven(4))
+        ));
+        assert!(matches!(
+            system.record_temperature(1, "bad-time", 1300.0, 1310.0),
+            Err(CokeError::InvalidTime(ref t)) if t == "bad-time"
+        ));
+        assert!(matches!(
+            system.record_operation(1, "10#", "LOAD", "2025-06-18 08:00"),
+            Err(CokeError::InvalidChamber { oven: 1, ref chamber }) if chamber == "10#"
+        ));
+        assert!(matches!(
+            system.record_operation(1, "1#", "OPEN", "2025-06-18 08:00"),
+            Err(CokeError::InvalidOperationType(_))
+        ));
+        assert!(matches!(
+            system.record_operation_strict(1, "1#", "PUSH", "2025-06-18 08:00"),
+            Err(CokeError::Conflict(_))
+        ));
+        assert!(matches!(
+            system.temperature_stats(1, "2025-06-18 00:00", "2025-06-19 00:00"),
+            Err(CokeError::NoData(_))
+        ));
+
+        system
+            .record_temperature(1, "2025-06-18 08:00", 1300.0, 1310.0)
+            .unwrap();
+        assert!(matches!(
+            system.record_temperature(1, "2025-06-18 08:00", 1300.0, 1310.0),
+            Err(CokeError::Conflict(_))
+        ));
+    }
+
+    #[test]
+    fn test_temperature_at() {
+        let (_temp_db, mut system) = setup_test_db();
+        assert_eq!(system.temperature_at(1, "2025-06-18 08:30").unwrap(), None);
+
+        system
+            .record_temperature(1, "2025-06-18 08:00", 1300.0, 1310.0)
+            .unwrap();
+        system
+            .record_temperature(1, "2025-06-18 09:00", 1320.0, 1330.0)
+            .unwrap();
+
+        assert_eq!(
+            system.temperature_at(1, "2025-06-18 08:30").unwrap(),
+            Some((1310.0, 1320.0))
+        );
+        assert_eq!(
+            system.temperature_at(1, "2025-06-18 07:00").unwrap(),
+            Some((1300.0, 1310.0))
+        );
+        assert_eq!(
+            system.temperature_at(1, "2025-06-18 09:00").unwrap(),
+            Some((1320.0, 1330.0))
+        );
+
+        assert!(matches!(
+            system.temperature_at(4, "2025-06-18 08:30"),
+            Err(CokeError::InvalidOven(4))
+        ));
+    }
+
+    #[test]
+    fn test_total_coking_minutes() {
+        let (_temp_db, system) = setup_test_db();
+        assert_eq!(
+            system
+                .total_coking_minutes(1, "2025-06-18 00:00", "2025-06-20 00:00")
+                .unwrap(),
+            0
+        );
+
+        insert_test_cycle(
+            &system,
+            "1#",
+            "2025-06-18 00:00:00",
+            "2025-06-18 20:00:00",
+            None,
+        );
+        insert_test_cycle(
+            &system,
+            "2#",
+            "2025-06-18 01:00:00",
+            "2025-06-18 19:30:00",
+            None,
+        );
+        insert_test_cycle(
+            &system,
+            "3#",
+            "2025-06-18 02:00:00",
+            "2025-06-19 06:15:00",
+            None,
+        );
+        // 范围之外
+        insert_test_cycle(
+            &system,
+            "4#",
+            "2025-06-19 02:00:00",
+            "2025-06-20 06:15:00",
+            None,
+        );
+
+        assert_eq!(
+            system
+                .total_coking_minutes(1, "2025-06-18 00:00", "2025-06-20 00:00")
+                .unwrap(),
+            1200 + 1110 + 1695
+        );
+        assert!(system
+            .total_coking_minutes(4, "2025-06-18 00:00", "2025-06-20 00:00")
+            .is_err());
+    }
+
+    #[test]
+    fn test_find_overdue_chambers() {
+        let temp_db = NamedTempFile::new().unwrap();
+        let mut ovens = HashMap::new();
+        ovens.insert(
+            1,
+            CokeOven::new(&["1#", "2#", "3#"]).with_expected_cycle_minutes(20 * 60),
+        );
+        ovens.insert(2, CokeOven::new(&["4#"]));
+        let mut system =
+            CokeOvenSystem::new_with_layout(temp_db.path().to_str().unwrap(), ovens).unwrap();
+
+        assert_eq!(system.expected_cycle_minutes(1).unwrap(), Some(1200));
+        assert_eq!(system.expected_cycle_minutes(2).unwrap(), None);
+
+        system
+            .record_operation(1, "1#", "LOAD", "2025-06-18 00:00")
+            .unwrap();
+        system
+            .record_operation(1, "2#", "LOAD", "2025-06-18 08:00")
+            .unwrap();
+        system
+            .record_operation(1, "3#", "LOAD", "2025-06-17 20:00")
+            .unwrap();
+        system
+            .record_operation(1, "3#", "PUSH", "2025-06-18 16:00")
+            .unwrap();
+
+        // 默认使用焦炉额定结焦时间（20 小时）
+        assert_eq!(
+            system
+                .find_overdue_chambers(1, "2025-06-18 21:00", None)
+                .unwrap(),
+            vec![("1#".to_string(), 21 * 60)]
+        );
+
+        // 显式阈值优先
+        assert_eq!(
+            system
+                .find_overdue_chambers(1, "2025-06-18 21:00", Some(12 * 60))
+                .unwrap(),
+            vec![("1#".to_string(), 21 * 60), ("2#".to_string(), 13 * 60)]
+        );
+
+        // 修改额定值后生效
+        system.set_expected_cycle_minutes(1, 22 * 60).unwrap();
+        assert!(system
+            .find_overdue_chambers(1, "2025-06-18 21:00", None)
+            .unwrap()
+            .is_empty());
+
+        assert!(matches!(
+            system.find_overdue_chambers(2, "2025-06-18 21:00", None),
+            Err(CokeError::NoData(_))
+        ));
+        assert!(system.set_expected_cycle_minutes(1, 0).is_err());
+    }
+
+    #[test]
+    fn test_stream_temperatures() {
+        let (_temp_db, mut system) = setup_test_db();
+        for hour in 0..5 {
+            system
+                .record_temperature(
+                    1,
+                    &format!("2025-06-18 {:02}:00", hour),
+                    1300.0 + hour as f64,
+                    1310.0,
+                )
+                .unwrap();
+        }
+
+        let mut count = 0;
+        let mut last = None;
+        system
+            .stream_temperatures(1, "2025-06-18 01:00", "2025-06-18 03:00", |record| {
+                count += 1;
+                last = Some(record.machine_side);
+                Ok(())
+            })
+            .unwrap();
+        assert_eq!(count, 3);
+        assert_eq!(last, Some(1303.0));
+
+        // 回调返回错误时停止
+        let mut seen = 0;
+        let result = system.stream_temperatures(1, "2025-06-18 00:00", "2025-06-19 00:00", |_| {
+            seen += 1;
+            Err(CokeError::InvalidArgument("停止".to_string()))
+        });
+        assert!(matches!(result, Err(CokeError::InvalidArgument(_))));
+        assert_eq!(seen, 1);
+
+        let mut out = Vec::new();
+        let rows = system
+            .export_temperatures_csv(1, "2025-06-18 00:00", "2025-06-18 01:00", &mut out)
+            .unwrap();
+        assert_eq!(rows, 2);
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "time,machine_side,coke_side\n2025-06-18 00:00:00,1300,1310\n2025-06-18 01:00:00,1301,1310\n"
+        );
+    }
+
+    #[test]
+    fn test_integrity_report() {
+        let (_temp_db, mut system) = setup_test_db();
+        system
+            .record_temperature(1, "2025-06-18 08:00", 1300.0, 1310.0)
+            .unwrap();
+        system
+            .record_operation(1, "1#", "LOAD", "2025-06-18 08:00")
+            .unwrap();
+        system
+            .record_operation(1, "1#", "PUSH", "2025-06-18 20:00")
+            .unwrap();
+        assert!(system.integrity_report().unwrap().is_clean());
+
+        // 每类问题各写入一行
+        system
+            .conn
+            .execute_batch(
+                "INSERT INTO temperature_records (coke_oven, time, machine_side, coke_side)
+                VALUES (1, 'not-a-time', 1300.0, 1310.0);
+                INSERT INTO operation_records (coke_oven, chamber, operation_type, time)
+                VALUES (1, '10#', 'LOAD', '2025-06-18 09:00:00');
+                INSERT INTO coking_cycles (coke_oven, chamber, loading_time, push_time, duration_hhmm, duration_minutes)
+                VALUES (1, '2#', '2025-06-18 20:00:00', '2025-06-18 08:00:00', '-12:00', -720);",
+            )
+            .unwrap();
+
+        let report = system.integrity_report().unwrap();
+        assert!(!report.is_clean());
+        assert_eq!(report.unparseable_times.count, 1);
+        assert!(report.unparseable_times.samples[0].contains("not-a-time"));
+        assert_eq!(report.unknown_chambers.count, 1);
+        assert!(report.unknown_chambers.samples[0].contains("10#"));
+        assert_eq!(report.push_before_load.count, 1);
+        assert_eq!(report.negative_durations.count, 1);
+        assert!(report.negative_durations.samples[0].contains("-720"));
+    }
+
+    #[test]
+    fn test_temperature_stddev() {
+        let (_temp_db, mut system) = setup_test_db();
+        system
+            .record_temperature(1, "2025-06-18 08:00", 1300.0, 1310.0)
+            .unwrap();
+        assert!(matches!(
+            system.temperature_stddev(1, "2025-06-18 00:00", "2025-06-19 00:00"),
+            Err(CokeError::NoData(_))
+        ));
+
+        // 机侧为 1298 + (2,4,4,4,5,5,7,9)，总体标准差为 2；焦侧恒定
+        for (i, v) in [4.0, 4.0, 4.0, 5.0, 5.0, 7.0, 9.0].iter().enumerate() {
+            system
+                .record_temperature(
+                    1,
+                    &format!("2025-06-18 {:02}:00", 9 + i),
+                    1298.0 + v,
+                    1310.0,
+                )
+                .unwrap();
+        }
+        let (machine, coke) = system
+            .temperature_stddev(1, "2025-06-18 00:00", "2025-06-19 00:00")
+            .unwrap();
+        assert!((machine - 2.0).abs() < 1e-9);
+        assert_eq!(coke, 0.0);
+    }
+
+    #[test]
+    fn test_drop_checkpoints() {
+        let temp_db = NamedTempFile::new().unwrap();
+        let db_path = temp_db.path().to_str().unwrap();
+
+        {
+            let mut system = CokeOvenSystem::new(db_path).unwrap();
+            for hour in 0..10 {
+                system
+                    .record_temperature(1, &format!("2025-06-18 {:02}:00", hour), 1300.0, 1310.0)
+                    .unwrap();
+            }
+            // 只读句柄同时释放不应 panic
+            let _readonly = CokeOvenSystem::open_readonly(db_path).unwrap();
+        }
+
+        let system = CokeOvenSystem::new(db_path).unwrap();
+        let integrity: String = system
+            .conn
+            .query_row("PRAGMA integrity_check", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(integrity, "ok");
+        let stats = system
+            .temperature_stats(1, "2025-06-18 00:00", "2025-06-19 00:00")
+            .unwrap();
+        assert_eq!(stats.count, 10);
+    }
+
+    #[test]
+    fn test_record_temperature_probe() {
+        let (_temp_db, mut system) = setup_test_db();
+
+        system
+            .record_temperature_probe(1, "2025-06-18 08:00", 1300.0, 1310.0, Some("M1"))
+            .unwrap();
+        system
+            .record_temperature_probe(1, "2025-06-18 08:00", 1305.0, 1315.0, Some("M2"))
+            .unwrap();
+        system
+            .record_temperature(1, "2025-06-18 08:00", 1302.0, 1312.0)
+            .unwrap();
+
+        // 同一探头同一时刻重复写入仍违反唯一约束，未指定探头的记录保持原有行为
+        assert!(matches!(
+            system.record_temperature_probe(1, "2025-06-18 08:00", 1300.0, 1310.0, Some("M1")),
+            Err(CokeError::Conflict(_))
+        ));
+        assert!(matches!(
+            system.record_temperature_probe(1, "2025-06-18 08:00", 1300.0, 1310.0, None),
+            Err(CokeError::Conflict(_))
+        ));
+
+        let m2 = system
+            .probe_temperatures(1, Some("M2"), "2025-06-18 00:00", "2025-06-19 00:00")
+            .unwrap();
+        assert_eq!(m2.len(), 1);
+        assert_eq!(m2[0].machine_side, 1305.0);
+
+        let unassigned = system
+            .probe_temperatures(1, None, "2025-06-18 00:00", "2025-06-19 00:00")
+            .unwrap();
+        assert_eq!(unassigned.len(), 1);
+        assert_eq!(unassigned[0].machine_side, 1302.0);
+    }
+
+    #[test]
+    fn test_probe_migration() {
+        let temp_db = NamedTempFile::new().unwrap();
+        let db_path = temp_db.path().to_str().unwrap();
+
+        // 旧版本结构：没有 probe 字段，唯一约束为 (coke_oven, time)
+        {
+            let conn = Connection::open(db_path).unwrap();
+            conn.execute_batch(
+                "CREATE TABLE temperature_records (
+                    id INTEGER PRIMARY KEY,
+                    coke_oven INTEGER NOT NULL,
+                    time TEXT NOT NULL,
+                    machine_side REAL NOT NULL,
+                    coke_side REAL NOT NULL,
+                    UNIQUE(coke_oven, time)
+                );
+                INSERT INTO temperature_records (coke_oven, time, machine_side, coke_side)
+                VALUES (1, '2025-06-18 08:00:00', 1300.0, 1310.0);",
+            )
+            .unwrap();
+        }
+
+        let mut system = CokeOvenSystem::new(db_path).unwrap();
+        system
+            .record_temperature_probe(1, "2025-06-18 08:00", 1305.0, 1315.0, Some("M1"))
+            .unwrap();
+        assert!(system
+            .record_temperature(1, "2025-06-18 08:00", 1300.0, 1310.0)
+            .is_err());
+
+        // 焦炉级统计只使用未指定探头的记录
+        let stats = system
+            .temperature_stats(1, "2025-06-18 00:00", "2025-06-19 00:00")
+            .unwrap();
+        assert_eq!(stats.count, 1);
+        assert_eq!(stats.avg_machine, 1300.0);
+        assert_eq!(
+            system
+                .probe_temperatures(1, Some("M1"), "2025-06-18 00:00", "2025-06-19 00:00")
+                .unwrap()
+                .len(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_oven_readers_ignore_probe_records() {
+        let temp_db = NamedTempFile::new().unwrap();
+        let mut system = CokeOvenSystem::new(temp_db.path().to_str().unwrap()).unwrap();
+
+        system
+            .record_temperature(1, "2025-06-18 08:00", 1000.0, 1100.0)
+            .unwrap();
+        system
+            .record_temperature(1, "2025-06-18 12:00", 1000.0, 1100.0)
+            .unwrap();
+        // 探头记录与焦炉记录同时刻及窗口内，不应混入焦炉序列
+        for time in ["2025-06-18 08:00", "2025-06-18 10:00", "2025-06-18 12:00"] {
+            system
+                .record_temperature_probe(1, time, 1500.0, 1600.0, Some("M1"))
+                .unwrap();
+        }
+
+        let (start, end) = ("2025-06-18 00:00", "2025-06-19 00:00");
+        assert_eq!(system.temperature_points(1, start, end).unwrap().len(), 2);
+        assert_eq!(
+            system
+                .temperatures_in_range(1, start, end, IntervalBounds::Closed)
+                .unwrap()
+                .len(),
+            2
+        );
+        let mut streamed = 0;
+        system
+            .stream_temperatures(1, start, end, |_| {
+                streamed += 1;
+                Ok(())
+            })
+            .unwrap();
+        assert_eq!(streamed, 2);
+        assert_eq!(system.temperature_stats(1, start, end).unwrap().count, 2);
+        assert_eq!(
+            system.all_ovens_temperature_stats(start, end).unwrap()[&1].count,
+            2
+        );
+        assert_eq!(
+            system
+                .average_temperature(1, "2025-06-18 09:00", "2025-06-18 11:00")
+                .unwrap(),
+            Some((1000.0, 1100.0))
+        );
+        assert!(system.find_precision_collisions(1).unwrap().is_empty());
+
+        system
+            .record_operation(1, "1#", "LOAD", "2025-06-18 08:00")
+            .unwrap();
+        system
+            .record_operation(1, "1#", "PUSH", "2025-06-18 12:00")
+            .unwrap();
+        let cycle = &system.query_coking_cycles(1).unwrap()[0];
+        assert_eq!(cycle.avg_temp_machine, Some(1000.0));
+        assert_eq!(cycle.avg_temp_coke, Some(1100.0));
+    }
+
+    #[test]
+    fn test_query_coking_cycles_paged() {
+        let (_temp_db, system) = setup_test_db();
+        for day in 1..=5 {
+            insert_test_cycle(
+                &system,
+                &format!("{}#", day),
+                &format!("2025-06-{:02} 08:00:00", day),
+                &format!("2025-06-{:02} 20:00:00", day),
+                None,
+            );
+        }
+        assert_eq!(system.coking_cycle_count(1).unwrap(), 5);
+
+        let chambers = |cycles: Vec<CokingCycle>| -> Vec<String> {
+            cycles.into_iter().map(|c| c.chamber).collect()
+        };
+        assert_eq!(
+            chambers(system.query_coking_cycles_paged(1, 2, 0).unwrap()),
+            vec!["5#", "4#"]
+        );
+        assert_eq!(
+            chambers(system.query_coking_cycles_paged(1, 2, 2).unwrap()),
+            vec!["3#", "2#"]
+        );
+        assert_eq!(
+            chambers(system.query_coking_cycles_paged(1, 2, 4).unwrap()),
+            vec!["1#"]
+        );
+        assert!(system
+            .query_coking_cycles_paged(1, 2, 6)
+            .unwrap()
+            .is_empty());
+
+        assert!(matches!(
+            system.query_coking_cycles_paged(1, -1, 0),
+            Err(CokeError::InvalidArgument(_))
+        ));
+        assert!(system.query_coking_cycles_paged(1, 2, -1).is_err());
+    }
+
+    #[test]
+    fn test_prune_before() {
+        let (_temp_db, mut system) = setup_test_db();
+        system
+            .record_temperature(1, "2025-06-18 08:00", 1300.0, 1310.0)
+            .unwrap();
+        system
+            .record_temperature(1, "2025-06-18 20:00", 1320.0, 1330.0)
+            .unwrap();
+        system
+            .record_temperature(1, "2025-06-19 08:00", 1340.0, 1350.0)
+            .unwrap();
+        system
+            .record_operation(1, "1#", "LOAD", "2025-06-18 08:00")
+            .unwrap();
+        system
+            .record_operation(1, "1#", "PUSH", "2025-06-18 20:00")
+            .unwrap();
+        system
+            .record_operation(1, "1#", "LOAD", "2025-06-19 08:00")
+            .unwrap();
+
+        let report = system.prune_before("2025-06-19 00:00").unwrap();
+        assert_eq!(
+            report,
+            PruneReport {
+                temperature_records: 2,
+                operation_records: 2,
+            }
+        );
+
+        let stats = system
+            .temperature_stats(1, "2025-06-01 00:00", "2025-07-01 00:00")
+            .unwrap();
+        assert_eq!(stats.count, 1);
+        assert_eq!(
+            system
+                .operation_history(1, "2025-06-01 00:00", "2025-07-01 00:00")
+                .unwrap()
+                .len(),
+            1
+        );
+
+        // 结焦周期保留，来源操作 id 置空
+        let cycles = system.query_coking_cycles(1).unwrap();
+        assert_eq!(cycles.len(), 1);
+        assert_eq!(cycles[0].duration_hhmm, "12:00");
+        assert_eq!(cycles[0].loading_op_id, None);
+        assert_eq!(cycles[0].push_op_id, None);
+    }
+
+    #[test]
+    fn test_from_connection() {
+        let conn = Connection::open_in_memory().unwrap();
+        let mut system = CokeOvenSystem::from_connection(conn).unwrap();
+
+        system
+            .record_operation(1, "1#", "LOAD", "2025-06-18 08:00")
+            .unwrap();
+        system
+            .record_operation(1, "1#", "PUSH", "2025-06-18 20:00")
+            .unwrap();
+        assert_eq!(system.query_coking_cycles(1).unwrap().len(), 1);
+        assert_eq!(system.chambers(2).unwrap().len(), 68);
+    }
+
+    #[test]
+    fn test_active_ovens() {
+        let (_temp_db, mut system) = setup_test_db();
+        assert!(system.active_ovens().unwrap().is_empty());
+
+        system
+            .record_temperature(1, "2025-06-18 08:00", 1350.0, 1360.0)
+            .unwrap();
+        system
+            .record_operation(1, "1#", "LOAD", "2025-06-18 08:00")
+            .unwrap();
+        assert_eq!(system.active_ovens().unwrap(), vec![1]);
+
+        system
+            .record_operation(3, "5#", "LOAD", "2025-06-18 08:00")
+            .unwrap();
+        assert_eq!(system.active_ovens().unwrap(), vec![1, 3]);
+    }
+
+    #[test]
+    fn test_journal_mode_option() {
+        let temp_db = NamedTempFile::new().unwrap();
+        let system =
+            CokeOvenSystem::new_with_options(temp_db.path().to_str().unwrap(), JournalMode::Delete)
+                .unwrap();
+        let mode: String = system
+            .conn
+            .query_row("PRAGMA journal_mode", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(mode, "delete");
+        drop(system);
+
+        // 默认仍为 WAL
+        let system = CokeOvenSystem::new(temp_db.path().to_str().unwrap()).unwrap();
+        let mode: String = system
+            .conn
+            .query_row("PRAGMA journal_mode", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(mode, "wal");
+    }
+
+    #[test]
+    fn test_calculate_integral_unsorted() {
+        let point = |time: &str, machine: f64, coke: f64| TimeTempPoint {
+            time: crate::models::parse_time(time).unwrap(),
+            machine,
+            coke,
+        };
+        let sorted = vec![
+            point("2025-06-18 08:00", 1300.0, 1310.0),
+            point("2025-06-18 09:00", 1340.0, 1350.0),
+            point("2025-06-18 09:00", 1340.0, 1350.0),
+            point("2025-06-18 11:00", 1380.0, 1370.0),
+            point("2025-06-18 12:00", 1360.0, 1365.0),
+        ];
+        let shuffled = vec![
+            point("2025-06-18 11:00", 1380.0, 1370.0),
+            point("2025-06-18 09:00", 1340.0, 1350.0),
+            point("2025-06-18 12:00", 1360.0, 1365.0),
+            point("2025-06-18 08:00", 1300.0, 1310.0),
+            point("2025-06-18 09:00", 1340.0, 1350.0),
+        ];
+
+        let (m1, c1, d1) = calculate_integral(&sorted, IntegrationMethod::Trapezoidal);
+        let (m2, c2, d2) = calculate_integral(&shuffled, IntegrationMethod::Trapezoidal);
+        assert_eq!(d1, 240.0);
+        assert_eq!(d1, d2);
+        assert!((m1 / d1 - m2 / d2).abs() < 1e-9);
+        assert!((c1 / d1 - c2 / d2).abs() < 1e-9);
+        assert_eq!(
+            calculate_integral(&[], IntegrationMethod::Trapezoidal),
+            (0.0, 0.0, 0.0)
+        );
+        assert_eq!(
+            calculate_integral(&[], IntegrationMethod::Simpson),
+            (0.0, 0.0, 0.0)
+        );
+    }
+
+    #[test]
+    fn test_time_offset_storage() {
+        let (_temp_db, mut system) = setup_test_db();
+        let offset = FixedOffset::east_opt(8 * 3600).unwrap();
+        system.set_time_offset(Some(offset));
+
+        system
+            .record_temperature(1, "2025-06-18 08:00", 1300.0, 1310.0)
+            .unwrap();
+        system
+            .record_operation(1, "1#", "LOAD", "2025-06-18 08:00")
+            .unwrap();
+        system
+            .record_operation(1, "1#", "PUSH", "2025-06-18 20:00")
+            .unwrap();
+
+        // 以 UTC 存储
+        let stored: String = system
+            .conn
+            .query_row("SELECT time FROM temperature_records", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(stored, "2025-06-18 00:00:00");
+        let cycles = system.query_coking_cycles(1).unwrap();
+        assert_eq!(
+            crate::models::format_time(cycles[0].loading_time),
+            "2025-06-18 00:00:00"
+        );
+        assert_eq!(
+            crate::models::format_time(cycles[0].push_time),
+            "2025-06-18 12:00:00"
+        );
+
+        // 查询范围与显示转换
+        let start = system.to_stored_time("2025-06-18 07:00").unwrap();
+        let end = system.to_stored_time("2025-06-18 09:00").unwrap();
+        assert_eq!(start, "2025-06-17 23:00:00");
+        let records = system.probe_temperatures(1, None, &start, &end).unwrap();
+        assert_eq!(records.len(), 1);
+        assert_eq!(
+            crate::models::format_time(system.to_local_time(records[0].time)),
+            "2025-06-18 08:00:00"
+        );
+    }
+
+    #[test]
+    fn test_temperature_limits() {
+        let (_temp_db, mut system) = setup_test_db();
+
+        // 默认不限制
+        system
+            .record_temperature(1, "2025-06-18 08:00", 13500.0, 1360.0)
+            .unwrap();
+
+        assert!(system.set_temperature_limits(2000.0, 0.0).is_err());
+        system.set_temperature_limits(0.0, 2000.0).unwrap();
+
+        let err = system
+            .record_temperature(1, "2025-06-18 09:00", 13500.0, 1360.0)
+            .unwrap_err();
+        assert!(matches!(err, CokeError::InvalidArgument(_)));
+        assert!(err.to_string().contains("13500"));
+        assert!(system
+            .record_temperature_probe(1, "2025-06-18 09:00", 1350.0, -5.0, Some("A"))
+            .is_err());
+
+        system
+            .record_temperature(1, "2025-06-18 09:00", 1350.0, 1360.0)
+            .unwrap();
+        let count: i64 = system
+            .conn
+            .query_row("SELECT COUNT(*) FROM temperature_records", [], |row| {
+                row.get(0)
+            })
+            .unwrap();
+        assert_eq!(count, 2);
+
+        system.clear_temperature_limits();
+        system
+            .record_temperature(1, "2025-06-18 10:00", 13500.0, 1360.0)
+            .unwrap();
+    }
+
+    #[test]
+    fn test_peak_temperature() {
+        let (_temp_db, mut system) = setup_test_db();
+        assert!(matches!(
+            system.peak_temperature(1, "2025-06-18 00:00", "2025-06-19 00:00", Side::Machine),
+            Err(CokeError::NoData(_))
+        ));
+
+        system
+            .record_temperature(1, "2025-06-18 08:00", 1300.0, 1400.0)
+            .unwrap();
+        system
+            .record_temperature(1, "2025-06-18 12:00", 1380.0, 1350.0)
+            .unwrap();
+        system
+            .record_temperature(1, "2025-06-18 16:00", 1320.0, 1330.0)
+            .unwrap();
+
+        let (time, machine, coke) = system
+            .peak_temperature(1, "2025-06-18 00:00", "2025-06-19 00:00", Side::Machine)
+            .unwrap();
+        assert_eq!(crate::models::format_time(time), "2025-06-18 12:00:00");
+        assert_eq!((machine, coke), (1380.0, 1350.0));
+
+        let (time, _, coke) = system
+            .peak_temperature(1, "2025-06-18 00:00", "2025-06-19 00:00", Side::Coke)
+            .unwrap();
+        assert_eq!(crate::models::format_time(time), "2025-06-18 08:00:00");
+        assert_eq!(coke, 1400.0);
+    }
+
+    #[test]
+    fn test_audit_trail() {
+        let (_temp_db, mut system) = setup_test_db();
+        let since =
+            crate::models::format_time(chrono::Local::now().naive_local() - Duration::minutes(1));
+
+        system.set_current_user(Some("张工")).unwrap();
+        system
+            .record_temperature(1, "2025-06-18 08:00", 1300.0, 1310.0)
+            .unwrap();
+        let temp_id: i64 = system
+            .conn
+            .query_row("SELECT id FROM temperature_records", [], |row| row.get(0))
+            .unwrap();
+
+        system.set_current_user(None).unwrap();
+        system
+            .record_operation(1, "1#", "LOAD", "2025-06-18 08:00")
+            .unwrap();
+
+        let trail = system.audit_trail(&since).unwrap();
+        assert_eq!(trail.len(), 2);
+        assert_eq!(trail[0].action, "INSERT");
+        assert_eq!(trail[0].table_name, "temperature_records");
+        assert_eq!(trail[0].record_key, temp_id.to_string());
+        assert_eq!(trail[0].user.as_deref(), Some("张工"));
+        assert_eq!(trail[1].table_name, "operation_records");
+        assert_eq!(trail[1].user, None);
+
+        // 删除同样被记录
+        system.prune_before("2025-06-19 00:00").unwrap();
+        let trail = system.audit_trail(&since).unwrap();
+        assert_eq!(trail.iter().filter(|e| e.action == "DELETE").count(), 2);
+
+        // 审计日志只允许追加
+        assert!(system.conn.execute("DELETE FROM audit_log", []).is_err());
+        assert!(system
+            .conn
+            .execute("UPDATE audit_log SET user = 'x'", [])
+            .is_err());
+        assert!(system.audit_trail("2999-01-01").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_audit_trail_other_connections() {
+        let temp_db = NamedTempFile::new().unwrap();
+        let path = temp_db.path().to_str().unwrap();
+        let since =
+            crate::models::format_time(chrono::Local::now().naive_local() - Duration::minutes(1));
+
+        let mut system = CokeOvenSystem::new(path).unwrap();
+        system.set_current_user(Some("李工")).unwrap();
+        drop(system);
+
+        // 外部连接的修改同样记录，操作人在重新连接后仍然有效
+        let other = Connection::open(path).unwrap();
+        other
+            .execute(
+                "INSERT INTO temperature_records (coke_oven, time, machine_side, coke_side)
+                    VALUES (1, '2025-06-18 08:00:00', 1300.0, 1310.0)",
+                [],
+            )
+            .unwrap();
+        drop(other);
+
+        let mut system = CokeOvenSystem::new(path).unwrap();
+        let trail = system.audit_trail(&since).unwrap();
+        assert_eq!(trail.len(), 1);
+        assert_eq!(trail[0].table_name, "temperature_records");
+        assert_eq!(trail[0].user.as_deref(), Some("李工"));
+        let lag = chrono::Local::now().naive_local() - trail[0].time;
+        assert!(lag >= Duration::zero() && lag < Duration::minutes(1));
+
+        // 设置时区偏移后按 UTC 存储，since 按输入时区解析
+        let offset = FixedOffset::east_opt(8 * 3600).unwrap();
+        system.set_time_offset(Some(offset));
+        let now_utc = chrono::Utc::now().naive_utc();
+        let since = crate::models::format_time(
+            crate::models::utc_to_offset(now_utc, offset) - Duration::minutes(1),
+        );
+        let trail = system.audit_trail(&since).unwrap();
+        assert_eq!(trail.len(), 1);
+        assert!((now_utc - trail[0].time).num_seconds().abs() < 60);
+        assert!(system.audit_trail("not a time").is_err());
+    }
+
+    #[test]
+    fn test_duration_histogram() {
+        let (_temp_db, system) = setup_test_db();
+        assert!(system.duration_histogram(1, 60).unwrap().is_empty());
+        assert!(system.duration_histogram(1, 0).is_err());
+
+        insert_test_cycle(&system, "1#", "2025-06-18 00:00", "2025-06-18 18:10", None);
+        insert_test_cycle(&system, "2#", "2025-06-18 00:00", "2025-06-18 18:50", None);
+        insert_test_cycle(&system, "3#", "2025-06-18 00:00", "2025-06-18 19:30", None);
+
+        assert_eq!(
+            system.duration_histogram(1, 60).unwrap(),
+            vec![(1080, 2), (1140, 1)]
+        );
+        assert_eq!(system.duration_histogram(1, 1440).unwrap(), vec![(0, 3)]);
+    }
+
+    #[test]
+    fn test_duplicate_records_conflict() {
+        let (_temp_db, mut system) = setup_test_db();
+
+        system
+            .record_temperature(1, "2025-06-18 08:00", 1300.0, 1310.0)
+            .unwrap();
+        let err = system
+            .record_temperature(1, "2025-06-18 08:00:00", 1305.0, 1315.0)
+            .unwrap_err();
+        assert!(matches!(err, CokeError::Conflict(_)));
+        assert_eq!(err.to_string(), "焦炉1在2025-06-18 08:00:00已有温度记录");
+
+        system
+            .record_operation(1, "1#", "LOAD", "2025-06-18 08:00")
+            .unwrap();
+        let err = system
+            .record_operation(1, "1#", "PUSH", "2025-06-18 08:00")
+            .unwrap_err();
+        assert!(matches!(err, CokeError::Conflict(_)));
+        assert_eq!(
+            err.to_string(),
+            "焦炉1炭化室1#在2025-06-18 08:00:00已有操作记录"
+        );
+        assert!(matches!(
+            system.record_operation_deferred(1, "1#", "LOAD", "2025-06-18 08:00"),
+            Err(CokeError::Conflict(_))
+        ));
+    }
+
+    #[test]
+    fn test_upsert_temperature() {
+        let (_temp_db, mut system) = setup_test_db();
+
+        assert!(system
+            .upsert_temperature(1, "2025-06-18 08:00", 1300.0, 1310.0)
+            .unwrap());
+        assert!(!system
+            .upsert_temperature(1, "2025-06-18 08:00:00", 1350.0, 1360.0)
+            .unwrap());
+
+        let rows: Vec<(f64, f64)> = system
+            .conn
+            .prepare("SELECT machine_side, coke_side FROM temperature_records")
+            .unwrap()
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(rows, vec![(1350.0, 1360.0)]);
+
+        // 其他探头的同一时刻记录不受影响
+        system
+            .record_temperature_probe(1, "2025-06-18 09:00", 1300.0, 1310.0, Some("M1"))
+            .unwrap();
+        assert!(system
+            .upsert_temperature(1, "2025-06-18 09:00", 1320.0, 1330.0)
+            .unwrap());
+
+        assert!(matches!(
+            system.upsert_temperature(9, "2025-06-18 08:00", 1300.0, 1310.0),
+            Err(CokeError::InvalidOven(9))
+        ));
+        assert!(matches!(
+            system.upsert_temperature(1, "bad", 1300.0, 1310.0),
+            Err(CokeError::InvalidTime(_))
+        ));
+    }
+
+    #[test]
+    fn test_heating_rate() {
+        let (_temp_db, mut system) = setup_test_db();
+        assert!(matches!(
+            system.heating_rate(1, "2025-06-18 08:00", "2025-06-18 12:00"),
+            Err(CokeError::NoData(_))
+        ));
+
+        // 机侧每小时升 20℃，焦侧每小时升 10℃
+        for hour in 0..=10 {
+            system
+                .record_temperature(
+                    1,
+                    &format!("2025-06-18 {:02}:00", hour + 6),
+                    1000.0 + 20.0 * hour as f64,
+                    1100.0 + 10.0 * hour as f64,
+                )
+                .unwrap();
+        }
+
+        let (machine, coke) = system
+            .heating_rate(1, "2025-06-18 08:30", "2025-06-18 12:00")
+            .unwrap();
+        assert!((machine - 20.0).abs() < 1e-9);
+        assert!((coke - 10.0).abs() < 1e-9);
+
+        assert!(matches!(
+            system.heating_rate(1, "2025-06-18 08:00", "2025-06-18 08:00"),
+            Err(CokeError::InvalidArgument(_))
+        ));
+    }
+
+    #[test]
+    fn test_all_ovens_temperature_stats() {
+        let (_temp_db, mut system) = setup_test_db();
+
+        system
+            .record_temperature(1, "2025-06-18 08:00", 1300.0, 1310.0)
+            .unwrap();
+        system
+            .record_temperature(1, "2025-06-18 09:00", 1320.0, 1330.0)
+            .unwrap();
+        system
+            .record_temperature(2, "2025-06-18 08:30", 1200.0, 1210.0)
+            .unwrap();
+        // 窗口外的记录不参与统计
+        system
+            .record_temperature(3, "2025-06-20 08:00", 1250.0, 1260.0)
+            .unwrap();
+
+        let stats = system
+            .all_ovens_temperature_stats("2025-06-18 00:00", "2025-06-19 00:00")
+            .unwrap();
+        assert_eq!(stats.len(), 2);
+        assert!(!stats.contains_key(&3));
+
+        let oven1 = &stats[&1];
+        assert_eq!(oven1.count, 2);
+        assert_eq!((oven1.min_machine, oven1.max_machine), (1300.0, 1320.0));
+        assert_eq!(oven1.avg_coke, 1320.0);
+        assert_eq!(
+            *oven1,
+            system
+                .temperature_stats(1, "2025-06-18 00:00", "2025-06-19 00:00")
+                .unwrap()
+        );
+
+        let oven2 = &stats[&2];
+        assert_eq!(oven2.count, 1);
+        assert_eq!(oven2.avg_machine, 1200.0);
+    }
+
+    #[test]
+    fn test_min_interior_samples() {
+        let (_temp_db, mut system) = setup_test_db();
+        system.set_min_interior_samples(2);
+
+        // 稀疏窗口：只有首尾两条记录
+        system
+            .record_temperature(1, "2025-06-18 00:00", 1300.0, 1310.0)
+            .unwrap();
+        system
+            .record_temperature(1, "2025-06-18 20:00", 1320.0, 1330.0)
+            .unwrap();
+        system
+            .record_operation(1, "1#", "LOAD", "2025-06-18 00:00")
+            .unwrap();
+        system
+            .record_operation(1, "1#", "PUSH", "2025-06-18 20:00")
+            .unwrap();
+
+        let sparse = &system.query_coking_cycles(1).unwrap()[0];
+        assert!(sparse.low_confidence);
+        assert_eq!(sparse.avg_temp_machine, None);
+        assert_eq!(sparse.avg_temp_coke, None);
+
+        // 密集窗口：内部有足够记录
+        for hour in [4, 8, 12, 16] {
+            system
+                .record_temperature(1, &format!("2025-06-19 {:02}:00", hour), 1350.0, 1360.0)
+                .unwrap();
+        }
+        system
+            .record_operation(1, "2#", "LOAD", "2025-06-19 00:00")
+            .unwrap();
+        system
+            .record_operation(1, "2#", "PUSH", "2025-06-19 20:00")
+            .unwrap();
+
+        let dense = system
+            .query_coking_cycles(1)
+            .unwrap()
+            .into_iter()
+            .find(|c| c.chamber == "2#")
+            .unwrap();
+        assert!(!dense.low_confidence);
+        assert!(dense.avg_temp_machine.is_some());
+
+        // 取消检查后重新计算，稀疏周期恢复平均温度
+        system.set_min_interior_samples(0);
+        system.recompute_cycle_averages(1, "1#").unwrap();
+        let sparse = &system.query_coking_cycles(1).unwrap()[0];
+        assert!(!sparse.low_confidence);
+        assert!(sparse.avg_temp_machine.is_some());
+    }
+
+    #[test]
+    fn test_find_overlapping_cycles() {
+        let (_temp_db, system) = setup_test_db();
+
+        insert_test_cycle(&system, "1#", "2025-06-18 00:00", "2025-06-18 20:00", None);
+        insert_test_cycle(&system, "1#", "2025-06-18 18:00", "2025-06-19 14:00", None);
+        // 首尾相接不算重叠
+        insert_test_cycle(&system, "1#", "2025-06-19 14:00", "2025-06-20 10:00", None);
+        // 其他炭化室的周期不参与比较
+        insert_test_cycle(&system, "2#", "2025-06-18 10:00", "2025-06-19 06:00", None);
+
+        let overlaps = system.find_overlapping_cycles(1, "1#").unwrap();
+        assert_eq!(overlaps.len(), 1);
+        let (first, second) = &overlaps[0];
+        assert_eq!(
+            crate::models::format_time(first.loading_time),
+            "2025-06-18 00:00:00"
+        );
+        assert_eq!(
+            crate::models::format_time(second.loading_time),
+            "2025-06-18 18:00:00"
+        );
+
+        assert!(system.find_overlapping_cycles(1, "2#").unwrap().is_empty());
+        assert!(system.find_overlapping_cycles(1, "999#").is_err());
+    }
+
+    #[test]
+    fn test_binary_round_trip() {
+        let (_source_db, mut source) = setup_test_db();
+        source
+            .record_temperature(1, "2025-06-18 08:00", 1300.0, 1310.5)
+            .unwrap();
+        source
+            .record_temperature_probe(1, "2025-06-18 08:00", 1301.0, 1311.0, Some("M1"))
+            .unwrap();
+        source
+            .record_temperature(2, "2025-06-18 09:00:30", 1200.25, 1210.0)
+            .unwrap();
+        source
+            .record_operation(1, "1#", "LOAD", "2025-06-18 08:00")
+            .unwrap();
+        source
+            .record_operation(1, "1#", "PUSH", "2025-06-19 04:00")
+            .unwrap();
+        source
+            .record_operation(2, "76#", "LOAD", "2025-06-18 09:00")
+            .unwrap();
+
+        let mut data = Vec::new();
+        source.export_binary(&mut data).unwrap();
+
+        let (_target_db, mut target) = setup_test_db();
+        assert_eq!(target.import_binary(&mut data.as_slice()).unwrap(), (3, 3));
+
+        let dump = |system: &CokeOvenSystem, sql: &str| -> Vec<String> {
+            let mut stmt = system.conn.prepare(sql).unwrap();
+            let column_count = stmt.column_count();
+            stmt.query_map([], |row| {
+                Ok((0..column_count)
+                    .map(|i| format!("{:?}", row.get_ref(i).unwrap()))
+                    .collect::<Vec<_>>()
+                    .join("|"))
+            })
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap()
+        };
+        for sql in [
+            "SELECT coke_oven, time, machine_side, coke_side, probe FROM temperature_records
+                ORDER BY id",
+            "SELECT coke_oven, chamber, operation_type, time FROM operation_records ORDER BY id",
+        ] {
+            assert_eq!(dump(&source, sql), dump(&target, sql));
+        }
+        assert_eq!(
+            target.query_coking_cycles(1).unwrap()[0].duration_hhmm,
+            source.query_coking_cycles(1).unwrap()[0].duration_hhmm
+        );
+
+        // 重复导入跳过已有记录
+        assert_eq!(target.import_binary(&mut data.as_slice()).unwrap(), (0, 0));
+        assert!(matches!(
+            target.import_binary(&mut &b"NOTCOKE\0"[..]),
+            Err(CokeError::InvalidArgument(_))
+        ));
+    }
+
+    #[test]
+    fn test_temperature_points() {
+        let (_temp_db, mut system) = setup_test_db();
+
+        system
+            .record_temperature(1, "2025-06-18 10:00", 1320.0, 1330.0)
+            .unwrap();
+        system
+            .record_temperature(1, "2025-06-18 08:00", 1300.0, 1310.0)
+            .unwrap();
+        system
+            .record_temperature(1, "2025-06-18 12:00", 1340.0, 1350.0)
+            .unwrap();
+
+        let points = system
+            .temperature_points(1, "2025-06-18 08:00", "2025-06-18 10:00")
+            .unwrap();
+        assert_eq!(
+            points,
+            vec![
+                TimeTempPoint {
+                    time: crate::models::parse_time("2025-06-18 08:00").unwrap(),
+                    machine: 1300.0,
+                    coke: 1310.0,
+                },
+                TimeTempPoint {
+                    time: crate::models::parse_time("2025-06-18 10:00").unwrap(),
+                    machine: 1320.0,
+                    coke: 1330.0,
+                },
+            ]
+        );
+
+        assert!(matches!(
+            system.temperature_points(9, "2025-06-18 08:00", "2025-06-18 10:00"),
+            Err(CokeError::InvalidOven(9))
+        ));
+    }
+
+    #[test]
+    fn test_record_push() {
+        let (_temp_db, mut system) = setup_test_db();
+
+        // 没有装煤记录
+        assert_eq!(
+            system.record_push(1, "1#", "2025-06-18 06:00").unwrap(),
+            None
+        );
+
+        system
+            .record_temperature(1, "2025-06-18 08:00", 1300.0, 1310.0)
+            .unwrap();
+        system
+            .record_temperature(1, "2025-06-19 12:30", 1340.0, 1350.0)
+            .unwrap();
+        system
+            .record_operation(1, "1#", "LOAD", "2025-06-18 08:00")
+            .unwrap();
+
+        let cycle = system
+            .record_push(1, "1#", "2025-06-19 12:30")
+            .unwrap()
+            .unwrap();
+        assert_eq!(cycle.chamber, "1#");
+        assert_eq!(cycle.duration_minutes, 1710);
+        assert_eq!(cycle.duration_hhmm, minutes_to_hhmm(1710));
+        assert_eq!(cycle.avg_temp_machine, Some(1320.0));
+        assert_eq!(cycle.avg_temp_coke, Some(1330.0));
+        assert!(cycle.loading_op_id.is_some() && cycle.push_op_id.is_some());
+        assert_eq!(system.query_coking_cycles(1).unwrap(), vec![cycle]);
+    }
+
+    #[test]
+    fn test_shared_system_threads() {
+        let temp_db = NamedTempFile::new().unwrap();
+        let shared = SharedSystem::open(temp_db.path().to_str().unwrap()).unwrap();
+
+        let handles: Vec<_> = (1..=3)
+            .map(|coke_oven| {
+                let shared = shared.clone();
+                std::thread::spawn(move || {
+                    for minute in 0..50 {
+                        shared
+                            .record_temperature(
+                                coke_oven,
+                                &format!("2025-06-18 08:{:02}", minute),
+                                1300.0,
+                                1310.0,
+                            )
+                            .unwrap();
+                    }
+                    let chamber = if coke_oven == 2 { "76#" } else { "5#" };
+                    shared
+                        .record_operation(coke_oven, chamber, "LOAD", "2025-06-18 08:00")
+                        .unwrap();
+                })
+            })
+            .collect();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+
+        shared
+            .with(|system| {
+                let temps: i64 = system.conn.query_row(
+                    "SELECT COUNT(*) FROM temperature_records",
+                    [],
+                    |row| row.get(0),
+                )?;
+                let ops: i64 =
+                    system
+                        .conn
+                        .query_row("SELECT COUNT(*) FROM operation_records", [], |row| {
+                            row.get(0)
+                        })?;
+                assert_eq!((temps, ops), (150, 3));
+                assert_eq!(system.active_ovens()?, vec![1, 2, 3]);
+                Ok(())
+            })
+            .unwrap();
+    }
+
+    #[test]
+    fn test_data_range() {
+        let (_temp_db, mut system) = setup_test_db();
+        assert_eq!(system.data_range(1).unwrap(), None);
+
+        for time in ["2025-06-18 12:00", "2025-06-18 08:00", "2025-06-19 06:30"] {
+            system.record_temperature(1, time, 1300.0, 1310.0).unwrap();
+        }
+
+        let (first, last) = system.data_range(1).unwrap().unwrap();
+        assert_eq!(crate::models::format_time(first), "2025-06-18 08:00:00");
+        assert_eq!(crate::models::format_time(last), "2025-06-19 06:30:00");
+        assert_eq!(system.data_range(2).unwrap(), None);
+        assert!(system.data_range(9).is_err());
+    }
+
+    #[test]
+    fn test_record_operation_now() {
+        let temp_db = NamedTempFile::new().unwrap();
+        let now = crate::models::parse_time("2025-06-18 08:16:30").unwrap();
+        let mut system = CokeOvenSystem::new(temp_db.path().to_str().unwrap())
+            .unwrap()
+            .with_clock(FixedClock(now));
+
+        assert!(!system.record_operation_now(1, "1#", "LOAD").unwrap());
+        let history = system
+            .operation_history(1, "2025-06-18 00:00", "2025-06-19 00:00")
+            .unwrap();
+        assert_eq!(history.len(), 1);
+        assert_eq!(history[0].time, now);
+        assert_eq!(history[0].operation_type, "LOAD");
+    }
+
+    #[test]
+    fn test_find_overdue_chambers_now() {
+        let temp_db = NamedTempFile::new().unwrap();
+        let mut ovens = HashMap::new();
+        ovens.insert(
+            1,
+            CokeOven::new(&["1#", "2#"]).with_expected_cycle_minutes(20 * 60),
+        );
+        let path = temp_db.path().to_str().unwrap();
+        let clock = |time: &str| FixedClock(crate::models::parse_time(time).unwrap());
+
+        let mut system = CokeOvenSystem::new_with_layout(path, ovens)
+            .unwrap()
+            .with_clock(clock("2025-06-18 00:00"));
+        system.record_operation_now(1, "1#", "LOAD").unwrap();
+        system
+            .record_operation(1, "2#", "LOAD", "2025-06-18 06:00")
+            .unwrap();
+        assert!(system
+            .find_overdue_chambers_now(1, None)
+            .unwrap()
+            .is_empty());
+
+        let system = system.with_clock(clock("2025-06-18 21:00"));
+        assert_eq!(
+            system.find_overdue_chambers_now(1, None).unwrap(),
+            vec![("1#".to_string(), 21 * 60)]
+        );
+        assert_eq!(
+            system.find_overdue_chambers_now(1, Some(12 * 60)).unwrap(),
+            vec![("1#".to_string(), 21 * 60), ("2#".to_string(), 15 * 60)]
+        );
+    }
+
+    #[test]
+    fn test_cycle_profile() {
+        let temp_db = NamedTempFile::new().unwrap();
+        let mut system = CokeOvenSystem::new(temp_db.path().to_str().unwrap()).unwrap();
+
+        system
+            .record_temperature(1, "2025-06-18 00:00", 1000.0, 1100.0)
+            .unwrap();
+        system
+            .record_temperature(1, "2025-06-18 04:00", 1040.0, 1140.0)
+            .unwrap();
+        system
+            .record_temperature(1, "2025-06-18 08:00", 1080.0, 1180.0)
+            .unwrap();
+        system
+            .record_operation(1, "1#", "LOAD", "2025-06-18 02:00")
+            .unwrap();
+        system
+            .record_operation(1, "1#", "PUSH", "2025-06-18 06:00")
+            .unwrap();
+
+        let points = system.cycle_profile(1, "1#", "2025-06-18 06:00").unwrap();
+        assert_eq!(points.len(), 3);
+
+        // 首尾为装煤、推焦时刻的插值点
+        let first = &points[0];
+        assert_eq!(
+            first.time,
+            crate::models::parse_time("2025-06-18 02:00").unwrap()
+        );
+        assert!((first.machine - 1020.0).abs() < 1e-9);
+        assert!((first.coke - 1120.0).abs() < 1e-9);
+        let last = &points[2];
+        assert_eq!(
+            last.time,
+            crate::models::parse_time("2025-06-18 06:00").unwrap()
+        );
+        assert!((last.machine - 1060.0).abs() < 1e-9);
+        assert!((last.coke - 1160.0).abs() < 1e-9);
+        assert_eq!(points[1].machine, 1040.0);
+
+        assert!(matches!(
+            system.cycle_profile(1, "1#", "2025-06-18 07:00"),
+            Err(CokeError::NoData(_))
+        ));
+        assert!(matches!(
+            system.cycle_profile(9, "1#", "2025-06-18 06:00"),
+            Err(CokeError::InvalidOven(9))
+        ));
+    }
+
+    #[test]
+    fn test_layout_rejects_malformed_chamber() {
+        let temp_db = NamedTempFile::new().unwrap();
+        let path = temp_db.path().to_str().unwrap();
+
+        let mut layout = HashMap::new();
+        layout.insert(1, CokeOven::new(&["1#", "1; DROP"]));
+        match CokeOvenSystem::new_with_layout(path, layout) {
+            Err(CokeError::InvalidChamber { oven, chamber }) => {
+                assert_eq!(oven, 1);
+                assert_eq!(chamber, "1; DROP");
+            }
+            _ => panic!("畸形炭化室名称应被拒绝"),
+        }
+
+        let mut layout = HashMap::new();
+        layout.insert(1, CokeOven::new(&["1#", "12#"]));
+        assert!(CokeOvenSystem::new_with_layout(path, layout).is_ok());
+    }
+
+    #[test]
+    fn test_process_pending_operations() {
+        let temp_db = NamedTempFile::new().unwrap();
+        let mut system = CokeOvenSystem::new(temp_db.path().to_str().unwrap()).unwrap();
+
+        system
+            .record_operation_deferred(1, "1#", "LOAD", "2025-06-18 00:00")
+            .unwrap();
+        system
+            .record_operation_deferred(1, "1#", "PUSH", "2025-06-18 20:00")
+            .unwrap();
+        system
+            .record_operation_deferred(1, "2#", "LOAD", "2025-06-18 01:00")
+            .unwrap();
+        assert_eq!(system.coking_cycle_count(1).unwrap(), 0);
+
+        assert_eq!(system.process_pending_operations().unwrap(), 3);
+        assert_eq!(system.coking_cycle_count(1).unwrap(), 1);
+
+        // 没有新操作时不做任何处理
+        assert_eq!(system.process_pending_operations().unwrap(), 0);
+        assert_eq!(system.coking_cycle_count(1).unwrap(), 1);
+
+        // 逐条记录已生成的周期不重复写入
+        system
+            .record_operation(1, "2#", "PUSH", "2025-06-18 21:00")
+            .unwrap();
+        assert_eq!(system.coking_cycle_count(1).unwrap(), 2);
+        system
+            .record_operation_deferred(1, "1#", "LOAD", "2025-06-18 22:00")
+            .unwrap();
+        system
+            .record_operation_deferred(1, "1#", "PUSH", "2025-06-19 18:00")
+            .unwrap();
+        assert_eq!(system.process_pending_operations().unwrap(), 3);
+        assert_eq!(system.coking_cycle_count(1).unwrap(), 3);
+        assert_eq!(system.process_pending_operations().unwrap(), 0);
+    }
+
+    #[test]
+    fn test_compare_ovens() {
+        let temp_db = NamedTempFile::new().unwrap();
+        let mut system = CokeOvenSystem::new(temp_db.path().to_str().unwrap()).unwrap();
+
+        for time in ["2025-06-18 00:00", "2025-06-18 12:00"] {
+            system.record_temperature(1, time, 1050.0, 1150.0).unwrap();
+            system.record_temperature(2, time, 1020.0, 1160.0).unwrap();
+        }
+
+        let cmp = system
+            .compare_ovens(1, 2, "2025-06-18 02:00", "2025-06-18 10:00")
+            .unwrap();
+        assert_eq!((cmp.oven_a, cmp.oven_b), (1, 2));
+        assert!((cmp.machine_a - 1050.0).abs() < 1e-9);
+        assert!((cmp.coke_b - 1160.0).abs() < 1e-9);
+        assert!((cmp.machine_delta - 30.0).abs() < 1e-9);
+        assert!((cmp.coke_delta + 10.0).abs() < 1e-9);
+
+        assert!(matches!(
+            system.compare_ovens(1, 9, "2025-06-18 02:00", "2025-06-18 10:00"),
+            Err(CokeError::InvalidOven(9))
+        ));
+        assert!(matches!(
+            system.compare_ovens(1, 3, "2025-06-18 02:00", "2025-06-18 10:00"),
+            Err(CokeError::NoData(_))
+        ));
+    }
+
+    #[test]
+    fn test_temperatures_in_range_bounds() {
+        let temp_db = NamedTempFile::new().unwrap();
+        let mut system = CokeOvenSystem::new(temp_db.path().to_str().unwrap()).unwrap();
+
+        for (time, t) in [
+            ("2025-06-18 08:00", 1000.0),
+            ("2025-06-18 09:00", 1010.0),
+            ("2025-06-18 10:00", 1020.0),
+        ] {
+            system.record_temperature(1, time, t, t).unwrap();
+        }
+
+        let machine = |bounds| -> Vec<f64> {
+            system
+                .temperatures_in_range(1, "2025-06-18 08:00", "2025-06-18 10:00", bounds)
+                .unwrap()
+                .iter()
+                .map(|r| r.machine_side)
+                .collect()
+        };
+        assert_eq!(
+            machine(IntervalBounds::Closed),
+            vec![1000.0, 1010.0, 1020.0]
+        );
+        assert_eq!(machine(IntervalBounds::Open), vec![1010.0]);
+        assert_eq!(machine(IntervalBounds::ClosedOpen), vec![1000.0, 1010.0]);
+        assert_eq!(machine(IntervalBounds::OpenClosed), vec![1010.0, 1020.0]);
+        assert_eq!(machine(IntervalBounds::default()).len(), 3);
+
+        assert!(matches!(
+            system.temperatures_in_range(
+                9,
+                "2025-06-18 08:00",
+                "2025-06-18 10:00",
+                IntervalBounds::Closed
+            ),
+            Err(CokeError::InvalidOven(9))
+        ));
+    }
+
+    #[test]
+    fn test_chambers_pushed_between() {
+        let temp_db = NamedTempFile::new().unwrap();
+        let mut system = CokeOvenSystem::new(temp_db.path().to_str().unwrap()).unwrap();
+
+        for (chamber, load, push) in [
+            ("12#", "2025-06-17 12:00", "2025-06-18 08:00"),
+            ("2#", "2025-06-17 13:00", "2025-06-18 09:00"),
+            ("2#", "2025-06-18 10:00", "2025-06-19 06:00"),
+            ("3#", "2025-06-17 00:00", "2025-06-17 20:00"),
+        ] {
+            system.record_operation(1, chamber, "LOAD", load).unwrap();
+            system.record_operation(1, chamber, "PUSH", push).unwrap();
+        }
+
+        assert_eq!(
+            system
+                .chambers_pushed_between(1, "2025-06-18 08:00", "2025-06-18 16:00")
+                .unwrap(),
+            vec!["2#", "12#"]
+        );
+        assert_eq!(
+            system
+                .chambers_pushed_between(1, "2025-06-17 00:00", "2025-06-20 00:00")
+                .unwrap(),
+            vec!["2#", "3#", "12#"]
+        );
+        assert!(system
+            .chambers_pushed_between(1, "2025-06-20 00:00", "2025-06-21 00:00")
+            .unwrap()
+            .is_empty());
+        assert!(system
+            .chambers_pushed_between(9, "2025-06-18 08:00", "2025-06-18 16:00")
+            .is_err());
+    }
+
+    #[test]
+    fn test_simpson_integration() {
+        // 机侧温度为时间的二次函数 1000 + 0.01·t²（t 为分钟），不等间距采样
+        let point = |minutes: i64| {
+            let t = minutes as f64;
+            TimeTempPoint {
+                time: crate::models::parse_time("2025-06-18 09:00").unwrap()
+                    + Duration::minutes(minutes),
+                machine: 1000.0 + 0.01 * t * t,
+                coke: 1100.0,
+            }
+        };
+        let points = vec![point(0), point(60), point(180)];
 
-        Ok(records)
-    }
-}
+        // 精确积分 1000·180 + 0.01·180³/3，平均 1108
+        let (m, c, d) = calculate_integral(&points, IntegrationMethod::Simpson);
+        assert_eq!(d, 180.0);
+        assert!((m / d - 1108.0).abs() < 1e-9);
+        assert!((c / d - 1100.0).abs() < 1e-9);
+        let (m, _, d) = calculate_integral(&points, IntegrationMethod::Trapezoidal);
+        assert!((m / d - 1126.0).abs() < 1e-9);
 
-// 计算积分面积和总时长
-fn calculate_integral(points: &[TimeTempPoint]) -> (f64, f64, f64) {
-    let mut total_machine = 0.0;
-    let mut total_coke = 0.0;
-    let mut total_duration = 0.0;
+        // 两个点时退化为梯形法
+        assert_eq!(
+            calculate_integral(&points[..2], IntegrationMethod::Simpson),
+            calculate_integral(&points[..2], IntegrationMethod::Trapezoidal)
+        );
 
-    for i in 0..points.len() - 1 {
-        let p1 = &points[i];
-        let p2 = &points[i + 1];
-        if p1.time == p2.time {
-            continue;
+        // 通过系统配置计算平均温度
+        let temp_db = NamedTempFile::new().unwrap();
+        let mut system = CokeOvenSystem::new(temp_db.path().to_str().unwrap()).unwrap();
+        for p in &points {
+            system
+                .record_temperature_dt(1, p.time, p.machine, p.coke)
+                .unwrap();
         }
-        let duration = (p2.time - p1.time).num_seconds() as f64 / 60.0;
-        total_machine += (p1.machine + p2.machine) * duration / 2.0;
-        total_coke += (p1.coke + p2.coke) * duration / 2.0;
-        total_duration += duration;
+        let (machine, _) = system
+            .calculate_avg_temperature(1, "2025-06-18 09:00:00", "2025-06-18 12:00:00")
+            .unwrap();
+        assert!((machine - 1126.0).abs() < 1e-9);
+
+        system.set_integration_method(IntegrationMethod::Simpson);
+        let (machine, coke) = system
+            .calculate_avg_temperature(1, "2025-06-18 09:00:00", "2025-06-18 12:00:00")
+            .unwrap();
+        assert!((machine - 1108.0).abs() < 1e-9);
+        assert!((coke - 1100.0).abs() < 1e-9);
     }
 
-    (total_machine, total_coke, total_duration)
-}
+    #[test]
+    fn test_cycle_notes() {
+        let temp_db = NamedTempFile::new().unwrap();
+        let mut system = CokeOvenSystem::new(temp_db.path().to_str().unwrap()).unwrap();
 
-// 辅助函数：分钟转 HH:mm
-fn minutes_to_hhmm(minutes: i32) -> String {
-    let hours = minutes / 60;
-    let minutes = minutes % 60;
-    format!("{:02}:{:02}", hours, minutes)
-}
+        system
+            .record_operation(1, "1#", "LOAD", "2025-06-18 00:00")
+            .unwrap();
+        system
+            .record_operation(1, "1#", "PUSH", "2025-06-18 20:00")
+            .unwrap();
+        let cycle_id = system.query_coking_cycles(1).unwrap()[0].id;
 
-// 测试代码
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use tempfile::NamedTempFile;
+        assert!(system.cycle_notes(cycle_id).unwrap().is_empty());
+        system.add_cycle_note(cycle_id, "疑似炉门泄漏").unwrap();
+        system.add_cycle_note(cycle_id, "已更换炉门密封").unwrap();
+        assert_eq!(
+            system.cycle_notes(cycle_id).unwrap(),
+            vec!["疑似炉门泄漏", "已更换炉门密封"]
+        );
 
-    // 创建测试临时数据库
-    fn setup_test_db() -> (NamedTempFile, CokeOvenSystem) {
+        assert!(matches!(
+            system.add_cycle_note(cycle_id + 100, "无效"),
+            Err(CokeError::NoData(_))
+        ));
+
+        // 周期重建后备注仍然保留（周期 id 可能改变）
+        system
+            .record_operation(1, "2#", "LOAD", "2025-06-18 01:00")
+            .unwrap();
+        system
+            .record_operation(1, "2#", "PUSH", "2025-06-18 21:00")
+            .unwrap();
+        system.rebuild_all_cycles(1).unwrap();
+        let rebuilt = system
+            .query_coking_cycles(1)
+            .unwrap()
+            .into_iter()
+            .find(|c| c.chamber == "1#")
+            .unwrap();
+        assert_eq!(
+            system.cycle_notes(rebuilt.id).unwrap(),
+            vec!["疑似炉门泄漏", "已更换炉门密封"]
+        );
+        let other = system
+            .query_coking_cycles(1)
+            .unwrap()
+            .into_iter()
+            .find(|c| c.chamber == "2#")
+            .unwrap();
+        assert!(system.cycle_notes(other.id).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_cycle_notes_migration() {
         let temp_db = NamedTempFile::new().unwrap();
         let db_path = temp_db.path().to_str().unwrap();
-        let system = CokeOvenSystem::new(db_path).unwrap();
-        (temp_db, system)
+
+        let cycle_id = {
+            let mut system = CokeOvenSystem::new(db_path).unwrap();
+            system
+                .record_operation(1, "1#", "LOAD", "2025-06-18 00:00")
+                .unwrap();
+            system
+                .record_operation(1, "1#", "PUSH", "2025-06-18 20:00")
+                .unwrap();
+            let cycle_id = system.query_coking_cycles(1).unwrap()[0].id;
+
+            // 旧版本结构：备注以周期 id 外键关联
+            system
+                .conn
+                .execute_batch(&format!(
+                    "DROP TABLE cycle_notes;
+                     CREATE TABLE cycle_notes (
+                         id INTEGER PRIMARY KEY,
+                         cycle_id INTEGER NOT NULL REFERENCES coking_cycles(id) ON DELETE CASCADE,
+                         note TEXT NOT NULL
+                     );
+                     INSERT INTO cycle_notes (cycle_id, note) VALUES ({}, '旧备注');",
+                    cycle_id
+                ))
+                .unwrap();
+            cycle_id
+        };
+
+        let mut system = CokeOvenSystem::new(db_path).unwrap();
+        assert_eq!(system.cycle_notes(cycle_id).unwrap(), vec!["旧备注"]);
+        system.rebuild_all_cycles(1).unwrap();
+        let cycle_id = system.query_coking_cycles(1).unwrap()[0].id;
+        assert_eq!(system.cycle_notes(cycle_id).unwrap(), vec!["旧备注"]);
     }
 
     #[test]
-    fn test_minutes_to_hhmm() {
-        assert_eq!(minutes_to_hhmm(0), "00:00");
-        assert_eq!(minutes_to_hhmm(59), "00:59");
-        assert_eq!(minutes_to_hhmm(60), "01:00");
-        assert_eq!(minutes_to_hhmm(119), "01:59");
-        assert_eq!(minutes_to_hhmm(120), "02:00");
-        assert_eq!(minutes_to_hhmm(1709), "28:29"); // 样本中正确的 28 小时 29 分钟
-        assert_eq!(minutes_to_hhmm(1724), "28:44"); // 样本中错误的 28 小时 44 分钟
+    fn test_close() {
+        let temp_db = NamedTempFile::new().unwrap();
+        let path = temp_db.path().to_str().unwrap();
+
+        let mut system = CokeOvenSystem::new(path).unwrap();
+        system
+            .record_temperature(1, "2025-06-18 08:00", 1000.0, 1100.0)
+            .unwrap();
+        assert!(system.close().is_ok());
+
+        // 检查点后数据已写入主库文件
+        let wal = format!("{}-wal", path);
+        assert!(std::fs::metadata(&wal).map_or(true, |m| m.len() == 0));
+        let system = CokeOvenSystem::new(path).unwrap();
+        assert_eq!(
+            system
+                .temperature_points(1, "2025-06-18 00:00", "2025-06-19 00:00")
+                .unwrap()
+                .len(),
+            1
+        );
     }
 
     #[test]
-    fn test_record_temperature() {
-        let (_temp_db, mut system) = setup_test_db();
+    fn test_close_blocked_checkpoint() {
+        let temp_db = NamedTempFile::new().unwrap();
+        let path = temp_db.path().to_str().unwrap();
 
-        // 有效记录
-        assert!(system
-            .record_temperature(1, "2025-06-18 08:00", 1350.0, 1360.0)
-            .is_ok());
+        let mut system = CokeOvenSystem::new(path).unwrap();
+        system.conn.busy_timeout(std::time::Duration::ZERO).unwrap();
+        system
+            .record_temperature(1, "2025-06-18 08:00", 1000.0, 1100.0)
+            .unwrap();
 
-        // 无效焦炉编号
-        assert!(system
-            .record_temperature(4, "2025-06-18 08:00", 1350.0, 1360.0)
-            .is_err());
+        // 另一连接持有旧快照的读事务，检查点无法截断 -wal 文件
+        let reader = Connection::open(path).unwrap();
+        reader.execute_batch("BEGIN").unwrap();
+        let _: i64 = reader
+            .query_row("SELECT COUNT(*) FROM temperature_records", [], |row| {
+                row.get(0)
+            })
+            .unwrap();
+        system
+            .record_temperature(1, "2025-06-18 09:00", 1000.0, 1100.0)
+            .unwrap();
 
-        // 无效时间格式
-        assert!(system
-            .record_temperature(1, "invalid_time", 1350.0, 1360.0)
-            .is_err());
+        assert!(matches!(system.close(), Err(CokeError::Conflict(_))));
+        reader.execute_batch("COMMIT").unwrap();
     }
 
     #[test]
-    fn test_record_operation() {
-        let (_temp_db, mut system) = setup_test_db();
-
-        // 有效装煤操作
-        assert!(system
-            .record_operation(1, "1#", "LOAD", "2025-06-18 08:00")
-            .is_ok());
+    fn test_flatline_intervals() {
+        let temp_db = NamedTempFile::new().unwrap();
+        let mut system = CokeOvenSystem::new(temp_db.path().to_str().unwrap()).unwrap();
 
-        // 有效推焦操作
-        assert!(system
-            .record_operation(1, "1#", "PUSH", "2025-06-19 12:45")
-            .is_ok());
+        // 焦侧 09:00–12:00 卡在 1150，机侧正常波动
+        for (time, machine, coke) in [
+            ("2025-06-18 08:00", 1000.0, 1140.0),
+            ("2025-06-18 09:00", 1010.0, 1150.0),
+            ("2025-06-18 10:00", 1020.0, 1150.0),
+            ("2025-06-18 11:00", 1015.0, 1150.0),
+            ("2025-06-18 12:00", 1005.0, 1150.0),
+            ("2025-06-18 13:00", 1005.0, 1160.0),
+        ] {
+            system.record_temperature(1, time, machine, coke).unwrap();
+        }
 
-        // 无效炭化室
+        let t = |s: &str| crate::models::parse_time(s).unwrap();
+        assert_eq!(
+            system
+                .flatline_intervals(1, 120, "2025-06-18 00:00", "2025-06-19 00:00")
+                .unwrap(),
+            vec![(t("2025-06-18 09:00"), t("2025-06-18 12:00"), "coke_side")]
+        );
+        assert_eq!(
+            system
+                .flatline_intervals(1, 60, "2025-06-18 00:00", "2025-06-19 00:00")
+                .unwrap(),
+            vec![
+                (t("2025-06-18 09:00"), t("2025-06-18 12:00"), "coke_side"),
+                (t("2025-06-18 12:00"), t("2025-06-18 13:00"), "machine_side"),
+            ]
+        );
         assert!(system
-            .record_operation(1, "999#", "LOAD", "2025-06-18 08:00")
-            .is_err());
-
-        // 无效操作类型
+            .flatline_intervals(1, 240, "2025-06-18 00:00", "2025-06-19 00:00")
+            .unwrap()
+            .is_empty());
         assert!(system
-            .record_operation(1, "1#", "INVALID", "2025-06-18 08:00")
+            .flatline_intervals(1, 0, "2025-06-18 00:00", "2025-06-19 00:00")
             .is_err());
     }
 
     #[test]
-    fn test_coking_cycle_calculation() {
-        let (_temp_db, mut system) = setup_test_db();
+    fn test_cycles_missing_averages() {
+        let temp_db = NamedTempFile::new().unwrap();
+        let mut system = CokeOvenSystem::new(temp_db.path().to_str().unwrap()).unwrap();
 
-        // 添加温度记录
+        // 没有任何温度记录，平均温度无法计算
         system
-            .record_temperature(1, "2025-06-18 08:00", 1350.0, 1360.0)
+            .record_operation(1, "1#", "LOAD", "2025-06-17 00:00")
             .unwrap();
         system
-            .record_temperature(1, "2025-06-19 12:00", 1400.0, 1410.0)
+            .record_operation(1, "1#", "PUSH", "2025-06-17 20:00")
             .unwrap();
+
         system
-            .record_temperature(1, "2025-06-19 13:00", 1420.0, 1430.0)
+            .record_temperature(1, "2025-06-18 00:00", 1000.0, 1100.0)
             .unwrap();
-
-        // 添加装煤和推焦操作
         system
-            .record_operation(1, "48#", "LOAD", "2025-06-18 08:16")
+            .record_temperature(1, "2025-06-18 20:00", 1040.0, 1140.0)
             .unwrap();
         system
-            .record_operation(1, "48#", "PUSH", "2025-06-19 12:45")
+            .record_operation(1, "2#", "LOAD", "2025-06-18 01:00")
             .unwrap();
-
-        // 检查结焦周期是否正确计算
-        let conn = &system.conn;
-        let mut stmt = conn
-            .prepare(
-                "SELECT loading_time, push_time, duration_hhmm
-            FROM coking_cycles
-            WHERE chamber = '48#'",
-            )
+        system
+            .record_operation(1, "2#", "PUSH", "2025-06-18 19:00")
             .unwrap();
 
-        let mut rows = stmt.query([]).unwrap();
-        let row = rows.next().unwrap().unwrap();
+        let missing = system.cycles_missing_averages(1).unwrap();
+        assert_eq!(missing.len(), 1);
+        assert_eq!(missing[0].chamber, "1#");
+        assert_eq!(missing[0].avg_temp_machine, None);
 
-        let loading_time: String = row.get(0).unwrap();
-        let push_time: String = row.get(1).unwrap();
-        let duration_hhmm: String = row.get(2).unwrap();
+        // 补录温度后重新计算即可修复
+        system
+            .record_temperature(1, "2025-06-16 23:00", 990.0, 1090.0)
+            .unwrap();
+        system.recompute_cycle_averages(1, "1#").unwrap();
+        assert!(system.cycles_missing_averages(1).unwrap().is_empty());
+        assert!(system.cycles_missing_averages(9).is_err());
+    }
 
-        assert_eq!(loading_time, "2025-06-18 08:16");
-        assert_eq!(push_time, "2025-06-19 12:45");
+    // 收集日志的测试记录器；日志记录器全局只能设置一次，由各测试共享
+    struct CapturingLogger(Mutex<Vec<(log::Level, String)>>);
 
-        // 验证时间差计算
-        let load_dt = crate::models::parse_time(&loading_time).unwrap();
-        let push_dt = crate::models::parse_time(&push_time).unwrap();
-        let duration = push_dt.signed_duration_since(load_dt);
-        let minutes = duration.num_minutes() as i32;
+    impl log::Log for CapturingLogger {
+        fn enabled(&self, _: &log::Metadata) -> bool {
+            true
+        }
 
-        // 计算预期值：28 小时 29 分钟
-        let expected_minutes = 28 * 60 + 29;
-        assert_eq!(
-            minutes, expected_minutes,
-            "实际分钟数：{}，预期分钟数：{}",
-            minutes, expected_minutes
-        );
+        fn log(&self, record: &log::Record) {
+            self.0
+                .lock()
+                .unwrap()
+                .push((record.level(), record.args().to_string()));
+        }
 
-        let expected_duration = minutes_to_hhmm(expected_minutes);
-        assert_eq!(
-            duration_hhmm, expected_duration,
-            "实际持续时间：{}，预期持续时间：{}",
-            duration_hhmm, expected_duration
-        )
+        fn flush(&self) {}
     }
 
+    static LOGGER: CapturingLogger = CapturingLogger(Mutex::new(Vec::new()));
+
     #[test]
-    fn test_get_nearest_temp_record() {
-        let (_temp_db, mut system) = setup_test_db();
+    fn test_average_failure_logs_warning() {
+        let _ = log::set_logger(&LOGGER);
+        log::set_max_level(log::LevelFilter::Warn);
 
-        // 添加温度记录
-        system
-            .record_temperature(1, "2025-06-18 08:00", 1350.0, 1360.0)
-            .unwrap();
+        let temp_db = NamedTempFile::new().unwrap();
+        let mut system = CokeOvenSystem::new(temp_db.path().to_str().unwrap()).unwrap();
+
+        // 没有温度记录，平均温度计算失败
         system
-            .record_temperature(1, "2025-06-18 10:00", 1360.0, 1370.0)
+            .record_operation(1, "1#", "LOAD", "2025-06-18 00:00")
             .unwrap();
         system
-            .record_temperature(1, "2025-06-18 12:00", 1370.0, 1380.0)
-            .unwrap();
-
-        // 测试在时间点之前的最近记录
-        let record = system
-            .get_nearest_temp_record(1, "2025-06-18 09:00", true)
-            .unwrap()
+            .record_operation(1, "1#", "PUSH", "2025-06-18 20:00")
             .unwrap();
-        assert_eq!(
-            record.time,
-            crate::models::parse_time("2025-06-18 08:00").unwrap()
-        );
 
-        // 测试在时间点之后的最近温度
-        let record = system
-            .get_nearest_temp_record(1, "2025-06-18 09:00", false)
-            .unwrap()
-            .unwrap();
-        assert_eq!(
-            record.time,
-            crate::models::parse_time("2025-06-18 10:00").unwrap()
-        );
+        let logs = LOGGER.0.lock().unwrap();
+        assert!(logs
+            .iter()
+            .any(|(level, msg)| *level == log::Level::Warn && msg.starts_with("计算平均温度失败")));
     }
 
     #[test]
-    fn test_calculate_avg_temperature() {
-        let (_temp_db, mut system) = setup_test_db();
+    fn test_estimated_push_time() {
+        let temp_db = NamedTempFile::new().unwrap();
+        let mut ovens = HashMap::new();
+        ovens.insert(
+            1,
+            CokeOven::new(&["1#", "2#"]).with_expected_cycle_minutes(19 * 60 + 30),
+        );
+        ovens.insert(2, CokeOven::new(&["3#"]));
+        let mut system =
+            CokeOvenSystem::new_with_layout(temp_db.path().to_str().unwrap(), ovens).unwrap();
+
+        assert_eq!(system.estimated_push_time(1, "1#").unwrap(), None);
 
-        // 添加温度记录
         system
-            .record_temperature(1, "2025-06-18 08:00", 100.0, 200.0)
+            .record_operation(1, "1#", "LOAD", "2025-06-18 06:00")
             .unwrap();
+        assert_eq!(
+            system.estimated_push_time(1, "1#").unwrap(),
+            Some(crate::models::parse_time("2025-06-19 01:30").unwrap())
+        );
+
         system
-            .record_temperature(1, "2025-06-18 10:00", 200.0, 300.0)
+            .record_operation(1, "1#", "PUSH", "2025-06-19 02:00")
             .unwrap();
+        assert_eq!(system.estimated_push_time(1, "1#").unwrap(), None);
+
         system
-            .record_temperature(1, "2025-06-18 12:00", 300.0, 400.0)
+            .record_operation(2, "3#", "LOAD", "2025-06-18 06:00")
             .unwrap();
+        assert!(matches!(
+            system.estimated_push_time(2, "3#"),
+            Err(CokeError::NoData(_))
+        ));
+        assert!(matches!(
+            system.estimated_push_time(1, "3#"),
+            Err(CokeError::InvalidChamber { .. })
+        ));
+    }
 
-        // 计算平均值
-        let (avg_machine, avg_coke) = system
-            .calculate_avg_temperature(1, "2025-06-18 09:00", "2025-06-18 11:00")
-            .unwrap();
+    #[test]
+    fn test_attach_combined_cycle_count() {
+        let main_db = NamedTempFile::new().unwrap();
+        let other_db = NamedTempFile::new().unwrap();
+        let other_path = other_db.path().to_str().unwrap();
 
-        // 预期平均值：梯形面积法计算
-        // 09:00-10:00: (150+200)/2 = 175 (机侧), (250+300)/2 = 275 (焦侧)
-        // 10:00-11:00: (200+250)/2 = 225 (机侧), (300+350)/2 = 325 (焦侧)
-        // 平均：(175+225)/2 = 200 (机侧), (275+325)/2 = 300 (焦侧)
-        assert!(
-            (avg_machine - 200.0).abs() < 0.1,
-            "机侧平均温度：{}",
-            avg_machine
-        );
-        assert!((avg_coke - 300.0).abs() < 0.1, "焦侧平均温度：{}", avg_coke);
+        let record_cycle = |system: &mut CokeOvenSystem, chamber: &str, day: u32| {
+            system
+                .record_operation(1, chamber, "LOAD", &format!("2025-06-{:02} 00:00", day))
+                .unwrap();
+            system
+                .record_operation(1, chamber, "PUSH", &format!("2025-06-{:02} 20:00", day))
+                .unwrap();
+        };
+
+        let mut other = CokeOvenSystem::new(other_path).unwrap();
+        for day in 10..13 {
+            record_cycle(&mut other, "2#", day);
+        }
+        other.close().unwrap();
+
+        let mut system = CokeOvenSystem::new(main_db.path().to_str().unwrap()).unwrap();
+        record_cycle(&mut system, "1#", 18);
+
+        system.attach("plant_b", other_path).unwrap();
+        assert_eq!(system.combined_cycle_count("plant_b").unwrap(), (1, 3));
+
+        system.detach("plant_b").unwrap();
+        assert!(matches!(
+            system.combined_cycle_count("plant_b"),
+            Err(CokeError::Db(_))
+        ));
+
+        for alias in [
+            "main",
+            "TEMP",
+            "",
+            "1db",
+            "b; DROP TABLE coking_cycles",
+            "a\"b",
+        ] {
+            assert!(matches!(
+                system.attach(alias, other_path),
+                Err(CokeError::InvalidArgument(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_import_binary_keeps_existing_cycles() {
+        let (_temp_db, mut system) = setup_test_db();
+        for (chamber, load, push) in [
+            ("1#", "2025-06-10 00:00", "2025-06-10 20:00"),
+            ("2#", "2025-06-18 00:00", "2025-06-18 20:00"),
+        ] {
+            system.record_operation(1, chamber, "LOAD", load).unwrap();
+            system.record_operation(1, chamber, "PUSH", push).unwrap();
+        }
+        let cycle_id = system.query_coking_cycles(1).unwrap()[1].id;
+        system.add_cycle_note(cycle_id, "备注").unwrap();
+
+        // 清理原始操作后保留的周期不应因导入而丢失
+        system.prune_before("2025-06-15 00:00").unwrap();
+        let mut data = Vec::new();
+        system.export_binary(&mut data).unwrap();
+        assert_eq!(system.import_binary(&mut data.as_slice()).unwrap(), (0, 0));
+        let cycles = system.query_coking_cycles(1).unwrap();
+        assert_eq!(cycles.len(), 2);
+        assert_eq!(cycles[1].id, cycle_id);
+        assert_eq!(system.cycle_notes(cycle_id).unwrap(), vec!["备注"]);
+
+        // 只为新导入的推焦计算周期，推焦先于装煤写入也能配对
+        let (_source_db, mut source) = setup_test_db();
+        source
+            .record_operation_deferred(1, "3#", "PUSH", "2025-06-19 20:00")
+            .unwrap();
+        source
+            .record_operation_deferred(1, "3#", "LOAD", "2025-06-19 00:00")
+            .unwrap();
+        let mut data = Vec::new();
+        source.export_binary(&mut data).unwrap();
+        assert_eq!(system.import_binary(&mut data.as_slice()).unwrap(), (0, 2));
+        let cycles = system.query_coking_cycles(1).unwrap();
+        assert_eq!(cycles.len(), 3);
+        assert_eq!(cycles[2].chamber, "3#");
+        assert_eq!(cycles[2].duration_minutes, 20 * 60);
+        assert_eq!(system.cycle_notes(cycle_id).unwrap(), vec!["备注"]);
     }
 }